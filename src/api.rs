@@ -0,0 +1,420 @@
+// Versioned REST/JSON API for scripts and other services.
+//
+// Callers authenticate with a personal API token from the Account page:
+// `Authorization: Bearer todo_...`. Reads need the `todos:read` scope and
+// writes `todos:write`. The handlers share their queries with the todo
+// server functions (`backend::todo_store`) and are described by the OpenAPI
+// document served at `/api/v1/openapi.json`.
+//
+// PATCH and DELETE take an optional `version`; if the todo has changed since
+// then the request fails with 409 instead of overwriting the other change.
+
+use axum::{
+    extract::{Path, Query, Request},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{NaiveDate, NaiveTime};
+use dioxus::prelude::{DioxusServerContext, ProvideServerContext, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::activity::ChangeSource;
+use crate::backend::api_tokens::{authenticate_api_token, ApiCaller, ApiScope};
+use crate::backend::server_utils::{get_db_pool, sqlx_err_to_server_err};
+use crate::backend::todo_store::{self, Stale, TodoRecord};
+use crate::backend::{NewTodo, Priority};
+use crate::telemetry::observe;
+use crate::validation;
+
+const OPENAPI_DOCUMENT: &str = include_str!("openapi.json");
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/v1/todos", get(list_todos).post(create_todo))
+        .route(
+            "/api/v1/todos/:id",
+            get(get_todo).patch(update_todo).delete(delete_todo),
+        )
+        .route("/api/v1/me", get(me))
+        .route("/api/v1/openapi.json", get(openapi))
+        .layer(middleware::from_fn(provide_server_context))
+}
+
+// Also the "todo" in webhook payloads
+#[derive(Serialize)]
+pub(crate) struct Todo {
+    id: i32,
+    content: String,
+    notes: String,
+    completed: bool,
+    version: i32,
+    list: Option<String>,
+    tags: Vec<String>,
+    due_date: Option<NaiveDate>,
+    due_time: Option<NaiveTime>,
+    priority: Option<Priority>,
+}
+
+impl From<TodoRecord> for Todo {
+    fn from(record: TodoRecord) -> Self {
+        Todo {
+            id: record.id,
+            content: record.content,
+            notes: record.notes,
+            completed: record.completed,
+            version: record.version,
+            list: record.list,
+            tags: record.tags,
+            due_date: record.due_date,
+            due_time: record.due_time,
+            priority: record.priority.and_then(Priority::from_i16),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Me {
+    username: String,
+    scopes: Vec<&'static str>,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct TodoChanges {
+    content: Option<String>,
+    notes: Option<String>,
+    completed: Option<bool>,
+    // Only apply if the todo is still at this version
+    version: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct DeleteParams {
+    version: Option<i32>,
+}
+
+enum ApiError {
+    Unauthorized,
+    MissingScope(ApiScope),
+    NotFound,
+    Conflict { current_version: i32 },
+    BadRequest(String),
+    Internal,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "Missing, invalid or revoked API token".to_string(),
+            ),
+            ApiError::MissingScope(scope) => (
+                StatusCode::FORBIDDEN,
+                format!("This token lacks the {} scope", scope.as_str()),
+            ),
+            ApiError::NotFound => (StatusCode::NOT_FOUND, "Todo not found".to_string()),
+            ApiError::Conflict { current_version } => (
+                StatusCode::CONFLICT,
+                format!(
+                    "Todo was changed in the meantime; it is now at version {}",
+                    current_version
+                ),
+            ),
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::Internal => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error".to_string(),
+            ),
+        };
+        let mut response = (status, Json(ErrorBody { error: message })).into_response();
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, "Bearer".parse().unwrap());
+        }
+        response
+    }
+}
+
+impl From<Stale> for ApiError {
+    fn from(stale: Stale) -> Self {
+        match stale {
+            Stale::Missing => ApiError::NotFound,
+            Stale::Changed { current_version } => ApiError::Conflict { current_version },
+        }
+    }
+}
+
+// Details were already logged by `sqlx_err_to_server_err` and `observe`
+impl From<ServerFnError> for ApiError {
+    fn from(_: ServerFnError) -> Self {
+        ApiError::Internal
+    }
+}
+
+// GET /api/v1/todos
+async fn list_todos(
+    headers: HeaderMap,
+    Query(params): Query<ListParams>,
+) -> Result<Json<Vec<Todo>>, ApiError> {
+    let caller = authenticate(&headers, ApiScope::TodosRead).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    let offset = params.offset.unwrap_or(0);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) || offset < 0 {
+        return Err(ApiError::BadRequest(format!(
+            "limit must be 1 to {} and offset at least 0",
+            MAX_PAGE_SIZE
+        )));
+    }
+
+    let todos = observe("api_list_todos", &caller.username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        todo_store::list(pool, caller.user_id, limit, offset).await
+    })
+    .await?;
+    Ok(Json(todos.into_iter().map(Todo::from).collect()))
+}
+
+// POST /api/v1/todos
+async fn create_todo(
+    headers: HeaderMap,
+    Json(body): Json<NewTodo>,
+) -> Result<(StatusCode, Json<Todo>), ApiError> {
+    let caller = authenticate(&headers, ApiScope::TodosWrite).await?;
+    let body = body.checked().map_err(ApiError::BadRequest)?;
+
+    let todo = observe("api_create_todo", &caller.username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        todo_store::insert(pool, caller.user_id, &body, ChangeSource::Api).await
+    })
+    .await?;
+    Ok((StatusCode::CREATED, Json(todo.into())))
+}
+
+// GET /api/v1/todos/{id}
+async fn get_todo(headers: HeaderMap, Path(id): Path<i32>) -> Result<Json<Todo>, ApiError> {
+    let caller = authenticate(&headers, ApiScope::TodosRead).await?;
+
+    let todo = observe("api_get_todo", &caller.username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        todo_store::get(pool, caller.user_id, id).await
+    })
+    .await?;
+    todo.map(|todo| Json(todo.into())).ok_or(ApiError::NotFound)
+}
+
+// PATCH /api/v1/todos/{id}
+async fn update_todo(
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Json(changes): Json<TodoChanges>,
+) -> Result<Json<Todo>, ApiError> {
+    let caller = authenticate(&headers, ApiScope::TodosWrite).await?;
+    if changes.content.is_none() && changes.notes.is_none() && changes.completed.is_none() {
+        return Err(ApiError::BadRequest("Nothing to update".to_string()));
+    }
+    if let Some(content) = &changes.content {
+        validation::validate_todo_content(content).map_err(ApiError::BadRequest)?;
+    }
+    if let Some(notes) = &changes.notes {
+        validation::validate_todo_notes(notes).map_err(ApiError::BadRequest)?;
+    }
+
+    let todo = observe("api_update_todo", &caller.username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        todo_store::update(
+            pool,
+            caller.user_id,
+            id,
+            todo_store::Changes {
+                content: changes.content.as_deref(),
+                notes: changes.notes.as_deref(),
+                completed: changes.completed,
+            },
+            changes.version,
+            ChangeSource::Api,
+        )
+        .await
+    })
+    .await??;
+    Ok(Json(todo.into()))
+}
+
+// DELETE /api/v1/todos/{id}
+async fn delete_todo(
+    headers: HeaderMap,
+    Path(id): Path<i32>,
+    Query(params): Query<DeleteParams>,
+) -> Result<StatusCode, ApiError> {
+    let caller = authenticate(&headers, ApiScope::TodosWrite).await?;
+
+    observe("api_delete_todo", &caller.username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        todo_store::delete(pool, caller.user_id, id, params.version, ChangeSource::Api).await
+    })
+    .await??;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// GET /api/v1/me: who the token belongs to, for clients checking a token
+async fn me(headers: HeaderMap) -> Result<Json<Me>, ApiError> {
+    let token = bearer_token(&headers)?;
+    let caller = authenticate_api_token(token)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+    Ok(Json(Me {
+        username: caller.username,
+        scopes: caller.scopes.iter().map(|scope| scope.as_str()).collect(),
+    }))
+}
+
+// GET /api/v1/openapi.json
+async fn openapi() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "application/json")],
+        OPENAPI_DOCUMENT,
+    )
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<&str, ApiError> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .ok_or(ApiError::Unauthorized)
+}
+
+async fn authenticate(headers: &HeaderMap, scope: ApiScope) -> Result<ApiCaller, ApiError> {
+    let token = bearer_token(headers)?;
+    let caller = authenticate_api_token(token)
+        .await?
+        .ok_or(ApiError::Unauthorized)?;
+    require_scope(caller, scope)
+}
+
+fn require_scope(caller: ApiCaller, scope: ApiScope) -> Result<ApiCaller, ApiError> {
+    if !caller.scopes.contains(&scope) {
+        return Err(ApiError::MissingScope(scope));
+    }
+    Ok(caller)
+}
+
+// Lets `observe` and friends read the request (request id, client IP) the
+// same way they do inside server functions
+async fn provide_server_context(request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+    let context = DioxusServerContext::new(parts.clone());
+    ProvideServerContext::new(next.run(Request::from_parts(parts, body)), context).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(authorization: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = authorization {
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn status_of<T>(result: Result<T, ApiError>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err(e) => e.into_response().status(),
+        }
+    }
+
+    fn caller(scopes: Vec<ApiScope>) -> ApiCaller {
+        ApiCaller {
+            user_id: 1,
+            username: "alice".to_string(),
+            scopes,
+        }
+    }
+
+    #[test]
+    fn reads_the_bearer_token() {
+        assert_eq!(
+            bearer_token(&headers(Some("Bearer todo_abc "))).ok(),
+            Some("todo_abc")
+        );
+        for authorization in [
+            None,
+            Some("Basic YWxpY2U6cHc="),
+            Some("bearer todo_abc"),
+            Some("todo_abc"),
+        ] {
+            assert_eq!(
+                status_of(bearer_token(&headers(authorization))),
+                StatusCode::UNAUTHORIZED,
+                "{:?}",
+                authorization
+            );
+        }
+    }
+
+    // None of these get as far as the database
+    #[tokio::test]
+    async fn rejects_missing_and_foreign_tokens() {
+        for authorization in [
+            None,
+            Some("Basic YWxpY2U6cHc="),
+            Some("Bearer "),
+            Some("Bearer ghp_abc123"),
+        ] {
+            let response = authenticate(&headers(authorization), ApiScope::TodosRead)
+                .await
+                .err()
+                .expect("should be rejected")
+                .into_response();
+            assert_eq!(
+                response.status(),
+                StatusCode::UNAUTHORIZED,
+                "{:?}",
+                authorization
+            );
+            assert_eq!(response.headers()[header::WWW_AUTHENTICATE], "Bearer");
+        }
+    }
+
+    #[test]
+    fn a_token_needs_the_scope_it_uses() {
+        assert_eq!(
+            status_of(require_scope(
+                caller(vec![ApiScope::TodosRead]),
+                ApiScope::TodosWrite
+            )),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status_of(require_scope(caller(vec![]), ApiScope::TodosRead)),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status_of(require_scope(
+                caller(ApiScope::ALL.to_vec()),
+                ApiScope::TodosWrite
+            )),
+            StatusCode::OK
+        );
+    }
+}
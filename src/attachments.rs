@@ -0,0 +1,404 @@
+// Attachment files: which uploads are accepted, thumbnails, the download
+// routes and cleanup. The server functions are in `backend::attachments`.
+//
+// Files live in `storage::blob_store()` under a random key, and
+// `/attachments/<key>` serves them to whoever has the link, like a private
+// share link: keys are 128 random bits and only ever shown to the owner.
+// Only images are shown inline; everything else downloads.
+
+use axum::{
+    body::Body,
+    extract::{Path, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use dioxus::prelude::server_fn::ServerFn;
+use image::{ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+use std::time::Duration;
+
+use crate::backend::attachments::UploadAttachment;
+use crate::backend::server_utils::get_db_pool;
+use crate::storage::blob_store;
+use crate::validation::ATTACHMENT_MAX_BYTES;
+
+// Uploads arrive base64-encoded in a server function call, so requests may
+// be a third larger than the largest file, plus the other arguments
+pub const MAX_REQUEST_BYTES: usize = ATTACHMENT_MAX_BYTES / 3 * 4 + 64 * 1024;
+
+const THUMBNAIL_SIZE: u32 = 160;
+// Larger images are stored but get no thumbnail
+const MAX_IMAGE_SIDE: u32 = 12_000;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+// By extension; the contents must match (see `content_matches`)
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("pdf", "application/pdf"),
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("zip", "application/zip"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    (
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    ),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+];
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/attachments/:key", get(download))
+        .route("/attachments/:key/thumbnail", get(download_thumbnail))
+}
+
+// Middleware holding `upload_attachment` calls to MAX_REQUEST_BYTES. Other
+// routes are left alone, so the REST API's extractors keep axum's default
+// limit instead of one sized for uploads.
+pub async fn limit_upload_body(request: Request, next: Next) -> Response {
+    if request.uri().path() != UploadAttachment::PATH {
+        return next.run(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    match axum::body::to_bytes(body, MAX_REQUEST_BYTES).await {
+        Ok(bytes) => {
+            next.run(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        }
+        Err(e) => {
+            tracing::info!(error = %e, "Upload request refused");
+            StatusCode::PAYLOAD_TOO_LARGE.into_response()
+        }
+    }
+}
+
+pub fn file_key(key: &str) -> String {
+    format!("files/{}", key)
+}
+
+pub fn thumbnail_key(key: &str) -> String {
+    format!("thumbnails/{}.png", key)
+}
+
+// The content type to store for an upload, or why it is refused. SVG and
+// HTML are never accepted since they can carry scripts.
+pub fn content_type(file_name: &str, data: &[u8]) -> Result<&'static str, String> {
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    let content_type = CONTENT_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, content_type)| *content_type)
+        .ok_or_else(|| {
+            format!(
+                "{} files can't be attached. Allowed: {}.",
+                if extension.is_empty() {
+                    "These".to_string()
+                } else {
+                    format!(".{}", extension)
+                },
+                CONTENT_TYPES
+                    .iter()
+                    .map(|(extension, _)| *extension)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+    if !content_matches(content_type, data) {
+        return Err(format!("{} is not a valid .{} file.", file_name, extension));
+    }
+    Ok(content_type)
+}
+
+fn content_matches(content_type: &str, data: &[u8]) -> bool {
+    match content_type {
+        "image/png" => data.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/jpeg" => data.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "image/gif" => data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a"),
+        "image/webp" => data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP",
+        "application/pdf" => data.starts_with(b"%PDF-"),
+        text if text.starts_with("text/") => std::str::from_utf8(data).is_ok(),
+        // The zip container and the office formats built on it
+        _ => data.starts_with(b"PK\x03\x04"),
+    }
+}
+
+pub fn is_image(content_type: &str) -> bool {
+    content_type.starts_with("image/")
+}
+
+// A PNG of at most THUMBNAIL_SIZE square, or None if the image can't be
+// decoded within the limits. CPU-bound: call from `spawn_blocking`.
+pub fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    let mut reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_SIDE);
+    limits.max_image_height = Some(MAX_IMAGE_SIDE);
+    reader.limits(limits);
+    let image = reader.decode().ok()?;
+
+    let mut png = Vec::new();
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .ok()?;
+    Some(png)
+}
+
+// GET /attachments/{key}
+async fn download(Path(key): Path<String>) -> Response {
+    let (file_name, content_type) = match lookup(&key).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(status) => return status.into_response(),
+    };
+    let disposition = if is_image(&content_type) {
+        "inline"
+    } else {
+        "attachment"
+    };
+    serve(
+        &file_key(&key),
+        content_type,
+        format!(
+            "{}; filename=\"{}\"",
+            disposition,
+            header_safe_name(&file_name)
+        ),
+    )
+    .await
+}
+
+// GET /attachments/{key}/thumbnail
+async fn download_thumbnail(Path(key): Path<String>) -> Response {
+    match lookup(&key).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(status) => return status.into_response(),
+    }
+    serve(
+        &thumbnail_key(&key),
+        "image/png".to_string(),
+        "inline".to_string(),
+    )
+    .await
+}
+
+// File name and content type of a live attachment
+async fn lookup(key: &str) -> Result<Option<(String, String)>, StatusCode> {
+    let pool = get_db_pool().await.map_err(database_error)?;
+    sqlx::query_as(
+        "SELECT file_name, content_type FROM attachments WHERE storage_key = $1 AND todo_id IS NOT NULL",
+    )
+    .bind(key)
+    .fetch_optional(pool)
+    .await
+    .map_err(database_error)
+}
+
+fn database_error(e: sqlx::Error) -> StatusCode {
+    tracing::error!(error = ?e, "SQLx error");
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn serve(blob_key: &str, content_type: String, disposition: String) -> Response {
+    match blob_store().get(blob_key).await {
+        Ok(Some(data)) => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CONTENT_DISPOSITION, disposition),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+                (header::CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+                (header::CACHE_CONTROL, "private, max-age=86400".to_string()),
+            ],
+            data,
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, blob_key, "Reading attachment failed");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// For the quoted filename in Content-Disposition
+fn header_safe_name(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || " ._-()".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// Deleting a todo or user keeps its attachment rows with `todo_id` NULL, so
+// their files can be removed here before the rows go
+pub async fn sweep_orphans() {
+    loop {
+        if let Err(e) = remove_orphans().await {
+            tracing::warn!(error = %e, "Removing orphaned attachments failed");
+        }
+        tokio::time::sleep(SWEEP_INTERVAL).await;
+    }
+}
+
+async fn remove_orphans() -> Result<(), crate::storage::StorageError> {
+    let pool = get_db_pool().await?;
+    let orphans: Vec<(i32, String, bool)> = sqlx::query_as(
+        "SELECT id, storage_key, has_thumbnail FROM attachments WHERE todo_id IS NULL LIMIT 500",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for (id, key, has_thumbnail) in orphans {
+        blob_store().delete(&file_key(&key)).await?;
+        if has_thumbnail {
+            blob_store().delete(&thumbnail_key(&key)).await?;
+        }
+        sqlx::query("DELETE FROM attachments WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        tracing::debug!(attachment_id = id, "Orphaned attachment removed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Bytes;
+    use axum::routing::post;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::new(width, height);
+        let mut data = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn content_type_follows_extension_and_contents() {
+        assert_eq!(content_type("photo.PNG", &png(1, 1)), Ok("image/png"));
+        assert_eq!(content_type("notes.txt", b"hello"), Ok("text/plain"));
+        assert_eq!(
+            content_type("report.pdf", b"%PDF-1.7"),
+            Ok("application/pdf")
+        );
+    }
+
+    #[test]
+    fn scriptable_and_unknown_types_are_refused() {
+        assert!(content_type("logo.svg", b"<svg></svg>").is_err());
+        assert!(content_type("page.html", b"<html></html>").is_err());
+        assert!(content_type("README", b"hello").is_err());
+    }
+
+    #[test]
+    fn contents_must_match_the_extension() {
+        assert!(content_type("photo.png", b"<script>").is_err());
+        assert!(content_type("notes.txt", &[0xFF, 0xFE, 0x00]).is_err());
+        assert!(content_type("sheet.xlsx", b"not a zip").is_err());
+    }
+
+    #[test]
+    fn thumbnails_fit_the_box() {
+        let thumbnail = make_thumbnail(&png(640, 320)).unwrap();
+        let image = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2)
+        );
+        assert_eq!(make_thumbnail(b"not an image"), None);
+    }
+
+    #[test]
+    fn header_names_are_quoted_safely() {
+        assert_eq!(header_safe_name("my report (1).pdf"), "my report (1).pdf");
+        assert_eq!(header_safe_name("a\"b\r\nc.txt"), "a_b__c.txt");
+    }
+
+    // The middleware in front of stand-in routes that report the body size:
+    // like server functions the upload route reads its body unbounded, the
+    // other route uses an extractor like the REST API does
+    async fn post_body(path: &str, size: usize) -> StatusCode {
+        let app = Router::new()
+            .route(
+                UploadAttachment::PATH,
+                post(|request: Request| async move {
+                    let body = axum::body::to_bytes(request.into_body(), usize::MAX).await;
+                    body.unwrap().len().to_string()
+                }),
+            )
+            .route(
+                "/other",
+                post(|body: Bytes| async move { body.len().to_string() }),
+            )
+            .layer(axum::middleware::from_fn(limit_upload_body));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        reqwest::Client::new()
+            .post(format!("http://{}{}", address, path))
+            .body(vec![b'a'; size])
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn uploads_may_use_the_raised_limit() {
+        // Over axum's 2 MB default, within MAX_REQUEST_BYTES
+        let size = 3 * 1024 * 1024;
+        assert!(size < MAX_REQUEST_BYTES);
+        assert_eq!(
+            post_body(UploadAttachment::PATH, size).await,
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_uploads_are_refused() {
+        assert_eq!(
+            post_body(UploadAttachment::PATH, MAX_REQUEST_BYTES + 1).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[tokio::test]
+    async fn other_routes_keep_the_default_limit() {
+        assert_eq!(
+            post_body("/other", 3 * 1024 * 1024).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
@@ -0,0 +1,411 @@
+// Append-only history of todo changes. `todo_store` writes one `todo_events`
+// row for every insert, update and delete, in the same transaction as the
+// change itself, so the history can't miss or invent a change. Events are
+// kept after the todo is deleted and only go away with the user.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::get_user_id;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use super::todo_store::TodoRecord;
+#[cfg(feature = "server")]
+use super::webhooks;
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+
+#[cfg(feature = "server")]
+const FEED_LIMIT: i64 = 50;
+#[cfg(feature = "server")]
+const HISTORY_LIMIT: i64 = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TodoAction {
+    Created,
+    Edited,
+    Completed,
+    Reopened,
+    Moved,
+    Tagged,
+    Untagged,
+    Rescheduled,
+    // Moved to another board column (see `board`)
+    StatusChanged,
+    Deleted,
+}
+
+impl TodoAction {
+    pub const ALL: [TodoAction; 10] = [
+        TodoAction::Created,
+        TodoAction::Edited,
+        TodoAction::Completed,
+        TodoAction::Reopened,
+        TodoAction::Moved,
+        TodoAction::Tagged,
+        TodoAction::Untagged,
+        TodoAction::Rescheduled,
+        TodoAction::StatusChanged,
+        TodoAction::Deleted,
+    ];
+
+    // Stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TodoAction::Created => "created",
+            TodoAction::Edited => "edited",
+            TodoAction::Completed => "completed",
+            TodoAction::Reopened => "reopened",
+            TodoAction::Moved => "moved",
+            TodoAction::Tagged => "tagged",
+            TodoAction::Untagged => "untagged",
+            TodoAction::Rescheduled => "rescheduled",
+            TodoAction::StatusChanged => "status_changed",
+            TodoAction::Deleted => "deleted",
+        }
+    }
+
+    pub fn parse(action: &str) -> Option<Self> {
+        TodoAction::ALL.into_iter().find(|a| a.as_str() == action)
+    }
+
+    // "alice added ...", "alice completed ..."
+    pub fn label(self) -> &'static str {
+        match self {
+            TodoAction::Created => "added",
+            TodoAction::Edited => "edited",
+            TodoAction::Completed => "completed",
+            TodoAction::Reopened => "reopened",
+            TodoAction::Moved => "moved",
+            TodoAction::Tagged => "tagged",
+            TodoAction::Untagged => "untagged",
+            TodoAction::Rescheduled => "rescheduled",
+            TodoAction::StatusChanged => "changed the status of",
+            TodoAction::Deleted => "deleted",
+        }
+    }
+}
+
+// Which client made a change
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeSource {
+    App,
+    Api,
+}
+
+impl ChangeSource {
+    pub const ALL: [ChangeSource; 2] = [ChangeSource::App, ChangeSource::Api];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeSource::App => "app",
+            ChangeSource::Api => "api",
+        }
+    }
+
+    pub fn parse(source: &str) -> Option<Self> {
+        ChangeSource::ALL.into_iter().find(|s| s.as_str() == source)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ChangeSource::App => "in the app",
+            ChangeSource::Api => "via the API",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TodoEvent {
+    pub id: i64,
+    pub todo_id: usize,
+    pub action: TodoAction,
+    // Username of whoever made the change
+    pub actor: String,
+    pub source: ChangeSource,
+    // The todo's text after the change (for deletes, what was deleted)
+    pub content: String,
+    pub version: i32,
+    pub at: String,
+}
+
+// Every change to one todo, newest first. Still works after it was deleted.
+#[server]
+pub async fn todo_history(username: String, id: usize) -> Result<Vec<TodoEvent>, ServerFnError> {
+    observe("todo_history", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let rows = sqlx::query_as(&format!(
+            "SELECT {} WHERE e.user_id = $1 AND e.todo_id = $2 ORDER BY e.id DESC LIMIT $3",
+            EVENT_QUERY
+        ))
+        .bind(user_id)
+        .bind(id as i32)
+        .bind(HISTORY_LIMIT)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(rows.into_iter().filter_map(todo_event).collect())
+    })
+    .await
+}
+
+// The latest changes across all of the user's todos, newest first
+#[server]
+pub async fn recent_activity(username: String) -> Result<Vec<TodoEvent>, ServerFnError> {
+    observe("recent_activity", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let rows = sqlx::query_as(&format!(
+            "SELECT {} WHERE e.user_id = $1 ORDER BY e.id DESC LIMIT $2",
+            EVENT_QUERY
+        ))
+        .bind(user_id)
+        .bind(FEED_LIMIT)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(rows.into_iter().filter_map(todo_event).collect())
+    })
+    .await
+}
+
+// Append the event for a change that `tx` has just made to `todo`, and queue
+// it for the user's webhooks. Todos are only ever changed by their owner
+// today, so owner and actor are the same; they are stored separately so
+// shared lists can tell them apart.
+#[cfg(feature = "server")]
+pub(crate) async fn record(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    todo: &TodoRecord,
+    action: TodoAction,
+    source: ChangeSource,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO todo_events
+            (todo_id, user_id, actor_id, action, source, content, completed, version)
+        VALUES ($1, $2, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(todo.id)
+    .bind(user_id)
+    .bind(action.as_str())
+    .bind(source.as_str())
+    .bind(&todo.content)
+    .bind(todo.completed)
+    .bind(todo.version)
+    .execute(&mut *tx)
+    .await?;
+    webhooks::enqueue(tx, user_id, todo, action, source).await
+}
+
+#[cfg(feature = "server")]
+const EVENT_QUERY: &str = r#"
+    e.id, e.todo_id, e.action, u.username, e.source, e.content, e.version,
+    to_char(e.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"')
+    FROM todo_events e JOIN users u ON u.id = e.actor_id
+"#;
+
+#[cfg(feature = "server")]
+type EventRow = (i64, i32, String, String, String, String, i32, String);
+
+// Rows with an action or source this build doesn't know are skipped
+#[cfg(feature = "server")]
+fn todo_event(
+    (id, todo_id, action, actor, source, content, version, at): EventRow,
+) -> Option<TodoEvent> {
+    Some(TodoEvent {
+        id,
+        todo_id: todo_id as usize,
+        action: TodoAction::parse(&action)?,
+        actor,
+        source: ChangeSource::parse(&source)?,
+        content,
+        version,
+        at,
+    })
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::backend::server_utils::{test_db_pool, test_user};
+    use crate::backend::{todo_store, NewTodo};
+    use sqlx::PgPool;
+
+    async fn add_webhook(pool: &PgPool, user_id: i32, events: &[&str]) -> i32 {
+        sqlx::query_scalar(
+            "INSERT INTO webhooks (user_id, url, secret, events) VALUES ($1, 'https://example.com/hook', 'secret', $2) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(events)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    // (webhook id, event, payload) of each queued delivery, oldest first
+    async fn deliveries(pool: &PgPool, user_id: i32) -> Vec<(i32, String, serde_json::Value)> {
+        let rows: Vec<(i32, String, String)> = sqlx::query_as(
+            r#"
+            SELECT d.webhook_id, d.event, d.payload
+            FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+            WHERE w.user_id = $1 ORDER BY d.id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        rows.into_iter()
+            .map(|(id, event, payload)| (id, event, serde_json::from_str(&payload).unwrap()))
+            .collect()
+    }
+
+    async fn record_change(pool: &PgPool, user_id: i32, todo: &TodoRecord, action: TodoAction) {
+        let mut tx = pool.begin().await.unwrap();
+        record(&mut tx, user_id, todo, action, ChangeSource::Api)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn records_the_event_with_the_todo_as_it_is_now() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, username) = test_user(&pool).await;
+        let mut todo = todo_store::insert(
+            &pool,
+            user_id,
+            &NewTodo::plain("Buy milk".to_string()),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+        todo.completed = true;
+        todo.version += 1;
+        record_change(&pool, user_id, &todo, TodoAction::Completed).await;
+
+        let rows: Vec<EventRow> = sqlx::query_as(&format!(
+            "SELECT {} WHERE e.todo_id = $1 ORDER BY e.id",
+            EVENT_QUERY
+        ))
+        .bind(todo.id)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let events: Vec<TodoEvent> = rows.into_iter().filter_map(todo_event).collect();
+        let summary: Vec<(TodoAction, ChangeSource, i32)> = events
+            .iter()
+            .map(|event| (event.action, event.source, event.version))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (TodoAction::Created, ChangeSource::App, 1),
+                (TodoAction::Completed, ChangeSource::Api, 2)
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|event| event.actor == username && event.content == "Buy milk"));
+    }
+
+    #[tokio::test]
+    async fn queues_deliveries_for_webhooks_that_want_the_event() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let completions = add_webhook(&pool, user_id, &["todo.completed"]).await;
+        let everything = add_webhook(
+            &pool,
+            user_id,
+            &[
+                "todo.created",
+                "todo.completed",
+                "todo.updated",
+                "todo.deleted",
+            ],
+        )
+        .await;
+        // Someone else's webhook never sees this user's changes
+        let (other_user, _) = test_user(&pool).await;
+        add_webhook(&pool, other_user, &["todo.created", "todo.completed"]).await;
+
+        let todo = todo_store::insert(
+            &pool,
+            user_id,
+            &NewTodo::plain("Buy milk".to_string()),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+        record_change(&pool, user_id, &todo, TodoAction::Completed).await;
+        // Reopening is sent as an update
+        record_change(&pool, user_id, &todo, TodoAction::Reopened).await;
+
+        let queued: Vec<(i32, String)> = deliveries(&pool, user_id)
+            .await
+            .into_iter()
+            .map(|(id, event, _)| (id, event))
+            .collect();
+        assert_eq!(
+            queued,
+            vec![
+                (everything, "todo.created".to_string()),
+                (completions, "todo.completed".to_string()),
+                (everything, "todo.completed".to_string()),
+                (everything, "todo.updated".to_string()),
+            ]
+        );
+        assert!(deliveries(&pool, other_user).await.is_empty());
+
+        let (_, _, payload) = &deliveries(&pool, user_id).await[1];
+        assert_eq!(payload["event"], "todo.completed");
+        assert_eq!(payload["source"], "api");
+        assert_eq!(payload["todo"]["id"], todo.id);
+        assert_eq!(payload["todo"]["content"], "Buy milk");
+    }
+
+    #[tokio::test]
+    async fn nothing_is_queued_if_the_change_rolls_back() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        add_webhook(&pool, user_id, &["todo.deleted"]).await;
+        let todo = todo_store::insert(
+            &pool,
+            user_id,
+            &NewTodo::plain("Buy milk".to_string()),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+
+        let mut tx = pool.begin().await.unwrap();
+        record(
+            &mut tx,
+            user_id,
+            &todo,
+            TodoAction::Deleted,
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+        tx.rollback().await.unwrap();
+
+        assert!(deliveries(&pool, user_id).await.is_empty());
+    }
+}
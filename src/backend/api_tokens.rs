@@ -0,0 +1,259 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::reauthenticate;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use crate::secrets;
+#[cfg(feature = "server")]
+use crate::telemetry::{self, observe};
+
+// Prefix on every token so they are easy to recognise (and to grep for in leaks)
+#[cfg(feature = "server")]
+const TOKEN_PREFIX: &str = "todo_";
+#[cfg(feature = "server")]
+const MAX_ACTIVE_TOKENS: i64 = 20;
+#[cfg(feature = "server")]
+const TOKEN_NAME_MAX_LEN: usize = 64;
+
+// What a token may do in the REST API
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiScope {
+    TodosRead,
+    TodosWrite,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 2] = [ApiScope::TodosRead, ApiScope::TodosWrite];
+
+    // Stored in the database and shown in API errors
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiScope::TodosRead => "todos:read",
+            ApiScope::TodosWrite => "todos:write",
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn parse(scope: &str) -> Option<Self> {
+        ApiScope::ALL.into_iter().find(|s| s.as_str() == scope)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ApiScope::TodosRead => "Read todos",
+            ApiScope::TodosWrite => "Create, edit and delete todos",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApiTokenInfo {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
+// Returned once on creation; only the hash is kept
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewApiToken {
+    pub info: ApiTokenInfo,
+    pub token: String,
+}
+
+// The owner of a valid token, for the REST API
+#[cfg(feature = "server")]
+pub struct ApiCaller {
+    pub user_id: i32,
+    pub username: String,
+    pub scopes: Vec<ApiScope>,
+}
+
+// Creating a token needs the password, like the other account settings
+#[server]
+pub async fn create_api_token(
+    username: String,
+    password: String,
+    name: String,
+    scopes: Vec<ApiScope>,
+) -> Result<NewApiToken, ServerFnError> {
+    observe("create_api_token", &username, async {
+        let name = name.trim().to_string();
+        if name.is_empty() || name.chars().count() > TOKEN_NAME_MAX_LEN {
+            return Err(ServerFnError::ServerError(format!(
+                "Token name must be 1 to {} characters long",
+                TOKEN_NAME_MAX_LEN
+            )));
+        }
+        if scopes.is_empty() {
+            return Err(ServerFnError::ServerError(
+                "Pick at least one scope".to_string(),
+            ));
+        }
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let active: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM api_tokens WHERE user_id = $1 AND revoked_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        if active >= MAX_ACTIVE_TOKENS {
+            return Err(ServerFnError::ServerError(format!(
+                "You can have at most {} active tokens; revoke one first",
+                MAX_ACTIVE_TOKENS
+            )));
+        }
+
+        let token = format!("{}{}", TOKEN_PREFIX, secrets::generate_token());
+        let scope_names: Vec<String> = ApiScope::ALL
+            .into_iter()
+            .filter(|scope| scopes.contains(scope))
+            .map(|scope| scope.as_str().to_string())
+            .collect();
+
+        let row: TokenInfoRow = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO api_tokens (user_id, name, token_hash, scopes)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+            "#,
+            TOKEN_INFO_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(&name)
+        .bind(secrets::hash_token(&token))
+        .bind(&scope_names)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        tracing::info!(token_id = row.0, "API token created");
+        Ok(NewApiToken {
+            info: token_info(row),
+            token,
+        })
+    })
+    .await
+}
+
+// Active tokens, newest first. Needs the password too, since knowing the
+// username alone mustn't reveal (or let anyone revoke) a user's tokens.
+#[server]
+pub async fn list_api_tokens(
+    username: String,
+    password: String,
+) -> Result<Vec<ApiTokenInfo>, ServerFnError> {
+    observe("list_api_tokens", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let rows: Vec<TokenInfoRow> = sqlx::query_as(&format!(
+            r#"
+                SELECT {}
+                FROM api_tokens
+                WHERE user_id = $1 AND revoked_at IS NULL
+                ORDER BY id DESC
+                "#,
+            TOKEN_INFO_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(rows.into_iter().map(token_info).collect())
+    })
+    .await
+}
+
+#[server]
+pub async fn revoke_api_token(
+    username: String,
+    password: String,
+    id: i32,
+) -> Result<(), ServerFnError> {
+    observe("revoke_api_token", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let result = sqlx::query(
+            "UPDATE api_tokens SET revoked_at = now() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServerFnError::ServerError("Token not found".to_string()));
+        }
+        tracing::info!(token_id = id, "API token revoked");
+        Ok(())
+    })
+    .await
+}
+
+// Look up the caller for a bearer token. None for unknown or revoked tokens.
+#[cfg(feature = "server")]
+pub(crate) async fn authenticate_api_token(
+    token: &str,
+) -> Result<Option<ApiCaller>, ServerFnError> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+
+    let row: Option<(i32, String, Vec<String>)> = sqlx::query_as(
+        r#"
+        UPDATE api_tokens t
+        SET last_used_at = now()
+        FROM users u
+        WHERE t.token_hash = $1 AND t.revoked_at IS NULL AND u.id = t.user_id
+        RETURNING u.id, u.username, t.scopes
+        "#,
+    )
+    .bind(secrets::hash_token(token))
+    .fetch_optional(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    Ok(row.map(|(user_id, username, scopes)| {
+        telemetry::record_user_id(user_id);
+        ApiCaller {
+            user_id,
+            username,
+            scopes: scopes.iter().filter_map(|s| ApiScope::parse(s)).collect(),
+        }
+    }))
+}
+
+#[cfg(feature = "server")]
+const TOKEN_INFO_COLUMNS: &str = r#"
+    id, name, scopes,
+    to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"'),
+    to_char(last_used_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"')
+"#;
+
+// A row selected with `TOKEN_INFO_COLUMNS`
+#[cfg(feature = "server")]
+type TokenInfoRow = (i32, String, Vec<String>, String, Option<String>);
+
+#[cfg(feature = "server")]
+fn token_info((id, name, scopes, created_at, last_used_at): TokenInfoRow) -> ApiTokenInfo {
+    ApiTokenInfo {
+        id,
+        name,
+        scopes: scopes.iter().filter_map(|s| ApiScope::parse(s)).collect(),
+        created_at,
+        last_used_at,
+    }
+}
@@ -0,0 +1,259 @@
+// Files attached to todos. Uploads go through `upload_attachment` like every
+// other change, so they need the same login as the rest of the app; the
+// files themselves are served by the routes in `crate::attachments`.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use super::{get_user_id, todo_store, TodoItem};
+#[cfg(feature = "server")]
+use crate::attachments::{content_type, file_key, is_image, make_thumbnail, thumbnail_key};
+#[cfg(feature = "server")]
+use crate::storage::blob_store;
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+#[cfg(feature = "server")]
+use crate::validation;
+#[cfg(feature = "server")]
+use server_fn::error::NoCustomError;
+#[cfg(feature = "server")]
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow))]
+pub struct Attachment {
+    pub id: i32,
+    pub todo_id: i32,
+    // Random; the only thing the download URLs contain
+    pub storage_key: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub has_thumbnail: bool,
+}
+
+impl Attachment {
+    pub fn url(&self) -> String {
+        format!("/attachments/{}", self.storage_key)
+    }
+
+    pub fn thumbnail_url(&self) -> Option<String> {
+        self.has_thumbnail
+            .then(|| format!("/attachments/{}/thumbnail", self.storage_key))
+    }
+
+    // "2.4 MB", "310 KB"
+    pub fn size_label(&self) -> String {
+        let size = self.size_bytes as f64;
+        if size >= 1024.0 * 1024.0 {
+            format!("{:.1} MB", size / (1024.0 * 1024.0))
+        } else {
+            format!("{} KB", (size / 1024.0).ceil() as i64)
+        }
+    }
+}
+
+// `data` is the file's bytes in URL-safe base64 without padding (see
+// `encode_upload`), which survives the form encoding of server function
+// arguments without growing further
+#[server]
+pub async fn upload_attachment(
+    username: String,
+    todo_id: usize,
+    file_name: String,
+    data: String,
+) -> Result<Attachment, ServerFnError> {
+    observe("upload_attachment", &username, async {
+        use base64::Engine;
+
+        let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(data.as_bytes())
+            .map_err(|_| {
+                ServerFnError::<NoCustomError>::ServerError("The upload was damaged.".to_string())
+            })?;
+        let file_name = file_name.trim().to_string();
+        validation::validate_attachment(&file_name, data.len())
+            .map_err(ServerFnError::<NoCustomError>::ServerError)?;
+        let content_type =
+            content_type(&file_name, &data).map_err(ServerFnError::<NoCustomError>::ServerError)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+        todo_store::get(pool, user_id, todo_id as i32)
+            .await?
+            .ok_or_else(|| {
+                ServerFnError::<NoCustomError>::ServerError("Todo not found".to_string())
+            })?;
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM attachments WHERE todo_id = $1")
+            .bind(todo_id as i32)
+            .fetch_one(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+        if count >= validation::ATTACHMENTS_PER_TODO as i64 {
+            return Err(ServerFnError::ServerError(format!(
+                "A todo can have at most {} attachments.",
+                validation::ATTACHMENTS_PER_TODO
+            )));
+        }
+
+        let thumbnail = if is_image(content_type) {
+            let image = data.clone();
+            tokio::task::spawn_blocking(move || make_thumbnail(&image))
+                .await
+                .ok()
+                .flatten()
+        } else {
+            None
+        };
+
+        let key = uuid::Uuid::new_v4().simple().to_string();
+        let size_bytes = data.len() as i64;
+        let has_thumbnail = thumbnail.is_some();
+        if let Err(e) = store_blobs(&key, data, content_type, thumbnail).await {
+            delete_blobs(&key, has_thumbnail).await;
+            return Err(e);
+        }
+
+        let inserted = sqlx::query_as(
+            r#"
+            INSERT INTO attachments
+                (todo_id, user_id, storage_key, file_name, content_type, size_bytes, has_thumbnail)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, todo_id, storage_key, file_name, content_type, size_bytes, has_thumbnail
+            "#,
+        )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .bind(&key)
+        .bind(&file_name)
+        .bind(content_type)
+        .bind(size_bytes)
+        .bind(has_thumbnail)
+        .fetch_one(pool)
+        .await;
+        // No row points at the files, so nothing else would remove them
+        let attachment: Attachment = match inserted {
+            Ok(attachment) => attachment,
+            Err(e) => {
+                delete_blobs(&key, has_thumbnail).await;
+                return Err(sqlx_err_to_server_err(e));
+            }
+        };
+
+        tracing::info!(
+            todo_id,
+            attachment_id = attachment.id,
+            size_bytes,
+            content_type,
+            "Attachment uploaded"
+        );
+        Ok(attachment)
+    })
+    .await
+}
+
+#[server]
+pub async fn delete_attachment(username: String, id: i32) -> Result<(), ServerFnError> {
+    observe("delete_attachment", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        // Scoped through the todo, like every other todo change
+        let deleted: Option<(String, bool)> = sqlx::query_as(
+            r#"
+            DELETE FROM attachments a
+            USING todos t
+            WHERE a.id = $1 AND a.todo_id = t.id AND t.user_id = $2
+            RETURNING a.storage_key, a.has_thumbnail
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        let (key, has_thumbnail) = deleted.ok_or_else(|| {
+            ServerFnError::<NoCustomError>::ServerError("Attachment not found".to_string())
+        })?;
+
+        delete_blobs(&key, has_thumbnail).await;
+        tracing::info!(attachment_id = id, "Attachment deleted");
+        Ok(())
+    })
+    .await
+}
+
+// The bytes of a picked file as `upload_attachment` expects them
+pub fn encode_upload(data: &[u8]) -> String {
+    use base64::Engine;
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data)
+}
+
+// Fills in `attachments` for todos of one user, oldest attachment first
+#[cfg(feature = "server")]
+pub(crate) async fn load_for(pool: &PgPool, todos: &mut [TodoItem]) -> Result<(), ServerFnError> {
+    let ids: Vec<i32> = todos.iter().map(|todo| todo.id as i32).collect();
+    let attachments: Vec<Attachment> = sqlx::query_as(
+        r#"
+        SELECT id, todo_id, storage_key, file_name, content_type, size_bytes, has_thumbnail
+        FROM attachments
+        WHERE todo_id = ANY($1)
+        ORDER BY id
+        "#,
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    for attachment in attachments {
+        if let Some(todo) = todos
+            .iter_mut()
+            .find(|todo| todo.id as i32 == attachment.todo_id)
+        {
+            todo.attachments.push(attachment);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+async fn store_blobs(
+    key: &str,
+    data: Vec<u8>,
+    content_type: &str,
+    thumbnail: Option<Vec<u8>>,
+) -> Result<(), ServerFnError> {
+    let stored = async {
+        blob_store().put(&file_key(key), data, content_type).await?;
+        if let Some(thumbnail) = thumbnail {
+            blob_store()
+                .put(&thumbnail_key(key), thumbnail, "image/png")
+                .await?;
+        }
+        Ok::<_, crate::storage::StorageError>(())
+    }
+    .await;
+    stored.map_err(|e| {
+        tracing::error!(error = %e, "Storing attachment failed");
+        ServerFnError::ServerError("The file could not be stored. Please try again.".to_string())
+    })
+}
+
+// Best effort: a blob left behind only costs space
+#[cfg(feature = "server")]
+async fn delete_blobs(key: &str, has_thumbnail: bool) {
+    let mut blob_keys = vec![file_key(key)];
+    if has_thumbnail {
+        blob_keys.push(thumbnail_key(key));
+    }
+    for blob_key in blob_keys {
+        if let Err(e) = blob_store().delete(&blob_key).await {
+            tracing::warn!(error = %e, blob_key, "Deleting attachment blob failed");
+        }
+    }
+}
@@ -0,0 +1,510 @@
+// The board view: user-defined status columns (Backlog / Doing / Done...)
+// and moving todos between them. A todo's column is its `status_id`; todos
+// without one are shown under "No status". Columns with `marks_done`
+// complete the todos moved into them, see `todo_store::set_status`.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::activity::ChangeSource;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+use super::TodoItem;
+#[cfg(feature = "server")]
+use super::{attachments, get_user_id, todo_store};
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+
+pub const COLUMN_NAME_MAX_LEN: usize = 40;
+#[cfg(feature = "server")]
+const MAX_COLUMNS: i64 = 12;
+// Most todos the board shows, newest first
+#[cfg(feature = "server")]
+const BOARD_LIMIT: i64 = 500;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow))]
+pub struct BoardColumn {
+    pub id: i32,
+    pub name: String,
+    // Moving a todo here marks it done
+    pub marks_done: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Board {
+    // Left to right
+    pub columns: Vec<BoardColumn>,
+    pub todos: Vec<TodoItem>,
+}
+
+#[server]
+pub async fn get_board(username: String) -> Result<Board, ServerFnError> {
+    observe("get_board", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let columns = columns(pool, user_id).await?;
+        let rows = todo_store::list(pool, user_id, BOARD_LIMIT, 0).await?;
+        let mut todos: Vec<TodoItem> = rows.into_iter().map(TodoItem::from).collect();
+        attachments::load_for(pool, &mut todos).await?;
+        Ok(Board { columns, todos })
+    })
+    .await
+}
+
+// Added on the right
+#[server]
+pub async fn create_board_column(
+    username: String,
+    name: String,
+    marks_done: bool,
+) -> Result<BoardColumn, ServerFnError> {
+    observe("create_board_column", &username, async {
+        let name = check_name(&name)?;
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM board_columns WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_one(pool)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+        if count >= MAX_COLUMNS {
+            return Err(ServerFnError::ServerError(format!(
+                "A board can have at most {} columns",
+                MAX_COLUMNS
+            )));
+        }
+        check_name_free(pool, user_id, &name, None).await?;
+
+        let column: BoardColumn = sqlx::query_as(
+            r#"
+            INSERT INTO board_columns (user_id, name, marks_done, position)
+            SELECT $1, $2, $3, COALESCE(MAX(position), 0) + 1
+            FROM board_columns WHERE user_id = $1
+            RETURNING id, name, marks_done
+            "#,
+        )
+        .bind(user_id)
+        .bind(&name)
+        .bind(marks_done)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        tracing::debug!(column_id = column.id, "Board column created");
+        Ok(column)
+    })
+    .await
+}
+
+// Renames a column or changes whether it marks todos done. Todos already in
+// it keep their completion.
+#[server]
+pub async fn update_board_column(
+    username: String,
+    id: i32,
+    name: String,
+    marks_done: bool,
+) -> Result<BoardColumn, ServerFnError> {
+    observe("update_board_column", &username, async {
+        let name = check_name(&name)?;
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+        check_name_free(pool, user_id, &name, Some(id)).await?;
+
+        let column: Option<BoardColumn> = sqlx::query_as(
+            r#"
+            UPDATE board_columns SET name = $3, marks_done = $4
+            WHERE id = $1 AND user_id = $2
+            RETURNING id, name, marks_done
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(&name)
+        .bind(marks_done)
+        .fetch_optional(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        column.ok_or_else(not_found)
+    })
+    .await
+}
+
+// `ids` is every one of the user's columns, in their new order
+#[server]
+pub async fn reorder_board_columns(username: String, ids: Vec<i32>) -> Result<(), ServerFnError> {
+    observe("reorder_board_columns", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM board_columns WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_one(&mut tx)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+        let result = sqlx::query(
+            r#"
+            UPDATE board_columns c SET position = o.position
+            FROM unnest($2::INTEGER[]) WITH ORDINALITY AS o(id, position)
+            WHERE c.id = o.id AND c.user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(&ids)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        // A column missing, repeated or added meanwhile: leave the order alone
+        if ids.len() as i64 != count || result.rows_affected() != ids.len() as u64 {
+            tx.rollback().await.map_err(sqlx_err_to_server_err)?;
+            return Err(ServerFnError::ServerError(
+                "The columns have changed; reload the board and try again".to_string(),
+            ));
+        }
+        tx.commit().await.map_err(sqlx_err_to_server_err)?;
+        Ok(())
+    })
+    .await
+}
+
+// Its todos go back to "No status" and keep their completion, see
+// `todo_store::delete_status`
+#[server]
+pub async fn delete_board_column(username: String, id: i32) -> Result<(), ServerFnError> {
+    observe("delete_board_column", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        if !todo_store::delete_status(pool, user_id, id, ChangeSource::App).await? {
+            return Err(not_found());
+        }
+        tracing::debug!(column_id = id, "Board column deleted");
+        Ok(())
+    })
+    .await
+}
+
+// Puts a todo in a column (None: "No status"). Fails with
+// `AppError::Conflict` like `update_todo`.
+#[server]
+pub async fn move_todo_to_column(
+    username: String,
+    id: usize,
+    expected_version: i32,
+    column_id: Option<i32>,
+) -> Result<TodoItem, ServerFnError> {
+    observe("move_todo_to_column", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let todo = todo_store::set_status(
+            pool,
+            user_id,
+            id as i32,
+            column_id,
+            Some(expected_version),
+            ChangeSource::App,
+        )
+        .await?
+        .map_err(todo_store::Stale::into_server_error)?;
+
+        tracing::debug!(todo_id = id, ?column_id, "Todo moved on the board");
+        let mut todo = TodoItem::from(todo);
+        attachments::load_for(pool, std::slice::from_mut(&mut todo)).await?;
+        Ok(todo)
+    })
+    .await
+}
+
+#[cfg(feature = "server")]
+async fn columns(pool: &sqlx::PgPool, user_id: i32) -> Result<Vec<BoardColumn>, ServerFnError> {
+    sqlx::query_as(
+        "SELECT id, name, marks_done FROM board_columns WHERE user_id = $1 ORDER BY position, id",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)
+}
+
+#[cfg(feature = "server")]
+fn check_name(name: &str) -> Result<String, ServerFnError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > COLUMN_NAME_MAX_LEN {
+        return Err(ServerFnError::ServerError(format!(
+            "Column names must be 1 to {} characters long",
+            COLUMN_NAME_MAX_LEN
+        )));
+    }
+    Ok(name.to_string())
+}
+
+// Names are unique per user, ignoring case; `except` is the column being
+// renamed
+#[cfg(feature = "server")]
+async fn check_name_free(
+    pool: &sqlx::PgPool,
+    user_id: i32,
+    name: &str,
+    except: Option<i32>,
+) -> Result<(), ServerFnError> {
+    let taken: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM board_columns
+            WHERE user_id = $1 AND lower(name) = lower($2) AND id IS DISTINCT FROM $3
+        )
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(except)
+    .fetch_one(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    if taken {
+        return Err(ServerFnError::ServerError(format!(
+            "There is already a column called {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub(super) fn not_found() -> ServerFnError {
+    ServerFnError::ServerError("Column not found".to_string())
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::backend::server_utils::{test_db_pool, test_user};
+    use crate::backend::NewTodo;
+    use sqlx::PgPool;
+
+    async fn add_column(pool: &PgPool, user_id: i32, name: &str, marks_done: bool) -> i32 {
+        sqlx::query_scalar(
+            "INSERT INTO board_columns (user_id, name, marks_done, position) VALUES ($1, $2, $3, 1) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(marks_done)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn add_todo(pool: &PgPool, user_id: i32) -> todo_store::TodoRecord {
+        todo_store::insert(
+            pool,
+            user_id,
+            &NewTodo::plain("Write report".to_string()),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn move_to(
+        pool: &PgPool,
+        user_id: i32,
+        todo: &todo_store::TodoRecord,
+        column_id: Option<i32>,
+    ) -> Result<todo_store::TodoRecord, ServerFnError> {
+        todo_store::set_status(
+            pool,
+            user_id,
+            todo.id,
+            column_id,
+            Some(todo.version),
+            ChangeSource::App,
+        )
+        .await
+        .map(|moved| moved.ok().expect("not stale"))
+    }
+
+    async fn actions(pool: &PgPool, todo_id: i32) -> Vec<String> {
+        sqlx::query_scalar("SELECT action FROM todo_events WHERE todo_id = $1 ORDER BY id")
+            .bind(todo_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn column_names_are_trimmed_and_limited() {
+        assert_eq!(check_name("  Doing ").unwrap(), "Doing");
+        assert!(check_name("   ").is_err());
+        assert!(check_name(&"x".repeat(COLUMN_NAME_MAX_LEN)).is_ok());
+        assert!(check_name(&"x".repeat(COLUMN_NAME_MAX_LEN + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn done_columns_complete_and_reopen_todos() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let doing = add_column(&pool, user_id, "Doing", false).await;
+        let done = add_column(&pool, user_id, "Done", true).await;
+        let todo = add_todo(&pool, user_id).await;
+
+        let todo = move_to(&pool, user_id, &todo, Some(doing)).await.unwrap();
+        assert_eq!((todo.status_id, todo.completed), (Some(doing), false));
+        let todo = move_to(&pool, user_id, &todo, Some(done)).await.unwrap();
+        assert_eq!((todo.status_id, todo.completed), (Some(done), true));
+        let todo = move_to(&pool, user_id, &todo, None).await.unwrap();
+        assert_eq!((todo.status_id, todo.completed), (None, false));
+
+        assert_eq!(
+            actions(&pool, todo.id).await,
+            vec!["created", "status_changed", "completed", "reopened"]
+        );
+    }
+
+    #[tokio::test]
+    async fn moving_into_a_missing_or_foreign_column_fails() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let (other_user, _) = test_user(&pool).await;
+        let foreign = add_column(&pool, other_user, "Theirs", true).await;
+        let todo = add_todo(&pool, user_id).await;
+
+        for column_id in [foreign, foreign + 1_000_000] {
+            let error = move_to(&pool, user_id, &todo, Some(column_id))
+                .await
+                .err()
+                .expect("should fail");
+            assert_eq!(error.to_string(), not_found().to_string());
+        }
+        let unchanged = todo_store::get(&pool, user_id, todo.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (unchanged.version, unchanged.status_id),
+            (todo.version, None)
+        );
+    }
+
+    #[tokio::test]
+    async fn moving_a_changed_todo_is_stale() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let doing = add_column(&pool, user_id, "Doing", false).await;
+        let todo = add_todo(&pool, user_id).await;
+        move_to(&pool, user_id, &todo, Some(doing)).await.unwrap();
+
+        let stale = todo_store::set_status(
+            &pool,
+            user_id,
+            todo.id,
+            None,
+            Some(todo.version),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            stale,
+            Err(todo_store::Stale::Changed { current_version }) if current_version == todo.version + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn deleting_a_column_moves_its_todos_out_as_changes() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let done = add_column(&pool, user_id, "Done", true).await;
+        let todo = add_todo(&pool, user_id).await;
+        let todo = move_to(&pool, user_id, &todo, Some(done)).await.unwrap();
+        let elsewhere = add_todo(&pool, user_id).await;
+
+        assert!(
+            todo_store::delete_status(&pool, user_id, done, ChangeSource::App)
+                .await
+                .unwrap()
+        );
+
+        let after = todo_store::get(&pool, user_id, todo.id)
+            .await
+            .unwrap()
+            .unwrap();
+        // Out of the column, still done, and at a new version
+        assert_eq!(after.status_id, None);
+        assert!(after.completed);
+        assert_eq!(after.version, todo.version + 1);
+        assert_eq!(
+            actions(&pool, todo.id).await,
+            vec!["created", "completed", "status_changed"]
+        );
+        let untouched = todo_store::get(&pool, user_id, elsewhere.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(untouched.version, elsewhere.version);
+        assert!(columns(&pool, user_id).await.unwrap().is_empty());
+
+        // Already gone
+        assert!(
+            !todo_store::delete_status(&pool, user_id, done, ChangeSource::App)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_can_delete_a_column() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (owner, _) = test_user(&pool).await;
+        let (other_user, _) = test_user(&pool).await;
+        let column = add_column(&pool, owner, "Doing", false).await;
+
+        assert!(
+            !todo_store::delete_status(&pool, other_user, column, ChangeSource::App)
+                .await
+                .unwrap()
+        );
+        assert_eq!(columns(&pool, owner).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn column_names_are_unique_ignoring_case() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let doing = add_column(&pool, user_id, "Doing", false).await;
+
+        assert!(check_name_free(&pool, user_id, "doing", None)
+            .await
+            .is_err());
+        // Renaming a column to itself, in another case, is fine
+        assert!(check_name_free(&pool, user_id, "DOING", Some(doing))
+            .await
+            .is_ok());
+        let (other_user, _) = test_user(&pool).await;
+        assert!(check_name_free(&pool, other_user, "Doing", None)
+            .await
+            .is_ok());
+    }
+}
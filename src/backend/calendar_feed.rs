@@ -0,0 +1,104 @@
+// The secret URL a calendar app subscribes to; `crate::ics` serves the feed.
+// Each user has at most one. Like API tokens only the hash is stored, so the
+// URL is shown once, and regenerating it cuts off every old subscription.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::password_reset::app_base_url;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use super::{get_user_id, reauthenticate};
+#[cfg(feature = "server")]
+use crate::secrets;
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CalendarFeedSettings {
+    pub created_at: String,
+    // When a calendar app last fetched the feed
+    pub last_used_at: Option<String>,
+}
+
+// None until the user creates a feed URL
+#[server]
+pub async fn calendar_feed_info(
+    username: String,
+) -> Result<Option<CalendarFeedSettings>, ServerFnError> {
+    observe("calendar_feed_info", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let row: Option<(String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"'),
+                   to_char(last_used_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"')
+            FROM calendar_feeds
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(row.map(|(created_at, last_used_at)| CalendarFeedSettings {
+            created_at,
+            last_used_at,
+        }))
+    })
+    .await
+}
+
+// Creates the feed URL, or replaces it with a new one. Returns the URL; it
+// can't be shown again.
+#[server]
+pub async fn regenerate_calendar_feed(
+    username: String,
+    password: String,
+) -> Result<String, ServerFnError> {
+    observe("regenerate_calendar_feed", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let token = secrets::generate_token();
+        sqlx::query(
+            r#"
+            INSERT INTO calendar_feeds (user_id, token_hash)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+                SET token_hash = EXCLUDED.token_hash, created_at = now(), last_used_at = NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(secrets::hash_token(&token))
+        .execute(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        tracing::info!("Calendar feed URL regenerated");
+        Ok(format!("{}/calendar/{}.ics", app_base_url(), token))
+    })
+    .await
+}
+
+#[server]
+pub async fn disable_calendar_feed(username: String) -> Result<(), ServerFnError> {
+    observe("disable_calendar_feed", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        sqlx::query("DELETE FROM calendar_feeds WHERE user_id = $1")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+
+        tracing::info!("Calendar feed disabled");
+        Ok(())
+    })
+    .await
+}
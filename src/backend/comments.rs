@@ -0,0 +1,183 @@
+// Discussion threads on todos. Only the todo's owner can read or add to its
+// thread, the same rule `delete_todo` applies, and only a comment's author
+// can edit or delete it. Author and todo owner are stored separately so
+// shared lists can let other people join in later.
+//
+// Comments go with their todo when it is deleted.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use super::{get_user_id, todo_store};
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+#[cfg(feature = "server")]
+use crate::validation;
+#[cfg(feature = "server")]
+use server_fn::error::NoCustomError;
+#[cfg(feature = "server")]
+use sqlx::PgPool;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow))]
+pub struct Comment {
+    pub id: i32,
+    pub todo_id: i32,
+    // Username of whoever wrote it
+    pub author: String,
+    // Markdown, like todo content
+    pub body: String,
+    pub created_at: String,
+    // Set once the comment has been edited
+    pub edited_at: Option<String>,
+}
+
+// Oldest first, so the thread reads top to bottom
+#[server]
+pub async fn list_comments(
+    username: String,
+    todo_id: usize,
+) -> Result<Vec<Comment>, ServerFnError> {
+    observe("list_comments", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+        check_todo(pool, user_id, todo_id).await?;
+
+        sqlx::query_as(&format!(
+            "SELECT {} WHERE c.todo_id = $1 ORDER BY c.id",
+            COMMENT_QUERY
+        ))
+        .bind(todo_id as i32)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)
+    })
+    .await
+}
+
+#[server]
+pub async fn add_comment(
+    username: String,
+    todo_id: usize,
+    body: String,
+) -> Result<Comment, ServerFnError> {
+    observe("add_comment", &username, async {
+        validation::validate_comment(&body).map_err(ServerFnError::<NoCustomError>::ServerError)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+        check_todo(pool, user_id, todo_id).await?;
+
+        let id: i32 = sqlx::query_scalar(
+            "INSERT INTO comments (todo_id, author_id, body) VALUES ($1, $2, $3) RETURNING id",
+        )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .bind(body.trim())
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        tracing::debug!(todo_id, comment_id = id, "Comment added");
+        get_comment(pool, id).await
+    })
+    .await
+}
+
+#[server]
+pub async fn edit_comment(
+    username: String,
+    id: i32,
+    body: String,
+) -> Result<Comment, ServerFnError> {
+    observe("edit_comment", &username, async {
+        validation::validate_comment(&body).map_err(ServerFnError::<NoCustomError>::ServerError)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let updated = sqlx::query(&format!(
+            "UPDATE comments c SET body = $2, edited_at = now() WHERE c.id = $3 AND {}",
+            CAN_CHANGE
+        ))
+        .bind(user_id)
+        .bind(body.trim())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        if updated.rows_affected() == 0 {
+            return Err(not_found());
+        }
+
+        tracing::debug!(comment_id = id, "Comment edited");
+        get_comment(pool, id).await
+    })
+    .await
+}
+
+#[server]
+pub async fn delete_comment(username: String, id: i32) -> Result<(), ServerFnError> {
+    observe("delete_comment", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let deleted = sqlx::query(&format!(
+            "DELETE FROM comments c WHERE c.id = $2 AND {}",
+            CAN_CHANGE
+        ))
+        .bind(user_id)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        if deleted.rows_affected() == 0 {
+            return Err(not_found());
+        }
+
+        tracing::debug!(comment_id = id, "Comment deleted");
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(feature = "server")]
+const COMMENT_QUERY: &str = r#"
+    c.id, c.todo_id, u.username AS author, c.body,
+    to_char(c.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"') AS created_at,
+    to_char(c.edited_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"') AS edited_at
+    FROM comments c JOIN users u ON u.id = c.author_id
+"#;
+
+// The caller ($1) wrote the comment and still owns its todo
+#[cfg(feature = "server")]
+const CAN_CHANGE: &str = r#"
+    c.author_id = $1
+    AND EXISTS (SELECT 1 FROM todos t WHERE t.id = c.todo_id AND t.user_id = $1)
+"#;
+
+// Other users' todos are reported as not found, like in `get_todo`
+#[cfg(feature = "server")]
+async fn check_todo(pool: &PgPool, user_id: i32, todo_id: usize) -> Result<(), ServerFnError> {
+    todo_store::get(pool, user_id, todo_id as i32)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| ServerFnError::<NoCustomError>::ServerError("Todo not found".to_string()))
+}
+
+#[cfg(feature = "server")]
+async fn get_comment(pool: &PgPool, id: i32) -> Result<Comment, ServerFnError> {
+    sqlx::query_as(&format!("SELECT {} WHERE c.id = $1", COMMENT_QUERY))
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)
+}
+
+#[cfg(feature = "server")]
+fn not_found() -> ServerFnError {
+    ServerFnError::ServerError("Comment not found".to_string())
+}
@@ -0,0 +1,126 @@
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use super::password_reset::app_base_url;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use crate::mail::{mail_sender, Email};
+#[cfg(feature = "server")]
+use crate::rate_limit::{self, Action};
+#[cfg(feature = "server")]
+use crate::secrets;
+#[cfg(feature = "server")]
+use crate::telemetry::{self, observe};
+
+// How long an emailed verification link stays valid
+#[cfg(feature = "server")]
+const VERIFY_TOKEN_TTL_HOURS: i32 = 24;
+
+// Email a link proving the user controls `email`. Until it is opened the
+// address still works for password resets (those go to the address itself),
+// but SSO logins are never linked to the account through it.
+#[cfg(feature = "server")]
+pub(crate) async fn send_verification(
+    pool: &sqlx::PgPool,
+    user_id: i32,
+    username: &str,
+    email: &str,
+) -> Result<(), ServerFnError> {
+    // Only the hash is stored; the plain token goes out in the email
+    let token = secrets::generate_token();
+    sqlx::query(
+        r#"
+        INSERT INTO email_verification_tokens (user_id, email, token_hash, expires_at)
+        VALUES ($1, $2, $3, now() + make_interval(hours => $4))
+        "#,
+    )
+    .bind(user_id)
+    .bind(email)
+    .bind(secrets::hash_token(&token))
+    .bind(VERIFY_TOKEN_TTL_HOURS)
+    .execute(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let link = format!("{}/verify-email?token={}", app_base_url(), token);
+    let message = Email {
+        to: email.to_string(),
+        subject: "Confirm your Todo list email".to_string(),
+        body: format!(
+            "Hi {},\n\nOpen this link within {} hours to confirm this address for your \
+             Todo list account:\n\n{}\n\nIf this wasn't you, you can ignore this email.\n",
+            username, VERIFY_TOKEN_TTL_HOURS, link
+        ),
+    };
+
+    tokio::spawn(async move {
+        match mail_sender().send(message).await {
+            Ok(()) => tracing::info!(user_id, "Email verification sent"),
+            Err(e) => {
+                tracing::error!(user_id, error = %e, "Failed to send email verification")
+            }
+        }
+    });
+    Ok(())
+}
+
+// Redeem a verification token. Only marks the address verified if it is
+// still the one on the account.
+#[server]
+pub async fn verify_email(token: String) -> Result<(), ServerFnError> {
+    observe("verify_email", "", async {
+        rate_limit::check_ip(Action::EmailVerification)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+        let row: Option<(i32, String)> = sqlx::query_as(
+            r#"
+            SELECT user_id, email
+            FROM email_verification_tokens
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > now()
+            FOR UPDATE
+            "#,
+        )
+        .bind(secrets::hash_token(&token))
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        let Some((user_id, email)) = row else {
+            return Err(ServerFnError::ServerError(
+                "This verification link is invalid or has expired".to_string(),
+            ));
+        };
+        telemetry::record_user_id(user_id);
+
+        let result = sqlx::query(
+            "UPDATE users SET email_verified = true WHERE id = $1 AND lower(email) = lower($2)",
+        )
+        .bind(user_id)
+        .bind(&email)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        sqlx::query(
+            "UPDATE email_verification_tokens SET used_at = now() WHERE user_id = $1 AND used_at IS NULL",
+        )
+        .bind(user_id)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        tx.commit().await.map_err(sqlx_err_to_server_err)?;
+
+        if result.rows_affected() == 0 {
+            return Err(ServerFnError::ServerError(
+                "This address is no longer set on the account".to_string(),
+            ));
+        }
+        tracing::info!("Email verified");
+        Ok(())
+    })
+    .await
+}
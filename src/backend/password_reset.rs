@@ -0,0 +1,284 @@
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use crate::mail::{mail_sender, Email};
+#[cfg(feature = "server")]
+use crate::rate_limit::{self, Action};
+#[cfg(feature = "server")]
+use crate::secrets;
+#[cfg(feature = "server")]
+use crate::telemetry::{self, observe};
+#[cfg(feature = "server")]
+use crate::validation;
+#[cfg(feature = "server")]
+use server_fn::error::NoCustomError;
+#[cfg(feature = "server")]
+use sqlx::PgPool;
+
+// How long an emailed reset link stays valid
+#[cfg(feature = "server")]
+const RESET_TOKEN_TTL_MINUTES: i32 = 30;
+
+// Email a reset link if an account uses this address. Always succeeds for a
+// well-formed address so the response doesn't reveal who has an account.
+#[server]
+pub async fn request_password_reset(email: String) -> Result<(), ServerFnError> {
+    observe("request_password_reset", "", async {
+        let email = email.trim().to_string();
+        validation::validate_email(&email).map_err(ServerFnError::<NoCustomError>::ServerError)?;
+        rate_limit::check(Action::PasswordReset, &email)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+
+        let user: Option<(i32, String)> =
+            sqlx::query_as("SELECT id, username FROM users WHERE lower(email) = lower($1)")
+                .bind(&email)
+                .fetch_optional(pool)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+
+        let Some((user_id, username)) = user else {
+            tracing::info!("Password reset requested for an unknown email");
+            return Ok(());
+        };
+        telemetry::record_user_id(user_id);
+
+        let token = create_reset_token(pool, user_id).await?;
+        let link = format!("{}/reset-password?token={}", app_base_url(), token);
+        let message = Email {
+            to: email,
+            subject: "Reset your Todo list password".to_string(),
+            body: format!(
+                "Hi {},\n\nSomeone asked to reset the password for your Todo list account.\n\
+                 Open this link within {} minutes to choose a new one:\n\n{}\n\n\
+                 If this wasn't you, you can ignore this email.\n",
+                username, RESET_TOKEN_TTL_MINUTES, link
+            ),
+        };
+
+        // Send in the background so response time doesn't depend on whether
+        // the address belongs to an account
+        tokio::spawn(async move {
+            match mail_sender().send(message).await {
+                Ok(()) => tracing::info!(user_id, "Password reset email sent"),
+                Err(e) => {
+                    tracing::error!(user_id, error = %e, "Failed to send password reset email")
+                }
+            }
+        });
+
+        Ok(())
+    })
+    .await
+}
+
+// Redeem a reset token: set the new password and burn every outstanding
+// token for that user
+#[server]
+pub async fn reset_password(token: String, new_password: String) -> Result<(), ServerFnError> {
+    observe("reset_password", "", async {
+        rate_limit::check_ip(Action::PasswordReset)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let username = redeem_reset_token(pool, &token, &new_password).await?;
+
+        // A fresh password also lifts any login lockout
+        rate_limit::record_success(&username);
+        tracing::info!("Password reset completed");
+        Ok(())
+    })
+    .await
+}
+
+// A new reset token for `user_id`. Only the hash is stored; the plain token
+// goes out in the email.
+#[cfg(feature = "server")]
+async fn create_reset_token(pool: &PgPool, user_id: i32) -> Result<String, ServerFnError> {
+    let token = secrets::generate_token();
+    sqlx::query(
+        r#"
+        INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+        VALUES ($1, $2, now() + make_interval(mins => $3))
+        "#,
+    )
+    .bind(user_id)
+    .bind(secrets::hash_token(&token))
+    .bind(RESET_TOKEN_TTL_MINUTES)
+    .execute(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+    Ok(token)
+}
+
+// Set the new password if `token` is unused and unexpired, and burn every
+// outstanding token for that user. Returns the user's name.
+#[cfg(feature = "server")]
+async fn redeem_reset_token(
+    pool: &PgPool,
+    token: &str,
+    new_password: &str,
+) -> Result<String, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+    let row: Option<(i32, String)> = sqlx::query_as(
+        r#"
+        SELECT u.id, u.username
+        FROM password_reset_tokens t
+        JOIN users u ON u.id = t.user_id
+        WHERE t.token_hash = $1 AND t.used_at IS NULL AND t.expires_at > now()
+        FOR UPDATE OF t
+        "#,
+    )
+    .bind(secrets::hash_token(token))
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let Some((user_id, username)) = row else {
+        return Err(ServerFnError::ServerError(
+            "This reset link is invalid or has expired".to_string(),
+        ));
+    };
+    telemetry::record_user_id(user_id);
+
+    validation::validate_password(new_password, &username)
+        .map_err(ServerFnError::<NoCustomError>::ServerError)?;
+
+    // Password stored as plain text, same as in register
+    sqlx::query("UPDATE users SET password = $1 WHERE id = $2")
+        .bind(new_password)
+        .bind(user_id)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+    sqlx::query(
+        "UPDATE password_reset_tokens SET used_at = now() WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+
+    Ok(username)
+}
+
+// Public URL of the app, used to build links in emails
+#[cfg(feature = "server")]
+pub(crate) fn app_base_url() -> String {
+    std::env::var("APP_BASE_URL")
+        .map(|url| url.trim_end_matches('/').to_string())
+        .unwrap_or_else(|_| "http://localhost:8080".to_string())
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::backend::server_utils::{test_db_pool, test_user};
+
+    const NEW_PASSWORD: &str = "Fresh-password-42";
+
+    async fn password_of(pool: &PgPool, user_id: i32) -> String {
+        sqlx::query_scalar("SELECT password FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn stores_only_the_token_hash() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let token = create_reset_token(&pool, user_id).await.unwrap();
+
+        let stored: Vec<String> =
+            sqlx::query_scalar("SELECT token_hash FROM password_reset_tokens WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(stored, vec![secrets::hash_token(&token)]);
+        assert_ne!(stored[0], token);
+    }
+
+    #[tokio::test]
+    async fn a_token_works_once_and_burns_the_others() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, username) = test_user(&pool).await;
+        let first = create_reset_token(&pool, user_id).await.unwrap();
+        let second = create_reset_token(&pool, user_id).await.unwrap();
+
+        assert_eq!(
+            redeem_reset_token(&pool, &first, NEW_PASSWORD)
+                .await
+                .unwrap(),
+            username
+        );
+        assert_eq!(password_of(&pool, user_id).await, NEW_PASSWORD);
+
+        for token in [&first, &second] {
+            assert!(redeem_reset_token(&pool, token, "Other-password-43")
+                .await
+                .is_err());
+        }
+        assert_eq!(password_of(&pool, user_id).await, NEW_PASSWORD);
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_are_refused() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let token = create_reset_token(&pool, user_id).await.unwrap();
+        sqlx::query(
+            "UPDATE password_reset_tokens SET expires_at = now() - interval '1 second' WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(redeem_reset_token(&pool, &token, NEW_PASSWORD)
+            .await
+            .is_err());
+        assert_eq!(password_of(&pool, user_id).await, "Test-password-1");
+    }
+
+    #[tokio::test]
+    async fn a_weak_password_leaves_the_token_usable() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let token = create_reset_token(&pool, user_id).await.unwrap();
+
+        assert!(redeem_reset_token(&pool, &token, "password123")
+            .await
+            .is_err());
+        assert!(redeem_reset_token(&pool, &token, NEW_PASSWORD)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn unknown_tokens_are_refused() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        assert!(
+            redeem_reset_token(&pool, &secrets::generate_token(), NEW_PASSWORD)
+                .await
+                .is_err()
+        );
+    }
+}
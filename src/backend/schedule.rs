@@ -0,0 +1,111 @@
+// Todos by due date for the calendar and agenda views, and moving a todo to
+// another day. Which day is "today" is the client's, as in `stats`.
+
+use chrono::NaiveDate;
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::activity::ChangeSource;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+use super::TodoItem;
+#[cfg(feature = "server")]
+use super::{attachments, get_user_id, todo_store};
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+
+// Most todos one calendar or agenda request returns
+#[cfg(feature = "server")]
+const SCHEDULE_LIMIT: i64 = 500;
+// Longest range `todos_due_between` accepts: a month view with its spill-over
+#[cfg(feature = "server")]
+const MAX_RANGE_DAYS: i64 = 42;
+
+// Open todos in the agenda's three groups, soonest first
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Agenda {
+    // Due today or overdue
+    pub today: Vec<TodoItem>,
+    pub upcoming: Vec<TodoItem>,
+    // No due date
+    pub someday: Vec<TodoItem>,
+}
+
+// Done and open todos due from `from` to `to` inclusive, for a calendar page
+#[server]
+pub async fn todos_due_between(
+    username: String,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<TodoItem>, ServerFnError> {
+    observe("todos_due_between", &username, async {
+        if to < from || (to - from).num_days() >= MAX_RANGE_DAYS {
+            return Err(ServerFnError::ServerError(format!(
+                "Pick a range of 1 to {} days.",
+                MAX_RANGE_DAYS
+            )));
+        }
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let rows = todo_store::list_due(pool, user_id, from, to, SCHEDULE_LIMIT).await?;
+        let mut todos: Vec<TodoItem> = rows.into_iter().map(TodoItem::from).collect();
+        attachments::load_for(pool, &mut todos).await?;
+        Ok(todos)
+    })
+    .await
+}
+
+#[server]
+pub async fn todo_agenda(username: String, today: NaiveDate) -> Result<Agenda, ServerFnError> {
+    observe("todo_agenda", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let rows = todo_store::list_open(pool, user_id, SCHEDULE_LIMIT).await?;
+        let mut todos: Vec<TodoItem> = rows.into_iter().map(TodoItem::from).collect();
+        attachments::load_for(pool, &mut todos).await?;
+
+        let mut agenda = Agenda::default();
+        for todo in todos {
+            match todo.due_date {
+                Some(date) if date <= today => agenda.today.push(todo),
+                Some(_) => agenda.upcoming.push(todo),
+                None => agenda.someday.push(todo),
+            }
+        }
+        Ok(agenda)
+    })
+    .await
+}
+
+// Moves a todo to `due_date`, keeping its time of day; None makes it a
+// someday todo. Fails with `AppError::Conflict` like `update_todo`.
+#[server]
+pub async fn reschedule_todo(
+    username: String,
+    id: usize,
+    expected_version: i32,
+    due_date: Option<NaiveDate>,
+) -> Result<TodoItem, ServerFnError> {
+    observe("reschedule_todo", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let todo = todo_store::reschedule(
+            pool,
+            user_id,
+            id as i32,
+            due_date,
+            Some(expected_version),
+            ChangeSource::App,
+        )
+        .await?
+        .map_err(todo_store::Stale::into_server_error)?;
+
+        tracing::debug!(todo_id = id, ?due_date, "Todo rescheduled");
+        Ok(todo.into())
+    })
+    .await
+}
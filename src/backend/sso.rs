@@ -0,0 +1,321 @@
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use super::get_user_id;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use crate::metrics;
+#[cfg(feature = "server")]
+use crate::oidc;
+#[cfg(feature = "server")]
+use crate::session;
+#[cfg(feature = "server")]
+use crate::telemetry::{self, observe};
+#[cfg(feature = "server")]
+use crate::validation::{self, USERNAME_MAX_LEN};
+#[cfg(feature = "server")]
+use server_fn::error::NoCustomError;
+
+// How many numbered variants of a username to try before giving up
+#[cfg(feature = "server")]
+const USERNAME_ATTEMPTS: u32 = 100;
+// SSO-only accounts have no password to re-enter, so setting one needs an
+// SSO login at most this long ago instead
+#[cfg(feature = "server")]
+const SET_PASSWORD_WINDOW_MINUTES: i32 = 10;
+
+// Verified claims from the provider's ID token
+#[cfg(feature = "server")]
+pub struct SsoIdentity {
+    pub issuer: String,
+    pub subject: String,
+    // Only set when the provider marked it verified
+    pub email: Option<String>,
+    pub preferred_username: Option<String>,
+}
+
+// Whether to show the "Sign in with SSO" button
+#[server]
+pub async fn sso_enabled() -> Result<bool, ServerFnError> {
+    Ok(oidc::is_configured())
+}
+
+// Last step of an SSO login: trade the ticket from the callback redirect for
+// the username to log in as
+#[server]
+pub async fn complete_sso_login(ticket: String) -> Result<String, ServerFnError> {
+    observe("complete_sso_login", "", async {
+        let Some((user_id, username)) = oidc::redeem_ticket(&ticket) else {
+            metrics::record_login_failure("sso_ticket_expired");
+            return Err(ServerFnError::ServerError(
+                "Sign-in link expired, please try again".to_string(),
+            ));
+        };
+        telemetry::record_user_id(user_id);
+        // Needed by accounts with 2FA on, and to set a password afterwards
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        session::start(pool, user_id).await?;
+        metrics::record_login("sso");
+        tracing::info!("User logged in with SSO");
+        Ok(username)
+    })
+    .await
+}
+
+// Whether to offer `set_sso_password`: the account has no password and this
+// browser holds its session, so nobody else learns which accounts are SSO-only
+#[server]
+pub async fn needs_password(username: String) -> Result<bool, ServerFnError> {
+    observe("needs_password", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+        if session::current_user(pool).await? != Some(user_id) {
+            return Ok(false);
+        }
+
+        sqlx::query_scalar("SELECT password IS NULL FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)
+    })
+    .await
+}
+
+// First password for an account created by SSO. Everything that asks for the
+// current password (API tokens, 2FA, deleting the account, ...) works once
+// one is set.
+#[server]
+pub async fn set_sso_password(username: String, new_password: String) -> Result<(), ServerFnError> {
+    observe("set_sso_password", &username, async {
+        validation::validate_password(&new_password, &username)
+            .map_err(ServerFnError::<NoCustomError>::ServerError)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+        if session::recent_user(pool, SET_PASSWORD_WINDOW_MINUTES).await? != Some(user_id) {
+            return Err(ServerFnError::ServerError(format!(
+                "Sign in with SSO again (within the last {} minutes) to set a password",
+                SET_PASSWORD_WINDOW_MINUTES
+            )));
+        }
+
+        // Password stored as plain text, same as in register. Only fills in a
+        // missing password; changing one goes through `change_password`.
+        let result =
+            sqlx::query("UPDATE users SET password = $1 WHERE id = $2 AND password IS NULL")
+                .bind(&new_password)
+                .bind(user_id)
+                .execute(pool)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+        if result.rows_affected() == 0 {
+            return Err(ServerFnError::ServerError(
+                "This account already has a password".to_string(),
+            ));
+        }
+
+        tracing::info!("Password set for SSO account");
+        Ok(())
+    })
+    .await
+}
+
+// The user an identity is linked to. On first login the identity is linked
+// to the account with the same email if both the provider and a mailed link
+// (see `email_verification`) verified it, or else to a new account without a
+// password. Anyone can type any address into `set_email`, so an unverified
+// match must never be linked.
+#[cfg(feature = "server")]
+pub(crate) async fn find_or_create_sso_user(
+    identity: &SsoIdentity,
+) -> Result<(i32, String), ServerFnError> {
+    let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+
+    let linked: Option<(i32, String)> = sqlx::query_as(
+        r#"
+        SELECT u.id, u.username
+        FROM user_identities i
+        JOIN users u ON u.id = i.user_id
+        WHERE i.issuer = $1 AND i.subject = $2
+        "#,
+    )
+    .bind(&identity.issuer)
+    .bind(&identity.subject)
+    .fetch_optional(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+    let existing: Option<(i32, String)> = match &identity.email {
+        Some(email) => {
+            let verified: Option<(i32, String)> = sqlx::query_as(
+                "SELECT id, username FROM users WHERE lower(email) = lower($1) AND email_verified",
+            )
+            .bind(email)
+            .fetch_optional(&mut tx)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+            if verified.is_none() {
+                // The provider verified the address, so an account merely
+                // claiming it gives it up to the new account
+                let released = sqlx::query(
+                    "UPDATE users SET email = NULL WHERE lower(email) = lower($1) AND NOT email_verified",
+                )
+                .bind(email)
+                .execute(&mut tx)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+                if released.rows_affected() > 0 {
+                    tracing::info!("Released unverified email claimed by another account");
+                }
+            }
+            verified
+        }
+        None => None,
+    };
+
+    let (user_id, username) = match existing {
+        Some(user) => {
+            tracing::info!(
+                user_id = user.0,
+                "Linking SSO identity to existing account by email"
+            );
+            user
+        }
+        None => {
+            let base = username_from_claims(identity);
+            let mut created = None;
+            for attempt in 1..=USERNAME_ATTEMPTS {
+                let candidate = numbered_username(&base, attempt);
+                // The unique index on lower(username) turns a taken name into no row
+                let id: Option<i32> = sqlx::query_scalar(
+                    r#"
+                    INSERT INTO users (username, password, email, email_verified)
+                    VALUES ($1, NULL, $2, $2 IS NOT NULL)
+                    ON CONFLICT DO NOTHING
+                    RETURNING id
+                    "#,
+                )
+                .bind(&candidate)
+                .bind(&identity.email)
+                .fetch_optional(&mut tx)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+                if let Some(id) = id {
+                    created = Some((id, candidate));
+                    break;
+                }
+            }
+            let Some(user) = created else {
+                return Err(ServerFnError::ServerError(
+                    "Could not pick a username for this account".to_string(),
+                ));
+            };
+            tracing::info!(user_id = user.0, "Created account for SSO identity");
+            user
+        }
+    };
+
+    sqlx::query("INSERT INTO user_identities (user_id, issuer, subject) VALUES ($1, $2, $3)")
+        .bind(user_id)
+        .bind(&identity.issuer)
+        .bind(&identity.subject)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+
+    Ok((user_id, username))
+}
+
+// preferred_username, else the email's local part, cut down to what
+// `validate_username` accepts
+#[cfg(feature = "server")]
+fn username_from_claims(identity: &SsoIdentity) -> String {
+    let raw = identity
+        .preferred_username
+        .as_deref()
+        .or_else(|| identity.email.as_deref().and_then(|e| e.split('@').next()))
+        .unwrap_or_default();
+
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .skip_while(|c| !c.is_ascii_alphanumeric())
+        .take(USERNAME_MAX_LEN)
+        .collect();
+
+    if validation::validate_username(&cleaned).is_ok() {
+        cleaned
+    } else {
+        "user".to_string()
+    }
+}
+
+// "alice", then "alice2", "alice3", ..., trimming the base to stay within the length limit
+#[cfg(feature = "server")]
+fn numbered_username(base: &str, attempt: u32) -> String {
+    if attempt == 1 {
+        return base.to_string();
+    }
+    let suffix = attempt.to_string();
+    let keep = USERNAME_MAX_LEN
+        .saturating_sub(suffix.len())
+        .min(base.len());
+    format!("{}{}", &base[..keep], suffix)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    fn identity(preferred_username: Option<&str>, email: Option<&str>) -> SsoIdentity {
+        SsoIdentity {
+            issuer: "https://idp.example.com".to_string(),
+            subject: "subject-1".to_string(),
+            email: email.map(str::to_string),
+            preferred_username: preferred_username.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn username_prefers_preferred_username() {
+        let identity = identity(Some("alice"), Some("a.smith@example.com"));
+        assert_eq!(username_from_claims(&identity), "alice");
+    }
+
+    #[test]
+    fn username_falls_back_to_email_local_part() {
+        let identity = identity(None, Some("a.smith@example.com"));
+        assert_eq!(username_from_claims(&identity), "a.smith");
+    }
+
+    #[test]
+    fn username_drops_characters_validation_rejects() {
+        let identity = identity(Some("__Alice Smith!"), None);
+        assert_eq!(username_from_claims(&identity), "AliceSmith");
+    }
+
+    #[test]
+    fn username_without_usable_claims_is_user() {
+        assert_eq!(username_from_claims(&identity(None, None)), "user");
+        assert_eq!(username_from_claims(&identity(Some("!!"), None)), "user");
+    }
+
+    #[test]
+    fn numbered_usernames_stay_within_the_length_limit() {
+        assert_eq!(numbered_username("alice", 1), "alice");
+        assert_eq!(numbered_username("alice", 2), "alice2");
+
+        let long = "a".repeat(USERNAME_MAX_LEN);
+        let numbered = numbered_username(&long, 42);
+        assert_eq!(numbered.len(), USERNAME_MAX_LEN);
+        assert!(numbered.ends_with("42"));
+    }
+}
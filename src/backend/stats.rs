@@ -0,0 +1,207 @@
+// Numbers for the Stats page, computed in SQL so the client never needs the
+// whole todo list. Completions, time to complete and streaks come from the
+// activity log (see `activity`), so they include todos deleted since and
+// start from when the log was introduced.
+//
+// Days are the user's own: the client passes its date and UTC offset.
+
+use chrono::NaiveDate;
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::get_user_id;
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+
+#[cfg(feature = "server")]
+pub const MAX_STATS_WEEKS: u32 = 52;
+// Real UTC offsets stay within ±14 hours
+#[cfg(feature = "server")]
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TodoStatsSummary {
+    pub open: i64,
+    pub completed: i64,
+    // Open with a due date before today
+    pub overdue: i64,
+    // One entry per day, oldest first, ending today
+    pub completions_per_day: Vec<DayCount>,
+    // From being added to the last time it was completed, for todos that are
+    // still done. None until one has been.
+    pub average_seconds_to_complete: Option<f64>,
+    // Days in a row with at least one completion, up to today (or yesterday,
+    // since today isn't over)
+    pub current_streak: i64,
+    pub longest_streak: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub completions: i64,
+}
+
+// `weeks` of daily completions up to `today`; `utc_offset_minutes` is how
+// far the user's clock is ahead of UTC, kept within ±14 hours
+#[server]
+pub async fn todo_stats(
+    username: String,
+    today: NaiveDate,
+    utc_offset_minutes: i32,
+    weeks: u32,
+) -> Result<TodoStatsSummary, ServerFnError> {
+    observe("todo_stats", &username, async {
+        let weeks = weeks.clamp(1, MAX_STATS_WEEKS) as i32;
+        let utc_offset_minutes =
+            utc_offset_minutes.clamp(-MAX_UTC_OFFSET_MINUTES, MAX_UTC_OFFSET_MINUTES);
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let (open, completed, overdue): (i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) FILTER (WHERE NOT completed),
+                   COUNT(*) FILTER (WHERE completed),
+                   COUNT(*) FILTER (WHERE NOT completed AND due_date < $2)
+            FROM todos
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(today)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        let days: Vec<(NaiveDate, i64)> = sqlx::query_as(&format!(
+            r#"
+            WITH completions AS (
+                SELECT {} AS day
+                FROM todo_events
+                WHERE user_id = $1 AND action = 'completed'
+                    AND created_at > $2::DATE - $4 * 7 - 1
+            )
+            SELECT d.day::DATE, COUNT(c.day)
+            FROM generate_series($2::DATE - ($4 * 7 - 1), $2::DATE, INTERVAL '1 day') AS d(day)
+            LEFT JOIN completions c ON c.day = d.day::DATE
+            GROUP BY d.day
+            ORDER BY d.day
+            "#,
+            LOCAL_DAY
+        ))
+        .bind(user_id)
+        .bind(today)
+        .bind(utc_offset_minutes)
+        .bind(weeks)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        let average_seconds_to_complete: Option<f64> = sqlx::query_scalar(
+            r#"
+            SELECT EXTRACT(EPOCH FROM AVG(done - added))::FLOAT8
+            FROM (
+                SELECT MIN(created_at) FILTER (WHERE action = 'created') AS added,
+                       MAX(created_at) FILTER (WHERE action = 'completed') AS done,
+                       MAX(created_at) FILTER (WHERE action = 'reopened') AS reopened
+                FROM todo_events
+                WHERE user_id = $1
+                GROUP BY todo_id
+            ) t
+            WHERE added IS NOT NULL AND done IS NOT NULL
+                AND (reopened IS NULL OR reopened < done)
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        // Runs of consecutive completion days: subtracting a day's rank
+        // from it gives the same date for every day in a run
+        let (current_streak, longest_streak): (i64, i64) = sqlx::query_as(&format!(
+            r#"
+            WITH days AS (
+                SELECT DISTINCT {} AS day
+                FROM todo_events
+                WHERE user_id = $1 AND action = 'completed'
+            ),
+            runs AS (
+                SELECT MAX(day) AS last_day, COUNT(*) AS length
+                FROM (
+                    SELECT day, day - (ROW_NUMBER() OVER (ORDER BY day))::INTEGER AS run
+                    FROM days
+                ) ranked
+                GROUP BY run
+            )
+            SELECT COALESCE(MAX(length) FILTER (WHERE last_day >= $2::DATE - 1), 0),
+                   COALESCE(MAX(length), 0)
+            FROM runs
+            "#,
+            LOCAL_DAY
+        ))
+        .bind(user_id)
+        .bind(today)
+        .bind(utc_offset_minutes)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(TodoStatsSummary {
+            open,
+            completed,
+            overdue,
+            completions_per_day: days
+                .into_iter()
+                .map(|(date, completions)| DayCount { date, completions })
+                .collect(),
+            average_seconds_to_complete,
+            current_streak,
+            longest_streak,
+        })
+    })
+    .await
+}
+
+// An event's date on the user's clock; the offset in minutes is $3
+#[cfg(feature = "server")]
+const LOCAL_DAY: &str =
+    "(created_at AT TIME ZONE 'UTC' + make_interval(mins => $3::INTEGER))::DATE";
+
+// "3 days 4 h", "5 h 20 min", "12 min"
+pub fn duration_label(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as i64;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{} min", minutes),
+        (0, _) => format!("{} h {} min", hours, minutes),
+        (1, _) => format!("1 day {} h", hours),
+        _ => format!("{} days {} h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_labels() {
+        for (seconds, label) in [
+            (0.0, "0 min"),
+            (29.0, "0 min"),
+            (30.0, "1 min"),
+            (59.0 * 60.0, "59 min"),
+            (60.0 * 60.0, "1 h 0 min"),
+            (23.0 * 3600.0 + 59.0 * 60.0, "23 h 59 min"),
+            (24.0 * 3600.0, "1 day 0 h"),
+            (47.0 * 3600.0 + 45.0 * 60.0, "1 day 23 h"),
+            (48.0 * 3600.0, "2 days 0 h"),
+            (10.0 * 86400.0 + 5.0 * 3600.0, "10 days 5 h"),
+        ] {
+            assert_eq!(duration_label(seconds), label, "{} seconds", seconds);
+        }
+    }
+}
@@ -0,0 +1,753 @@
+// Todo queries shared by the server functions in `backend` and the REST API
+// in `api`. Every query is scoped to the owning user.
+//
+// Every write bumps `version`. Updates and deletes can pass the version the
+// caller last saw and are refused with `Stale` if the todo has moved on.
+// Every write is also recorded in the activity log (see `activity`) and
+// queued for the user's webhooks in the same transaction.
+
+use chrono::{NaiveDate, NaiveTime};
+use dioxus::prelude::*;
+use sqlx::{FromRow, PgPool, Row};
+
+use super::activity::{self, ChangeSource, TodoAction};
+use super::board;
+use super::server_utils::sqlx_err_to_server_err;
+use super::{BulkAction, NewTodo};
+use crate::errors::AppError;
+
+#[derive(Clone, sqlx::FromRow)]
+pub struct TodoRecord {
+    pub id: i32,
+    pub content: String,
+    pub notes: String,
+    pub completed: bool,
+    pub version: i32,
+    pub list: Option<String>,
+    pub tags: Vec<String>,
+    pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub priority: Option<i16>,
+    pub status_id: Option<i32>,
+}
+
+// Selected or returned by every query that produces a `TodoRecord`
+const TODO_COLUMNS: &str = r#"
+    id, content, notes, completed, version, tags, due_date, due_time, priority, status_id,
+    (SELECT name FROM todo_lists l WHERE l.id = todos.list_id) AS list
+"#;
+
+// Fields `update` sets; None leaves a field alone
+#[derive(Default)]
+pub struct Changes<'a> {
+    pub content: Option<&'a str>,
+    pub notes: Option<&'a str>,
+    pub completed: Option<bool>,
+}
+
+// Why a versioned update or delete did not happen
+pub enum Stale {
+    // The user has no such todo (any more)
+    Missing,
+    // It was changed after the caller read it
+    Changed { current_version: i32 },
+}
+
+impl Stale {
+    pub fn into_server_error(self) -> ServerFnError {
+        let current_version = match self {
+            Stale::Missing => None,
+            Stale::Changed { current_version } => Some(current_version),
+        };
+        AppError::Conflict { current_version }.into_server_error()
+    }
+}
+
+pub async fn insert(
+    pool: &PgPool,
+    user_id: i32,
+    todo: &NewTodo,
+    source: ChangeSource,
+) -> Result<TodoRecord, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+    let todo = insert_in(&mut tx, user_id, todo, source)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(todo)
+}
+
+// Like `insert`, but a `key` the user has sent before returns the todo it
+// created then instead of adding another one, or None if that todo has since
+// been deleted. Lets offline clients retry a create whose response they
+// never saw.
+pub async fn insert_once(
+    pool: &PgPool,
+    user_id: i32,
+    todo: &NewTodo,
+    key: &str,
+    source: ChangeSource,
+) -> Result<Option<TodoRecord>, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+    // A concurrent request with the same key waits here until the first commits
+    let claimed = sqlx::query(
+        "INSERT INTO todo_create_keys (user_id, key) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(key)
+    .execute(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    if claimed.rows_affected() == 0 {
+        let existing = sqlx::query_as(&format!(
+            r#"
+            SELECT {}
+            FROM todos
+            WHERE user_id = $1
+                AND id = (SELECT todo_id FROM todo_create_keys WHERE user_id = $1 AND key = $2)
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(key)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        tx.commit().await.map_err(sqlx_err_to_server_err)?;
+        return Ok(existing);
+    }
+
+    let todo = insert_in(&mut tx, user_id, todo, source)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    sqlx::query("UPDATE todo_create_keys SET todo_id = $3 WHERE user_id = $1 AND key = $2")
+        .bind(user_id)
+        .bind(key)
+        .bind(todo.id)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(Some(todo))
+}
+
+async fn insert_in(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    todo: &NewTodo,
+    source: ChangeSource,
+) -> Result<TodoRecord, sqlx::Error> {
+    let list_id = match &todo.list {
+        Some(name) => Some(find_or_create_list(tx, user_id, name).await?),
+        None => None,
+    };
+    let todo: TodoRecord = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO todos (user_id, content, due_date, due_time, priority, list_id, tags)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING {}
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(&todo.content)
+    .bind(todo.due_date)
+    .bind(todo.due_time)
+    .bind(todo.priority.map(|p| p.as_i16()))
+    .bind(list_id)
+    .bind(&todo.tags)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    activity::record(tx, user_id, &todo, TodoAction::Created, source).await?;
+    Ok(todo)
+}
+
+// Newest first
+pub async fn list(
+    pool: &PgPool,
+    user_id: i32,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TodoRecord>, ServerFnError> {
+    sqlx::query_as(&format!(
+        "SELECT {} FROM todos WHERE user_id = $1 ORDER BY id DESC LIMIT $2 OFFSET $3",
+        TODO_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)
+}
+
+// Due from `from` to `to` inclusive, soonest first
+pub async fn list_due(
+    pool: &PgPool,
+    user_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+    limit: i64,
+) -> Result<Vec<TodoRecord>, ServerFnError> {
+    sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM todos
+        WHERE user_id = $1 AND due_date BETWEEN $2 AND $3
+        ORDER BY due_date, due_time NULLS FIRST, id
+        LIMIT $4
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(from)
+    .bind(to)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)
+}
+
+// Not completed, soonest due first and those without a due date last
+pub async fn list_open(
+    pool: &PgPool,
+    user_id: i32,
+    limit: i64,
+) -> Result<Vec<TodoRecord>, ServerFnError> {
+    sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM todos
+        WHERE user_id = $1 AND NOT completed
+        ORDER BY due_date NULLS LAST, due_time NULLS FIRST, id DESC
+        LIMIT $2
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)
+}
+
+pub async fn get(
+    pool: &PgPool,
+    user_id: i32,
+    id: i32,
+) -> Result<Option<TodoRecord>, ServerFnError> {
+    sqlx::query_as(&format!(
+        "SELECT {} FROM todos WHERE id = $1 AND user_id = $2",
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)
+}
+
+// Changes the given fields and leaves the others alone. With
+// `expected_version`, only if the todo is still at that version.
+pub async fn update(
+    pool: &PgPool,
+    user_id: i32,
+    id: i32,
+    changes: Changes<'_>,
+    expected_version: Option<i32>,
+    source: ChangeSource,
+) -> Result<Result<TodoRecord, Stale>, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+    let updated: Option<TodoRecord> = sqlx::query_as(&format!(
+        r#"
+        UPDATE todos
+        SET content = COALESCE($1, content),
+            notes = COALESCE($2, notes),
+            completed = COALESCE($3, completed),
+            version = version + 1,
+            updated_at = now()
+        WHERE id = $4 AND user_id = $5 AND ($6::INTEGER IS NULL OR version = $6)
+        RETURNING {}
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(changes.content)
+    .bind(changes.notes)
+    .bind(changes.completed)
+    .bind(id)
+    .bind(user_id)
+    .bind(expected_version)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let Some(todo) = updated else {
+        tx.rollback().await.map_err(sqlx_err_to_server_err)?;
+        return Ok(Err(stale(pool, user_id, id).await?));
+    };
+
+    // A text change is the interesting part when both change at once
+    let edited = changes.content.is_some() || changes.notes.is_some();
+    let action = match (edited, changes.completed) {
+        (false, Some(true)) => TodoAction::Completed,
+        (false, Some(false)) => TodoAction::Reopened,
+        _ => TodoAction::Edited,
+    };
+    activity::record(&mut tx, user_id, &todo, action, source)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(Ok(todo))
+}
+
+// Moves the todo to another day, keeping its time of day, or with None
+// takes its due date and time away. With `expected_version`, only if the
+// todo is still at that version.
+pub async fn reschedule(
+    pool: &PgPool,
+    user_id: i32,
+    id: i32,
+    due_date: Option<NaiveDate>,
+    expected_version: Option<i32>,
+    source: ChangeSource,
+) -> Result<Result<TodoRecord, Stale>, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+    let updated: Option<TodoRecord> = sqlx::query_as(&format!(
+        r#"
+        UPDATE todos
+        SET due_date = $1,
+            due_time = CASE WHEN $1::DATE IS NULL THEN NULL ELSE due_time END,
+            version = version + 1,
+            updated_at = now()
+        WHERE id = $2 AND user_id = $3 AND ($4::INTEGER IS NULL OR version = $4)
+        RETURNING {}
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(due_date)
+    .bind(id)
+    .bind(user_id)
+    .bind(expected_version)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let Some(todo) = updated else {
+        tx.rollback().await.map_err(sqlx_err_to_server_err)?;
+        return Ok(Err(stale(pool, user_id, id).await?));
+    };
+
+    activity::record(&mut tx, user_id, &todo, TodoAction::Rescheduled, source)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(Ok(todo))
+}
+
+// Puts the todo in one of the user's board columns, or with None in none.
+// Moving into a `marks_done` column completes it and moving out of one
+// reopens it. With `expected_version`, only if the todo is still at that
+// version.
+pub async fn set_status(
+    pool: &PgPool,
+    user_id: i32,
+    id: i32,
+    status_id: Option<i32>,
+    expected_version: Option<i32>,
+    source: ChangeSource,
+) -> Result<Result<TodoRecord, Stale>, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+    // Read in the same transaction, and held until it commits, so the column
+    // can't be deleted or change `marks_done` while the todo moves into it
+    let marks_done = match status_id {
+        Some(status_id) => sqlx::query_scalar(
+            "SELECT marks_done FROM board_columns WHERE id = $1 AND user_id = $2 FOR SHARE",
+        )
+        .bind(status_id)
+        .bind(user_id)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?
+        .ok_or_else(board::not_found)?,
+        None => false,
+    };
+
+    let updated = sqlx::query(&format!(
+        r#"
+        WITH old AS (
+            SELECT id AS todo_id, completed AS was_completed
+            FROM todos
+            WHERE id = $3 AND user_id = $4 AND ($5::INTEGER IS NULL OR version = $5)
+            FOR UPDATE
+        )
+        UPDATE todos
+        SET status_id = $1,
+            completed = CASE
+                WHEN $2 THEN true
+                WHEN EXISTS (
+                    SELECT 1 FROM board_columns c WHERE c.id = todos.status_id AND c.marks_done
+                ) THEN false
+                ELSE todos.completed
+            END,
+            version = version + 1,
+            updated_at = now()
+        FROM old
+        WHERE todos.id = old.todo_id
+        RETURNING {}, old.was_completed
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(status_id)
+    .bind(marks_done)
+    .bind(id)
+    .bind(user_id)
+    .bind(expected_version)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let Some(row) = updated else {
+        tx.rollback().await.map_err(sqlx_err_to_server_err)?;
+        return Ok(Err(stale(pool, user_id, id).await?));
+    };
+    let todo = TodoRecord::from_row(&row).map_err(sqlx_err_to_server_err)?;
+    let was_completed: bool = row
+        .try_get("was_completed")
+        .map_err(sqlx_err_to_server_err)?;
+
+    // Completing or reopening is the interesting part, as in `update`
+    let action = match (was_completed, todo.completed) {
+        (false, true) => TodoAction::Completed,
+        (true, false) => TodoAction::Reopened,
+        _ => TodoAction::StatusChanged,
+    };
+    activity::record(&mut tx, user_id, &todo, action, source)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(Ok(todo))
+}
+
+// Deletes one of the user's board columns. Its todos go back to no column
+// and keep their completion; each move counts as a change to the todo.
+// False if the user has no such column.
+pub async fn delete_status(
+    pool: &PgPool,
+    user_id: i32,
+    status_id: i32,
+    source: ChangeSource,
+) -> Result<bool, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+    // Locked first so no todo is moved into it meanwhile (see `set_status`)
+    let exists: Option<i32> = sqlx::query_scalar(
+        "SELECT id FROM board_columns WHERE id = $1 AND user_id = $2 FOR UPDATE",
+    )
+    .bind(status_id)
+    .bind(user_id)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+    if exists.is_none() {
+        tx.rollback().await.map_err(sqlx_err_to_server_err)?;
+        return Ok(false);
+    }
+
+    let moved: Vec<TodoRecord> = sqlx::query_as(&format!(
+        r#"
+        UPDATE todos SET status_id = NULL, version = version + 1, updated_at = now()
+        WHERE user_id = $1 AND status_id = $2
+        RETURNING {}
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(status_id)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+    for todo in &moved {
+        activity::record(&mut tx, user_id, todo, TodoAction::StatusChanged, source)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+    }
+
+    sqlx::query("DELETE FROM board_columns WHERE id = $1")
+        .bind(status_id)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(true)
+}
+
+// With `expected_version`, only if the todo is still at that version
+pub async fn delete(
+    pool: &PgPool,
+    user_id: i32,
+    id: i32,
+    expected_version: Option<i32>,
+    source: ChangeSource,
+) -> Result<Result<(), Stale>, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+    let deleted: Option<TodoRecord> = sqlx::query_as(&format!(
+        r#"
+        DELETE FROM todos
+        WHERE id = $1 AND user_id = $2 AND ($3::INTEGER IS NULL OR version = $3)
+        RETURNING {}
+        "#,
+        TODO_COLUMNS
+    ))
+    .bind(id)
+    .bind(user_id)
+    .bind(expected_version)
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let Some(todo) = deleted else {
+        tx.rollback().await.map_err(sqlx_err_to_server_err)?;
+        return Ok(Err(stale(pool, user_id, id).await?));
+    };
+
+    activity::record(&mut tx, user_id, &todo, TodoAction::Deleted, source)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(Ok(()))
+}
+
+// What `bulk` did: the requested ids the user owns, and the todos among them
+// that actually changed
+pub struct BulkOutcome {
+    pub found: Vec<i32>,
+    pub changed: Vec<TodoRecord>,
+}
+
+// Apply `action` to each of `ids` the user owns, all in one transaction.
+// Completing a todo that is already completed (or reopening an open one)
+// leaves it alone: no new version, no activity entry, no webhook.
+pub async fn bulk(
+    pool: &PgPool,
+    user_id: i32,
+    ids: &[i32],
+    action: &BulkAction,
+    source: ChangeSource,
+) -> Result<BulkOutcome, ServerFnError> {
+    let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+
+    let found: Vec<i32> = sqlx::query_scalar(
+        "SELECT id FROM todos WHERE user_id = $1 AND id = ANY($2) ORDER BY id FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(ids)
+    .fetch_all(&mut tx)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    let set = match action {
+        BulkAction::Delete => None,
+        BulkAction::SetCompleted(_) => Some("completed = $3"),
+        BulkAction::MoveToList(_) => Some("list_id = $3"),
+        BulkAction::AddTag(_) => {
+            Some("tags = CASE WHEN $3 = ANY(tags) THEN tags ELSE array_append(tags, $3) END")
+        }
+        BulkAction::RemoveTag(_) => Some("tags = array_remove(tags, $3)"),
+    };
+    // Rows the action would leave as they are
+    let unchanged = match action {
+        BulkAction::SetCompleted(_) => "AND completed <> $3",
+        _ => "",
+    };
+    let sql = match set {
+        Some(set) => format!(
+            r#"
+            UPDATE todos SET {}, version = version + 1, updated_at = now()
+            WHERE user_id = $1 AND id = ANY($2) {}
+            RETURNING {}
+            "#,
+            set, unchanged, TODO_COLUMNS
+        ),
+        None => format!(
+            "DELETE FROM todos WHERE user_id = $1 AND id = ANY($2) RETURNING {}",
+            TODO_COLUMNS
+        ),
+    };
+
+    let query = sqlx::query_as(&sql).bind(user_id).bind(ids);
+    let query = match action {
+        BulkAction::Delete => query,
+        BulkAction::SetCompleted(completed) => query.bind(*completed),
+        BulkAction::MoveToList(name) => {
+            let list_id = match name {
+                Some(name) => Some(
+                    find_or_create_list(&mut tx, user_id, name)
+                        .await
+                        .map_err(sqlx_err_to_server_err)?,
+                ),
+                None => None,
+            };
+            query.bind(list_id)
+        }
+        BulkAction::AddTag(tag) | BulkAction::RemoveTag(tag) => query.bind(tag.as_str()),
+    };
+    let changed: Vec<TodoRecord> = query
+        .fetch_all(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+    let event = match action {
+        BulkAction::Delete => TodoAction::Deleted,
+        BulkAction::SetCompleted(true) => TodoAction::Completed,
+        BulkAction::SetCompleted(false) => TodoAction::Reopened,
+        BulkAction::MoveToList(_) => TodoAction::Moved,
+        BulkAction::AddTag(_) => TodoAction::Tagged,
+        BulkAction::RemoveTag(_) => TodoAction::Untagged,
+    };
+    for todo in &changed {
+        activity::record(&mut tx, user_id, todo, event, source)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+    }
+    tx.commit().await.map_err(sqlx_err_to_server_err)?;
+    Ok(BulkOutcome { found, changed })
+}
+
+// The user's list with this name (ignoring case), created if needed
+async fn find_or_create_list(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    name: &str,
+) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO todo_lists (user_id, name) VALUES ($1, $2)
+        ON CONFLICT (user_id, lower(name)) DO UPDATE SET name = todo_lists.name
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(name.trim())
+    .fetch_one(tx)
+    .await
+}
+
+// Why a write matched no row
+async fn stale(pool: &PgPool, user_id: i32, id: i32) -> Result<Stale, ServerFnError> {
+    Ok(match get(pool, user_id, id).await? {
+        Some(todo) => Stale::Changed {
+            current_version: todo.version,
+        },
+        None => Stale::Missing,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::server_utils::{test_db_pool, test_user};
+
+    async fn add(pool: &PgPool, user_id: i32, content: &str) -> TodoRecord {
+        insert(
+            pool,
+            user_id,
+            &NewTodo::plain(content.to_string()),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn actions(pool: &PgPool, todo_id: i32) -> Vec<String> {
+        sqlx::query_scalar("SELECT action FROM todo_events WHERE todo_id = $1 ORDER BY id")
+            .bind(todo_id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn bulk_complete_only_touches_todos_that_change() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let done = add(&pool, user_id, "Already done").await;
+        let open = add(&pool, user_id, "Still open").await;
+        let complete = BulkAction::SetCompleted(true);
+        bulk(&pool, user_id, &[done.id], &complete, ChangeSource::App)
+            .await
+            .unwrap();
+
+        let missing = open.id + 1_000_000;
+        let outcome = bulk(
+            &pool,
+            user_id,
+            &[done.id, open.id, missing],
+            &complete,
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome.found, vec![done.id, open.id]);
+        let changed: Vec<i32> = outcome.changed.iter().map(|todo| todo.id).collect();
+        assert_eq!(changed, vec![open.id]);
+        assert!(outcome.changed[0].completed);
+
+        let done_now = get(&pool, user_id, done.id).await.unwrap().unwrap();
+        assert_eq!(done_now.version, done.version + 1);
+        assert_eq!(actions(&pool, done.id).await, vec!["created", "completed"]);
+        assert_eq!(actions(&pool, open.id).await, vec!["created", "completed"]);
+    }
+
+    #[tokio::test]
+    async fn bulk_reopen_skips_open_todos() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let open = add(&pool, user_id, "Open").await;
+
+        let outcome = bulk(
+            &pool,
+            user_id,
+            &[open.id],
+            &BulkAction::SetCompleted(false),
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome.found, vec![open.id]);
+        assert!(outcome.changed.is_empty());
+        assert_eq!(actions(&pool, open.id).await, vec!["created"]);
+    }
+
+    #[tokio::test]
+    async fn bulk_ignores_other_users_todos() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (owner, _) = test_user(&pool).await;
+        let (other, _) = test_user(&pool).await;
+        let todo = add(&pool, owner, "Mine").await;
+
+        let outcome = bulk(
+            &pool,
+            other,
+            &[todo.id],
+            &BulkAction::Delete,
+            ChangeSource::App,
+        )
+        .await
+        .unwrap();
+
+        assert!(outcome.found.is_empty());
+        assert!(outcome.changed.is_empty());
+        assert!(get(&pool, owner, todo.id).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,529 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use super::{get_user_id, reauthenticate};
+#[cfg(feature = "server")]
+use crate::metrics;
+#[cfg(feature = "server")]
+use crate::rate_limit::{self, Action};
+#[cfg(feature = "server")]
+use crate::secrets;
+#[cfg(feature = "server")]
+use crate::session;
+#[cfg(feature = "server")]
+use crate::telemetry::{self, observe};
+#[cfg(feature = "server")]
+use std::collections::HashMap;
+#[cfg(feature = "server")]
+use std::sync::{LazyLock, Mutex};
+#[cfg(feature = "server")]
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+#[cfg(feature = "server")]
+use totp_rs::{Algorithm, TOTP};
+
+// What the user needs to add the account to an authenticator app
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub otpauth_uri: String,
+    // Base32 secret for manual entry
+    pub secret: String,
+    // PNG of the otpauth URI, base64 encoded
+    pub qr_png_base64: String,
+}
+
+#[cfg(feature = "server")]
+const ISSUER: &str = "Todo list";
+#[cfg(feature = "server")]
+const TOTP_STEP_SECS: u64 = 30;
+#[cfg(feature = "server")]
+const RECOVERY_CODE_COUNT: usize = 10;
+#[cfg(feature = "server")]
+const RECOVERY_CODE_BYTES: usize = 10;
+// How long the second login step may take, and how many codes it may try
+#[cfg(feature = "server")]
+const CHALLENGE_TTL: Duration = Duration::from_secs(5 * 60);
+#[cfg(feature = "server")]
+const CHALLENGE_MAX_ATTEMPTS: u32 = 5;
+
+// Logins that passed the password check and are waiting for a code. Kept in
+// memory like the rate limiter, so a restart just means logging in again.
+#[cfg(feature = "server")]
+struct Challenge {
+    user_id: i32,
+    username: String,
+    expires_at: Instant,
+    attempts: u32,
+}
+
+#[cfg(feature = "server")]
+static CHALLENGES: LazyLock<Mutex<HashMap<String, Challenge>>> = LazyLock::new(Default::default);
+
+// Called by `login` once the password is correct; returns the challenge id
+// the client sends back with the code
+#[cfg(feature = "server")]
+pub(super) fn start_challenge(user_id: i32, username: &str) -> String {
+    let challenge = secrets::generate_token();
+    let now = Instant::now();
+    let mut challenges = CHALLENGES.lock().unwrap();
+    challenges.retain(|_, pending| pending.expires_at > now);
+    challenges.insert(
+        secrets::hash_token(&challenge),
+        Challenge {
+            user_id,
+            username: username.to_string(),
+            expires_at: now + CHALLENGE_TTL,
+            attempts: 0,
+        },
+    );
+    challenge
+}
+
+// Second login step. `code` is either the current authenticator code or an
+// unused recovery code. Returns the username to log in as.
+#[server]
+pub async fn verify_two_factor(challenge: String, code: String) -> Result<String, ServerFnError> {
+    observe("verify_two_factor", "", async {
+        let expired =
+            || ServerFnError::ServerError("Login expired, please start again".to_string());
+        let key = secrets::hash_token(&challenge);
+
+        let (user_id, username) = {
+            let mut challenges = CHALLENGES.lock().unwrap();
+            let pending = challenges.get_mut(&key).ok_or_else(expired)?;
+            if pending.expires_at <= Instant::now() || pending.attempts >= CHALLENGE_MAX_ATTEMPTS {
+                challenges.remove(&key);
+                return Err(expired());
+            }
+            pending.attempts += 1;
+            (pending.user_id, pending.username.clone())
+        };
+        telemetry::record_user_id(user_id);
+        rate_limit::check(Action::Login, &username)?;
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let code = code.trim();
+
+        let accepted = if code.len() == 6 && code.chars().all(|c| c.is_ascii_digit()) {
+            verify_totp_code(pool, user_id, &username, code).await?
+        } else {
+            redeem_recovery_code(pool, user_id, code).await?
+        };
+
+        if !accepted {
+            tracing::info!(reason = "invalid_code", "Two-factor verification rejected");
+            metrics::record_login_failure("invalid_two_factor_code");
+            rate_limit::record_failure(&username);
+            return Err(ServerFnError::ServerError("Invalid code".to_string()));
+        }
+
+        CHALLENGES.lock().unwrap().remove(&key);
+        // Later calls for this account are only accepted with this session
+        session::start(pool, user_id).await?;
+        rate_limit::record_success(&username);
+        metrics::record_login("two_factor");
+        tracing::info!("User logged in with two-factor");
+        Ok(username)
+    })
+    .await
+}
+
+#[server]
+pub async fn two_factor_enabled(username: String) -> Result<bool, ServerFnError> {
+    observe("two_factor_enabled", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        sqlx::query_scalar("SELECT totp_secret IS NOT NULL FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)
+    })
+    .await
+}
+
+// Generate a new secret and keep it pending until the user proves their
+// authenticator has it with `confirm_totp_enrollment`
+#[server]
+pub async fn begin_totp_enrollment(
+    username: String,
+    password: String,
+) -> Result<TotpEnrollment, ServerFnError> {
+    observe("begin_totp_enrollment", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let totp = build_totp(secrets::random_bytes(20), &username)?;
+        let encrypted = secrets::encrypt(&totp.secret).map_err(encryption_err)?;
+
+        sqlx::query("UPDATE users SET totp_pending_secret = $1 WHERE id = $2")
+            .bind(encrypted)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+
+        Ok(TotpEnrollment {
+            otpauth_uri: totp.get_url(),
+            secret: totp.get_secret_base32(),
+            qr_png_base64: totp.get_qr_base64().map_err(totp_err)?,
+        })
+    })
+    .await
+}
+
+// Turn 2FA on once a code from the pending secret checks out. Returns fresh
+// recovery codes, which are only ever shown this once.
+#[server]
+pub async fn confirm_totp_enrollment(
+    username: String,
+    code: String,
+) -> Result<Vec<String>, ServerFnError> {
+    observe("confirm_totp_enrollment", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let pending: Option<String> =
+            sqlx::query_scalar("SELECT totp_pending_secret FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(pool)
+                .await
+                .map_err(sqlx_err_to_server_err)?;
+        let Some(pending) = pending else {
+            return Err(ServerFnError::ServerError(
+                "Start two-factor setup first".to_string(),
+            ));
+        };
+
+        let totp = build_totp(
+            secrets::decrypt(&pending).map_err(encryption_err)?,
+            &username,
+        )?;
+        let Some(step) = matching_step(&totp, code.trim(), unix_now()) else {
+            return Err(ServerFnError::ServerError("Invalid code".to_string()));
+        };
+
+        let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+
+        let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET totp_secret = totp_pending_secret, totp_pending_secret = NULL, totp_last_step = $1
+            WHERE id = $2
+            "#,
+        )
+        .bind(step as i64)
+        .bind(user_id)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        replace_recovery_codes(&mut tx, user_id, &recovery_codes).await?;
+        tx.commit().await.map_err(sqlx_err_to_server_err)?;
+        // From now on the account needs a session, so start one for the
+        // client that just proved it has the authenticator
+        session::start(pool, user_id).await?;
+
+        tracing::info!("Two-factor authentication enabled");
+        Ok(recovery_codes)
+    })
+    .await
+}
+
+#[server]
+pub async fn disable_two_factor(username: String, password: String) -> Result<(), ServerFnError> {
+    observe("disable_two_factor", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let mut tx = pool.begin().await.map_err(sqlx_err_to_server_err)?;
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET totp_secret = NULL, totp_pending_secret = NULL, totp_last_step = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+        replace_recovery_codes(&mut tx, user_id, &[]).await?;
+        tx.commit().await.map_err(sqlx_err_to_server_err)?;
+
+        tracing::info!("Two-factor authentication disabled");
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(feature = "server")]
+fn build_totp(secret: Vec<u8>, username: &str) -> Result<TOTP, ServerFnError> {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        TOTP_STEP_SECS,
+        secret,
+        Some(ISSUER.to_string()),
+        username.to_string(),
+    )
+    .map_err(totp_err)
+}
+
+// The time step `code` is valid for at `now` (Unix seconds), allowing one
+// step of clock skew
+#[cfg(feature = "server")]
+fn matching_step(totp: &TOTP, code: &str, now: u64) -> Option<u64> {
+    let current = now / TOTP_STEP_SECS;
+    [current.saturating_sub(1), current, current + 1]
+        .into_iter()
+        .find(|step| totp.generate(step * TOTP_STEP_SECS) == code)
+}
+
+// Check a code against the enabled secret, refusing a step that was already
+// used so an observed code can't be replayed
+#[cfg(feature = "server")]
+async fn verify_totp_code(
+    pool: &sqlx::PgPool,
+    user_id: i32,
+    username: &str,
+    code: &str,
+) -> Result<bool, ServerFnError> {
+    let secret: Option<String> = sqlx::query_scalar("SELECT totp_secret FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+    let Some(secret) = secret else {
+        return Ok(false);
+    };
+
+    let totp = build_totp(secrets::decrypt(&secret).map_err(encryption_err)?, username)?;
+    let Some(step) = matching_step(&totp, code, unix_now()) else {
+        return Ok(false);
+    };
+    claim_step(pool, user_id, step).await
+}
+
+// Record `step` as used unless it, or a later one, already was. A
+// conditional update, so two concurrent requests can't both use the step.
+#[cfg(feature = "server")]
+async fn claim_step(pool: &sqlx::PgPool, user_id: i32, step: u64) -> Result<bool, ServerFnError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users SET totp_last_step = $1
+        WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)
+        "#,
+    )
+    .bind(step as i64)
+    .bind(user_id)
+    .execute(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    if result.rows_affected() == 0 {
+        tracing::info!(step, "Rejected reused two-factor code");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+#[cfg(feature = "server")]
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+#[cfg(feature = "server")]
+async fn redeem_recovery_code(
+    pool: &sqlx::PgPool,
+    user_id: i32,
+    code: &str,
+) -> Result<bool, ServerFnError> {
+    let result = sqlx::query(
+        r#"
+        UPDATE totp_recovery_codes SET used_at = now()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(secrets::hash_token(&normalize_recovery_code(code)))
+    .execute(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    if result.rows_affected() > 0 {
+        tracing::info!("Recovery code used");
+    }
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(feature = "server")]
+async fn replace_recovery_codes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    codes: &[String],
+) -> Result<(), ServerFnError> {
+    sqlx::query("DELETE FROM totp_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+    for code in codes {
+        sqlx::query("INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(secrets::hash_token(&normalize_recovery_code(code)))
+            .execute(&mut *tx)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+    }
+    Ok(())
+}
+
+// 80 random bits as twenty hex characters, shown as "xxxxx-xxxxx-xxxxx-xxxxx".
+// Long enough that the unsalted hash we store can't be brute-forced back
+// into a usable code.
+#[cfg(feature = "server")]
+fn generate_recovery_code() -> String {
+    let raw = hex::encode(secrets::random_bytes(RECOVERY_CODE_BYTES));
+    raw.as_bytes()
+        .chunks(5)
+        .map(|group| std::str::from_utf8(group).unwrap())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(feature = "server")]
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_lowercase()
+}
+
+#[cfg(feature = "server")]
+fn totp_err(e: impl std::fmt::Display) -> ServerFnError {
+    tracing::error!(error = %e, "TOTP error");
+    ServerFnError::ServerError("Two-factor setup failed".to_string())
+}
+
+#[cfg(feature = "server")]
+fn encryption_err(e: String) -> ServerFnError {
+    tracing::error!(error = %e, "Secret encryption error");
+    ServerFnError::ServerError(
+        "Two-factor authentication is not configured on the server".to_string(),
+    )
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::backend::server_utils::{test_db_pool, test_user};
+
+    // RFC 6238 appendix B: the SHA-1 seed, and its codes truncated to 6 digits
+    const RFC_SEED: &[u8] = b"12345678901234567890";
+
+    fn rfc_totp() -> TOTP {
+        build_totp(RFC_SEED.to_vec(), "alice").unwrap()
+    }
+
+    #[test]
+    fn accepts_codes_from_one_step_either_side() {
+        let totp = rfc_totp();
+        // 1111111109 is step 37037036; its code is 081804 per RFC 6238
+        let now = 1_111_111_109;
+        let current = now / TOTP_STEP_SECS;
+        assert_eq!(totp.generate(now), "081804");
+        assert_eq!(matching_step(&totp, "081804", now), Some(current));
+
+        let previous = totp.generate(now - TOTP_STEP_SECS);
+        let next = totp.generate(now + TOTP_STEP_SECS);
+        assert_eq!(matching_step(&totp, &previous, now), Some(current - 1));
+        assert_eq!(matching_step(&totp, &next, now), Some(current + 1));
+
+        let too_old = totp.generate(now - 2 * TOTP_STEP_SECS);
+        let too_new = totp.generate(now + 2 * TOTP_STEP_SECS);
+        assert_eq!(matching_step(&totp, &too_old, now), None);
+        assert_eq!(matching_step(&totp, &too_new, now), None);
+        assert_eq!(matching_step(&totp, "", now), None);
+    }
+
+    #[test]
+    fn the_window_stops_at_step_zero() {
+        let totp = rfc_totp();
+        assert_eq!(matching_step(&totp, &totp.generate(0), 10), Some(0));
+    }
+
+    #[test]
+    fn recovery_codes_normalize_to_lowercase_hex() {
+        assert_eq!(
+            normalize_recovery_code("  ABCDE-12345-FFFFF-00000\n"),
+            "abcde12345fffff00000"
+        );
+        assert_eq!(
+            normalize_recovery_code("abcde12345fffff00000"),
+            normalize_recovery_code("ABCDE-12345-FFFFF-00000")
+        );
+    }
+
+    #[test]
+    fn recovery_codes_carry_80_bits() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 23);
+        assert_eq!(code.matches('-').count(), 3);
+        let normalized = normalize_recovery_code(&code);
+        assert_eq!(normalized.len(), 20);
+        assert!(normalized.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(generate_recovery_code(), code);
+    }
+
+    #[tokio::test]
+    async fn a_step_can_only_be_used_once() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+
+        assert!(claim_step(&pool, user_id, 100).await.unwrap());
+        // Replaying the same code, or an older one from the window
+        assert!(!claim_step(&pool, user_id, 100).await.unwrap());
+        assert!(!claim_step(&pool, user_id, 99).await.unwrap());
+        assert!(claim_step(&pool, user_id, 101).await.unwrap());
+
+        let last_step: Option<i64> =
+            sqlx::query_scalar("SELECT totp_last_step FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(last_step, Some(101));
+    }
+
+    #[tokio::test]
+    async fn recovery_codes_work_once_in_any_format() {
+        let Some(pool) = test_db_pool().await else {
+            return;
+        };
+        let (user_id, _) = test_user(&pool).await;
+        let codes = vec![generate_recovery_code(), generate_recovery_code()];
+        let mut tx = pool.begin().await.unwrap();
+        replace_recovery_codes(&mut tx, user_id, &codes)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let typed = codes[0].replace('-', "").to_uppercase();
+        assert!(redeem_recovery_code(&pool, user_id, &typed).await.unwrap());
+        assert!(!redeem_recovery_code(&pool, user_id, &codes[0])
+            .await
+            .unwrap());
+        assert!(redeem_recovery_code(&pool, user_id, &codes[1])
+            .await
+            .unwrap());
+    }
+}
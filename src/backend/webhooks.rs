@@ -0,0 +1,448 @@
+// Webhooks: URLs that get a signed JSON POST when one of the user's todos
+// is created, completed, updated or deleted. `enqueue` queues a delivery in
+// the same transaction as the change (it is called from `activity::record`),
+// and the worker in `crate::webhooks` sends it, retrying with backoff. Every
+// delivery stays in the log the Account page shows.
+//
+// Each webhook has its own signing secret, shown once on creation and stored
+// encrypted, since signing needs it back.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use super::activity::{ChangeSource, TodoAction};
+#[cfg(feature = "server")]
+use super::server_utils::{get_db_pool, sqlx_err_to_server_err};
+#[cfg(feature = "server")]
+use super::todo_store::TodoRecord;
+#[cfg(feature = "server")]
+use super::{get_user_id, reauthenticate};
+#[cfg(feature = "server")]
+use crate::secrets;
+#[cfg(feature = "server")]
+use crate::telemetry::observe;
+
+#[cfg(feature = "server")]
+const SECRET_PREFIX: &str = "whsec_";
+#[cfg(feature = "server")]
+const MAX_WEBHOOKS: i64 = 10;
+#[cfg(feature = "server")]
+const URL_MAX_LEN: usize = 2_000;
+// Deliveries `list_webhook_deliveries` returns
+#[cfg(feature = "server")]
+const DELIVERY_LOG_LIMIT: i64 = 50;
+
+// What a webhook can subscribe to. Every other change (editing, reopening,
+// moving, tagging, rescheduling, changing status) is `Updated`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    Created,
+    Completed,
+    Updated,
+    Deleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [WebhookEvent; 4] = [
+        WebhookEvent::Created,
+        WebhookEvent::Completed,
+        WebhookEvent::Updated,
+        WebhookEvent::Deleted,
+    ];
+
+    // Stored in the database and sent as the payload's "event"
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::Created => "todo.created",
+            WebhookEvent::Completed => "todo.completed",
+            WebhookEvent::Updated => "todo.updated",
+            WebhookEvent::Deleted => "todo.deleted",
+        }
+    }
+
+    pub fn parse(event: &str) -> Option<Self> {
+        WebhookEvent::ALL.into_iter().find(|e| e.as_str() == event)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WebhookEvent::Created => "Todo added",
+            WebhookEvent::Completed => "Todo completed",
+            WebhookEvent::Updated => "Todo changed",
+            WebhookEvent::Deleted => "Todo deleted",
+        }
+    }
+
+    #[cfg(feature = "server")]
+    fn for_action(action: TodoAction) -> Self {
+        match action {
+            TodoAction::Created => WebhookEvent::Created,
+            TodoAction::Completed => WebhookEvent::Completed,
+            TodoAction::Deleted => WebhookEvent::Deleted,
+            TodoAction::Edited
+            | TodoAction::Reopened
+            | TodoAction::Moved
+            | TodoAction::Tagged
+            | TodoAction::Untagged
+            | TodoAction::Rescheduled
+            | TodoAction::StatusChanged => WebhookEvent::Updated,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    // Not sent yet, or waiting for a retry
+    Pending,
+    Delivered,
+    // Out of attempts
+    Failed,
+}
+
+impl DeliveryStatus {
+    #[cfg(feature = "server")]
+    pub const ALL: [DeliveryStatus; 3] = [
+        DeliveryStatus::Pending,
+        DeliveryStatus::Delivered,
+        DeliveryStatus::Failed,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    #[cfg(feature = "server")]
+    pub fn parse(status: &str) -> Option<Self> {
+        DeliveryStatus::ALL
+            .into_iter()
+            .find(|s| s.as_str() == status)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebhookInfo {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<WebhookEvent>,
+    pub created_at: String,
+}
+
+// Returned once on creation; afterwards the secret is only used for signing
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NewWebhook {
+    pub info: WebhookInfo,
+    pub secret: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    // One of the `WebhookEvent` names, or "ping" for a test
+    pub event: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    // HTTP status of the last attempt, if the receiver answered
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    // When a pending delivery is tried next
+    pub next_attempt_at: Option<String>,
+}
+
+// Adding a webhook needs the password, as it sends the user's todos elsewhere
+#[server]
+pub async fn create_webhook(
+    username: String,
+    password: String,
+    url: String,
+    events: Vec<WebhookEvent>,
+) -> Result<NewWebhook, ServerFnError> {
+    observe("create_webhook", &username, async {
+        let url = url.trim().to_string();
+        check_url(&url)?;
+        if events.is_empty() {
+            return Err(ServerFnError::ServerError(
+                "Pick at least one event".to_string(),
+            ));
+        }
+
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = reauthenticate(pool, &username, &password).await?;
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhooks WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+        if count >= MAX_WEBHOOKS {
+            return Err(ServerFnError::ServerError(format!(
+                "You can have at most {} webhooks; delete one first",
+                MAX_WEBHOOKS
+            )));
+        }
+
+        let secret = format!("{}{}", SECRET_PREFIX, secrets::generate_token());
+        let encrypted = secrets::encrypt(secret.as_bytes()).map_err(encryption_err)?;
+        let event_names: Vec<String> = WebhookEvent::ALL
+            .into_iter()
+            .filter(|event| events.contains(event))
+            .map(|event| event.as_str().to_string())
+            .collect();
+
+        let row: WebhookRow = sqlx::query_as(&format!(
+            r#"
+            INSERT INTO webhooks (user_id, url, secret, events)
+            VALUES ($1, $2, $3, $4)
+            RETURNING {}
+            "#,
+            WEBHOOK_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(&url)
+        .bind(encrypted)
+        .bind(&event_names)
+        .fetch_one(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        tracing::info!(webhook_id = row.0, "Webhook created");
+        Ok(NewWebhook {
+            info: webhook_info(row),
+            secret,
+        })
+    })
+    .await
+}
+
+#[server]
+pub async fn list_webhooks(username: String) -> Result<Vec<WebhookInfo>, ServerFnError> {
+    observe("list_webhooks", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let rows: Vec<WebhookRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhooks WHERE user_id = $1 ORDER BY id",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(rows.into_iter().map(webhook_info).collect())
+    })
+    .await
+}
+
+// Also drops its delivery log, including anything not yet sent
+#[server]
+pub async fn delete_webhook(username: String, id: i32) -> Result<(), ServerFnError> {
+    observe("delete_webhook", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = $1 AND user_id = $2")
+            .bind(id)
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+
+        if result.rows_affected() == 0 {
+            return Err(not_found());
+        }
+        tracing::info!(webhook_id = id, "Webhook deleted");
+        Ok(())
+    })
+    .await
+}
+
+// Queues a "ping" delivery, to check the receiver and its signature check
+#[server]
+pub async fn send_test_webhook(username: String, id: i32) -> Result<(), ServerFnError> {
+    observe("send_test_webhook", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let payload = serde_json::json!({
+            "event": "ping",
+            "occurred_at": chrono::Utc::now(),
+            "webhook_id": id,
+        })
+        .to_string();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload)
+            SELECT id, 'ping', $3 FROM webhooks WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(payload)
+        .execute(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        if result.rows_affected() == 0 {
+            return Err(not_found());
+        }
+        Ok(())
+    })
+    .await
+}
+
+// The webhook's latest deliveries, newest first
+#[server]
+pub async fn list_webhook_deliveries(
+    username: String,
+    webhook_id: i32,
+) -> Result<Vec<WebhookDelivery>, ServerFnError> {
+    observe("list_webhook_deliveries", &username, async {
+        let pool = get_db_pool().await.map_err(sqlx_err_to_server_err)?;
+        let user_id = get_user_id(pool, &username).await?;
+
+        let rows: Vec<DeliveryRow> = sqlx::query_as(
+            r#"
+            SELECT d.id, d.event, d.status, d.attempts, d.response_status, d.last_error,
+                   to_char(d.created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS "UTC"'),
+                   CASE WHEN d.status = 'pending' THEN
+                       to_char(d.next_attempt_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS "UTC"')
+                   END
+            FROM webhook_deliveries d
+            JOIN webhooks w ON w.id = d.webhook_id
+            WHERE d.webhook_id = $1 AND w.user_id = $2
+            ORDER BY d.id DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(user_id)
+        .bind(DELIVERY_LOG_LIMIT)
+        .fetch_all(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+        Ok(rows.into_iter().filter_map(delivery).collect())
+    })
+    .await
+}
+
+// Queue a delivery of the change `tx` has just made to `todo` for each of
+// the user's webhooks that wants it. Sent once `tx` commits.
+#[cfg(feature = "server")]
+pub(crate) async fn enqueue(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: i32,
+    todo: &TodoRecord,
+    action: TodoAction,
+    source: ChangeSource,
+) -> Result<(), sqlx::Error> {
+    let event = WebhookEvent::for_action(action);
+    // The todo as the REST API returns it
+    let payload = serde_json::json!({
+        "event": event.as_str(),
+        "occurred_at": chrono::Utc::now(),
+        "source": source.as_str(),
+        "todo": crate::api::Todo::from(todo.clone()),
+    })
+    .to_string();
+
+    sqlx::query(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, event, payload)
+        SELECT id, $2, $3 FROM webhooks WHERE user_id = $1 AND $2 = ANY(events)
+        "#,
+    )
+    .bind(user_id)
+    .bind(event.as_str())
+    .bind(payload)
+    .execute(tx)
+    .await?;
+    Ok(())
+}
+
+// Only http(s) URLs with a host; where they point is checked on delivery
+#[cfg(feature = "server")]
+fn check_url(url: &str) -> Result<(), ServerFnError> {
+    let invalid = |message: &str| Err(ServerFnError::ServerError(message.to_string()));
+    if url.len() > URL_MAX_LEN {
+        return invalid("That URL is too long");
+    }
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return invalid("Enter a full URL, like https://example.com/hooks/todo");
+    };
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return invalid("Webhook URLs must start with http:// or https://");
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return invalid("Webhook URLs can't contain a username or password");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+fn not_found() -> ServerFnError {
+    ServerFnError::ServerError("Webhook not found".to_string())
+}
+
+#[cfg(feature = "server")]
+fn encryption_err(e: String) -> ServerFnError {
+    tracing::error!(error = %e, "Secret encryption error");
+    ServerFnError::ServerError("Webhooks are not configured on the server".to_string())
+}
+
+#[cfg(feature = "server")]
+const WEBHOOK_COLUMNS: &str = r#"
+    id, url, events,
+    to_char(created_at AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI "UTC"')
+"#;
+
+#[cfg(feature = "server")]
+type WebhookRow = (i32, String, Vec<String>, String);
+
+#[cfg(feature = "server")]
+fn webhook_info((id, url, events, created_at): WebhookRow) -> WebhookInfo {
+    WebhookInfo {
+        id,
+        url,
+        events: events
+            .iter()
+            .filter_map(|e| WebhookEvent::parse(e))
+            .collect(),
+        created_at,
+    }
+}
+
+#[cfg(feature = "server")]
+type DeliveryRow = (
+    i64,
+    String,
+    String,
+    i32,
+    Option<i32>,
+    Option<String>,
+    String,
+    Option<String>,
+);
+
+// Rows with a status this build doesn't know are skipped
+#[cfg(feature = "server")]
+fn delivery(
+    (id, event, status, attempts, response_status, last_error, created_at, next_attempt_at): DeliveryRow,
+) -> Option<WebhookDelivery> {
+    Some(WebhookDelivery {
+        id,
+        event,
+        status: DeliveryStatus::parse(&status)?,
+        attempts,
+        response_status,
+        last_error,
+        created_at,
+        next_attempt_at,
+    })
+}
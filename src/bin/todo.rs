@@ -0,0 +1,530 @@
+// `todo`: a command-line client for the REST API.
+//
+// Build with `cargo build --bin todo --no-default-features --features cli`.
+// `todo login` stores the server URL and a personal API token (create one on
+// the Account page) in `~/.config/todo-cli/config.json`, or wherever
+// `TODO_CONFIG` points.
+
+use clap::{Parser, Subcommand};
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::{Method, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+// Same content rules as the server
+#[allow(dead_code)]
+#[path = "../validation.rs"]
+mod validation;
+
+type CliError = Box<dyn std::error::Error>;
+
+const DEFAULT_SERVER: &str = "http://localhost:8080";
+// Longest content shown in a table cell before it is cut short
+const CONTENT_COLUMN_WIDTH: usize = 60;
+
+#[derive(Parser)]
+#[command(name = "todo", about = "Manage your todos from the terminal")]
+struct Cli {
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Save the server URL and an API token
+    Login {
+        /// Server URL, e.g. https://todos.example.com
+        #[arg(long)]
+        server: Option<String>,
+        /// Personal API token (prompted for if omitted)
+        #[arg(long)]
+        token: Option<String>,
+    },
+    /// Forget the saved credentials
+    Logout,
+    /// Add a todo
+    Add {
+        #[arg(required = true)]
+        content: Vec<String>,
+    },
+    /// List todos, newest first
+    Ls {
+        /// Hide completed todos
+        #[arg(long)]
+        pending: bool,
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+    },
+    /// Mark todos as done
+    Done {
+        #[arg(required = true)]
+        ids: Vec<i32>,
+        /// Mark them as not done instead
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Delete todos
+    Rm {
+        #[arg(required = true)]
+        ids: Vec<i32>,
+    },
+    /// Replace a todo's text
+    Edit {
+        id: i32,
+        #[arg(required = true)]
+        content: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Config {
+    server: String,
+    token: String,
+    username: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Todo {
+    id: i32,
+    content: String,
+    completed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Me {
+    username: String,
+    scopes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    let json = cli.json;
+
+    match cli.command {
+        Command::Login { server, token } => {
+            let server = match server {
+                Some(server) => server,
+                None => prompt(&format!("Server [{}]: ", DEFAULT_SERVER))?,
+            };
+            let server = match server.trim().trim_end_matches('/') {
+                "" => DEFAULT_SERVER.to_string(),
+                server => server.to_string(),
+            };
+            let token = match token {
+                Some(token) => token,
+                None => rpassword::prompt_password("API token: ")?,
+            };
+            let token = token.trim().to_string();
+
+            let api = Api::new(server.clone(), token.clone());
+            let me: Me = api.send(api.request(Method::GET, "/me"))?;
+            save_config(&Config {
+                server: server.clone(),
+                token,
+                username: me.username.clone(),
+            })?;
+
+            if json {
+                print_json(&me)?;
+            } else {
+                println!(
+                    "Logged in to {} as {} ({})",
+                    server,
+                    me.username,
+                    me.scopes.join(", ")
+                );
+            }
+        }
+        Command::Logout => {
+            let path = config_path()?;
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            if !json {
+                println!("Logged out");
+            }
+        }
+        Command::Add { content } => {
+            let content = content.join(" ");
+            validation::validate_todo_content(&content)?;
+            let api = Api::from_config()?;
+            let todo: Todo = api.send(
+                api.request(Method::POST, "/todos")
+                    .json(&serde_json::json!({ "content": content })),
+            )?;
+            if json {
+                print_json(&todo)?;
+            } else {
+                println!("Added #{}", todo.id);
+            }
+        }
+        Command::Ls { pending, limit } => {
+            let api = Api::from_config()?;
+            let todos = collect_todos(limit, pending, |offset| {
+                api.send(
+                    api.request(Method::GET, "/todos")
+                        .query(&[("limit", limit), ("offset", offset)]),
+                )
+            })?;
+            if json {
+                print_json(&todos)?;
+            } else {
+                print_table(&todos);
+            }
+        }
+        Command::Done { ids, undo } => {
+            let api = Api::from_config()?;
+            let mut updated = Vec::new();
+            for id in ids {
+                let todo: Todo = api.send(
+                    api.request(Method::PATCH, &format!("/todos/{}", id))
+                        .json(&serde_json::json!({ "completed": !undo })),
+                )?;
+                if !json {
+                    let state = if todo.completed { "done" } else { "not done" };
+                    println!("#{} marked {}", todo.id, state);
+                }
+                updated.push(todo);
+            }
+            if json {
+                print_json(&updated)?;
+            }
+        }
+        Command::Rm { ids } => {
+            let api = Api::from_config()?;
+            for &id in &ids {
+                api.send_empty(api.request(Method::DELETE, &format!("/todos/{}", id)))?;
+                if !json {
+                    println!("Deleted #{}", id);
+                }
+            }
+            if json {
+                print_json(&serde_json::json!({ "deleted": ids }))?;
+            }
+        }
+        Command::Edit { id, content } => {
+            let content = content.join(" ");
+            validation::validate_todo_content(&content)?;
+            let api = Api::from_config()?;
+            let todo: Todo = api.send(
+                api.request(Method::PATCH, &format!("/todos/{}", id))
+                    .json(&serde_json::json!({ "content": content })),
+            )?;
+            if json {
+                print_json(&todo)?;
+            } else {
+                println!("Updated #{}", todo.id);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+struct Api {
+    client: Client,
+    server: String,
+    token: String,
+}
+
+impl Api {
+    fn new(server: String, token: String) -> Self {
+        Api {
+            client: Client::new(),
+            server,
+            token,
+        }
+    }
+
+    fn from_config() -> Result<Self, CliError> {
+        let config = load_config()?;
+        Ok(Api::new(config.server, config.token))
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/api/v1{}", self.server, path))
+            .bearer_auth(&self.token)
+    }
+
+    fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, CliError> {
+        let response = check_status(request.send()?)?;
+        Ok(response.json()?)
+    }
+
+    fn send_empty(&self, request: RequestBuilder) -> Result<(), CliError> {
+        check_status(request.send()?)?;
+        Ok(())
+    }
+}
+
+// Turn error responses into the server's `{"error": ...}` message
+fn check_status(
+    response: reqwest::blocking::Response,
+) -> Result<reqwest::blocking::Response, CliError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .json::<ErrorBody>()
+        .map(|body| body.error)
+        .unwrap_or_else(|_| status.to_string());
+    if status == StatusCode::UNAUTHORIZED {
+        return Err(format!("{} (run `todo login` again)", message).into());
+    }
+    Err(message.into())
+}
+
+fn print_table(todos: &[Todo]) {
+    if todos.is_empty() {
+        println!("No todos yet.");
+        return;
+    }
+    let id_width = todos
+        .iter()
+        .map(|todo| todo.id.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(2);
+
+    println!("{:>id_width$}  {:4}  CONTENT", "ID", "DONE");
+    for todo in todos {
+        let done = if todo.completed { "x" } else { "" };
+        println!(
+            "{:>id_width$}  {:4}  {}",
+            todo.id,
+            done,
+            summary(&todo.content)
+        );
+    }
+}
+
+// Up to `limit` todos, newest first. The API has no completed filter, so
+// with `pending` keep paging (`limit` at a time) until enough open todos
+// turn up or the list runs out.
+fn collect_todos(
+    limit: u32,
+    pending: bool,
+    mut fetch_page: impl FnMut(u32) -> Result<Vec<Todo>, CliError>,
+) -> Result<Vec<Todo>, CliError> {
+    let mut todos = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = fetch_page(offset)?;
+        let last_page = (page.len() as u32) < limit;
+        offset += page.len() as u32;
+        todos.extend(page.into_iter().filter(|todo| !pending || !todo.completed));
+        if last_page || todos.len() as u32 >= limit {
+            break;
+        }
+    }
+    todos.truncate(limit as usize);
+    Ok(todos)
+}
+
+// First line only, cut to fit the column
+fn summary(content: &str) -> String {
+    let first_line = content.lines().next().unwrap_or_default();
+    if first_line.chars().count() > CONTENT_COLUMN_WIDTH || content.lines().nth(1).is_some() {
+        let cut: String = first_line
+            .chars()
+            .take(CONTENT_COLUMN_WIDTH.saturating_sub(3))
+            .collect();
+        format!("{}...", cut)
+    } else {
+        first_line.to_string()
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), CliError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn prompt(label: &str) -> Result<String, CliError> {
+    print!("{}", label);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn config_path() -> Result<PathBuf, CliError> {
+    if let Ok(path) = std::env::var("TODO_CONFIG") {
+        return Ok(PathBuf::from(path));
+    }
+    let dir = dirs::config_dir().ok_or("could not find a config directory; set TODO_CONFIG")?;
+    Ok(dir.join("todo-cli").join("config.json"))
+}
+
+fn load_config() -> Result<Config, CliError> {
+    let path = config_path()?;
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|_| "not logged in (run `todo login` first)".to_string())?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+// The token is a credential, so the file is only readable by its owner
+fn save_config(config: &Config) -> Result<(), CliError> {
+    let path = config_path()?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // Write a fresh file and rename it over the old one: the mode only
+    // applies when a file is created, so rewriting an existing config in
+    // place would keep whatever permissions it had
+    let temp_path = path.with_extension("json.tmp");
+    match std::fs::remove_file(&temp_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&temp_path)?;
+    file.write_all(serde_json::to_string_pretty(config)?.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ids `1..=count`, newest first; every `done_every`th one completed
+    fn server_todos(count: i32, done_every: i32) -> Vec<(i32, bool)> {
+        (1..=count)
+            .rev()
+            .map(|id| (id, id % done_every == 0))
+            .collect()
+    }
+
+    // List like `GET /todos` would, recording the offsets asked for
+    fn list(todos: &[(i32, bool)], limit: u32, pending: bool) -> (Vec<i32>, Vec<u32>) {
+        let mut offsets = Vec::new();
+        let listed = collect_todos(limit, pending, |offset| {
+            offsets.push(offset);
+            Ok(todos
+                .iter()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .map(|&(id, completed)| Todo {
+                    id,
+                    content: format!("todo {}", id),
+                    completed,
+                })
+                .collect())
+        })
+        .unwrap();
+        (listed.iter().map(|todo| todo.id).collect(), offsets)
+    }
+
+    #[test]
+    fn lists_one_page_without_a_filter() {
+        let (ids, offsets) = list(&server_todos(10, 2), 3, false);
+        assert_eq!(ids, vec![10, 9, 8]);
+        assert_eq!(offsets, vec![0]);
+    }
+
+    #[test]
+    fn pages_until_enough_todos_are_pending() {
+        // 10, 8, 6, ... are done, so three pending todos take two pages
+        let (ids, offsets) = list(&server_todos(10, 2), 3, true);
+        assert_eq!(ids, vec![9, 7, 5]);
+        assert_eq!(offsets, vec![0, 3]);
+    }
+
+    #[test]
+    fn stops_when_the_list_runs_out() {
+        let (ids, offsets) = list(&server_todos(5, 1), 2, true);
+        assert!(ids.is_empty());
+        assert_eq!(offsets, vec![0, 2, 4]);
+
+        let (ids, offsets) = list(&[], 50, false);
+        assert!(ids.is_empty());
+        assert_eq!(offsets, vec![0]);
+    }
+
+    #[test]
+    fn parses_the_command_line() {
+        let cli =
+            Cli::try_parse_from(["todo", "ls", "--pending", "--limit", "5", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(
+            cli.command,
+            Command::Ls {
+                pending: true,
+                limit: 5
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["todo", "add", "buy", "milk"]).unwrap();
+        assert!(matches!(cli.command, Command::Add { content } if content == ["buy", "milk"]));
+
+        let cli = Cli::try_parse_from(["todo", "done", "--undo", "3", "4"]).unwrap();
+        assert!(matches!(cli.command, Command::Done { ids, undo: true } if ids == [3, 4]));
+
+        let cli = Cli::try_parse_from(["todo", "login", "--server", "https://todos.example.com"])
+            .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Login {
+                server: Some(_),
+                token: None
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_command_lines() {
+        for args in [
+            vec!["todo"],
+            vec!["todo", "add"],
+            vec!["todo", "rm"],
+            vec!["todo", "done", "three"],
+            vec!["todo", "edit", "1"],
+            vec!["todo", "ls", "--limit", "-1"],
+        ] {
+            assert!(Cli::try_parse_from(&args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn summary_fits_the_column() {
+        assert_eq!(summary("Buy milk"), "Buy milk");
+        assert_eq!(summary(""), "");
+        assert_eq!(summary("Buy milk\nand eggs"), "Buy milk...");
+
+        let exact = "x".repeat(CONTENT_COLUMN_WIDTH);
+        assert_eq!(summary(&exact), exact);
+
+        let long = "é".repeat(CONTENT_COLUMN_WIDTH + 1);
+        let cut = summary(&long);
+        assert_eq!(cut.chars().count(), CONTENT_COLUMN_WIDTH);
+        assert!(cut.ends_with("..."));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::backend::{change_password, delete_account, get_email, set_email};
+use crate::components::api_tokens::ApiTokens;
+use crate::components::calendar_feed::CalendarFeed;
+use crate::components::sso::SetSsoPassword;
+use crate::components::two_factor::TwoFactorSettings;
+use crate::components::webhooks::Webhooks;
+use crate::errors::AppError;
+use crate::validation::{validate_email, validate_password};
+use crate::Route;
+use dioxus::prelude::*;
+
+// Account Settings Route: change password and delete account
+#[component]
+pub fn Account() -> Element {
+    let mut logged_in_user = use_context::<Signal<Option<String>>>();
+    let nav = navigator();
+
+    // Signals for the change password form
+    let mut current_password = use_signal(String::new);
+    let mut new_password = use_signal(String::new);
+    let mut confirm_password = use_signal(String::new);
+    let mut password_status = use_signal(String::new);
+
+    // Signals for the recovery email form
+    let mut email_input = use_signal(String::new);
+    let mut email_password = use_signal(String::new);
+    let mut email_status = use_signal(String::new);
+
+    // Current recovery email, refetched after it changes
+    let mut current_email = use_resource(move || {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => get_email(username).await,
+                None => Ok(None),
+            }
+        }
+    });
+
+    // Signals for the delete account form
+    let mut delete_password = use_signal(String::new);
+    let mut delete_confirmed = use_signal(|| false);
+    let mut delete_status = use_signal(String::new);
+
+    let Some(username) = logged_in_user.read().clone() else {
+        return rsx! {
+            p { "Please log in to manage your account." }
+            Link { to: Route::Home, "Go to login" }
+        };
+    };
+    let username_for_email = username.clone();
+    let username_for_password = username.clone();
+    let username_for_delete = username.clone();
+
+    rsx! {
+        div {
+            // --- Recovery Email Form ---
+            div { class: "auth-form",
+                h2 { "Recovery Email" }
+                match current_email.read().as_ref() {
+                    Some(Ok(Some(email))) => rsx! {
+                        p { "Password reset emails go to {email}." }
+                    },
+                    Some(Ok(None)) => rsx! {
+                        p { "No email set. Add one so you can reset a forgotten password." }
+                    },
+                    Some(Err(e)) => rsx! {
+                        p { color: "red", "Error loading email: {e}" }
+                    },
+                    None => rsx! {
+                        p { "Loading..." }
+                    },
+                }
+                p { "{email_status}" }
+                input {
+                    r#type: "text",
+                    placeholder: "Email (leave empty to remove)",
+                    value: "{email_input}",
+                    oninput: move |evt| {
+                        email_input.set(evt.value());
+                        email_status.set(String::new());
+                    },
+                }
+                input {
+                    r#type: "password",
+                    placeholder: "Current password",
+                    value: "{email_password}",
+                    oninput: move |evt| {
+                        email_password.set(evt.value());
+                        email_status.set(String::new());
+                    },
+                }
+                button {
+                    onclick: move |_| {
+                        let email = email_input.read().trim().to_string();
+                        let password = email_password.read().clone();
+                        if !email.is_empty() {
+                            if let Err(e) = validate_email(&email) {
+                                email_status.set(e);
+                                return;
+                            }
+                        }
+                        if password.is_empty() {
+                            email_status.set("Enter your current password.".to_string());
+                            return;
+                        }
+                        email_status.set("Saving...".to_string());
+                        let username = username_for_email.clone();
+                        let saved_message = if email.is_empty() {
+                            "Email removed."
+                        } else {
+                            "Email saved. Open the link we sent to verify it."
+                        };
+                        spawn(async move {
+                            match set_email(username, password, email).await {
+                                Ok(_) => {
+                                    email_status.set(saved_message.to_string());
+                                    email_input.set(String::new());
+                                    email_password.set(String::new());
+                                    current_email.restart();
+                                }
+                                Err(e) => {
+                                    eprintln!("Set email error: {:?}", e);
+                                    match AppError::from_server_error(&e) {
+                                        Some(app_error) => email_status.set(app_error.to_string()),
+                                        None => email_status.set(format!("Save failed: {}", e)),
+                                    }
+                                }
+                            }
+                        });
+                    },
+                    "Save Email"
+                }
+            }
+
+            hr {}
+
+            // --- First password for SSO-only accounts ---
+            SetSsoPassword { username: username.clone() }
+
+            // --- Change Password Form ---
+            div { class: "auth-form",
+                h2 { "Change Password" }
+                p { "{password_status}" }
+                input {
+                    r#type: "password",
+                    placeholder: "Current password",
+                    value: "{current_password}",
+                    oninput: move |evt| {
+                        current_password.set(evt.value());
+                        password_status.set(String::new());
+                    },
+                }
+                input {
+                    r#type: "password",
+                    placeholder: "New password",
+                    value: "{new_password}",
+                    oninput: move |evt| {
+                        new_password.set(evt.value());
+                        password_status.set(String::new());
+                    },
+                }
+                input {
+                    r#type: "password",
+                    placeholder: "Confirm new password",
+                    value: "{confirm_password}",
+                    oninput: move |evt| {
+                        confirm_password.set(evt.value());
+                        password_status.set(String::new());
+                    },
+                }
+                button {
+                    onclick: move |_| {
+                        let current = current_password.read().clone();
+                        let new = new_password.read().clone();
+                        if current.is_empty() {
+                            password_status.set("Enter your current password.".to_string());
+                            return;
+                        }
+                        if let Err(e) = validate_password(&new, &username_for_password) {
+                            password_status.set(e);
+                            return;
+                        }
+                        if *confirm_password.read() != new {
+                            password_status.set("Passwords do not match.".to_string());
+                            return;
+                        }
+                        password_status.set("Changing password...".to_string());
+                        let username = username_for_password.clone();
+                        spawn(async move {
+                            match change_password(username, current, new).await {
+                                Ok(_) => {
+                                    password_status.set("Password changed.".to_string());
+                                    current_password.set(String::new());
+                                    new_password.set(String::new());
+                                    confirm_password.set(String::new());
+                                }
+                                Err(e) => {
+                                    eprintln!("Change password error: {:?}", e);
+                                    match AppError::from_server_error(&e) {
+                                        Some(app_error) => password_status.set(app_error.to_string()),
+                                        None => password_status.set(format!("Change failed: {}", e)),
+                                    }
+                                }
+                            }
+                        });
+                    },
+                    "Change Password"
+                }
+            }
+
+            hr {}
+
+            // --- Two-Factor Authentication ---
+            TwoFactorSettings { username: username.clone() }
+
+            hr {}
+
+            // --- API Tokens ---
+            ApiTokens { username: username.clone() }
+
+            hr {}
+
+            // --- Calendar Feed ---
+            CalendarFeed { username: username.clone() }
+
+            hr {}
+
+            // --- Webhooks ---
+            Webhooks { username: username.clone() }
+
+            hr {}
+
+            // --- Delete Account Form ---
+            div { class: "auth-form",
+                h2 { "Delete Account" }
+                p { "This permanently deletes your account and all of your todos." }
+                p { class: "auth-status", "{delete_status}" }
+                input {
+                    r#type: "password",
+                    placeholder: "Password",
+                    value: "{delete_password}",
+                    oninput: move |evt| {
+                        delete_password.set(evt.value());
+                        delete_status.set(String::new());
+                    },
+                }
+                label {
+                    input {
+                        r#type: "checkbox",
+                        checked: delete_confirmed(),
+                        onchange: move |evt| delete_confirmed.set(evt.checked()),
+                    }
+                    " I understand this cannot be undone"
+                }
+                div {
+                    button {
+                        background_color: "#dc3545",
+                        onclick: move |_| {
+                            let password = delete_password.read().clone();
+                            if password.is_empty() {
+                                delete_status.set("Enter your password to confirm.".to_string());
+                                return;
+                            }
+                            if !delete_confirmed() {
+                                delete_status.set("Please tick the confirmation box.".to_string());
+                                return;
+                            }
+                            delete_status.set("Deleting account...".to_string());
+                            let username = username_for_delete.clone();
+                            spawn(async move {
+                                match delete_account(username, password).await {
+                                    Ok(_) => {
+                                        logged_in_user.set(None);
+                                        nav.push(Route::Home);
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Delete account error: {:?}", e);
+                                        match AppError::from_server_error(&e) {
+                                            Some(app_error) => delete_status.set(app_error.to_string()),
+                                            None => delete_status.set(format!("Delete failed: {}", e)),
+                                        }
+                                    }
+                                }
+                            });
+                        },
+                        "Delete Account"
+                    }
+                }
+            }
+        }
+    }
+}
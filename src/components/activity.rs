@@ -0,0 +1,94 @@
+use crate::backend::activity::{recent_activity, todo_history, TodoEvent};
+use crate::Route;
+use dioxus::prelude::*;
+
+// Activity Route: recent changes to the user's todos, or with `?todo=<id>`
+// the full history of one todo
+#[component]
+pub fn Activity(todo: String) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let todo_id = todo.parse::<usize>().ok();
+
+    let events = use_resource(use_reactive!(|todo_id| {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match (current_user, todo_id) {
+                (Some(username), Some(id)) => todo_history(username, id).await,
+                (Some(username), None) => recent_activity(username).await,
+                (None, _) => Ok(Vec::new()),
+            }
+        }
+    }));
+
+    if logged_in_user.read().is_none() {
+        return rsx! {
+            p { "Please log in to see your activity." }
+            Link { to: Route::Home, "Go to login" }
+        };
+    }
+
+    let title = match todo_id {
+        Some(id) => format!("History of todo #{}", id),
+        None => "Recent activity".to_string(),
+    };
+
+    rsx! {
+        div { class: "activity",
+            h2 { "{title}" }
+            if todo_id.is_some() {
+                Link {
+                    to: Route::Activity {
+                        todo: String::new(),
+                    },
+                    "All activity"
+                }
+            }
+            match events.read().as_ref() {
+                Some(Ok(events)) if events.is_empty() => rsx! {
+                    p { "Nothing has happened yet." }
+                },
+                Some(Ok(events)) => rsx! {
+                    ul {
+                        for event in events.iter().cloned() {
+                            ActivityItem { key: "{event.id}", event, show_link: todo_id.is_none() }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Could not load activity: {e}" }
+                },
+                None => rsx! {
+                    p { "Loading activity..." }
+                },
+            }
+        }
+    }
+}
+
+// "alice edited #12 in the app: Buy oat milk"
+#[component]
+pub fn ActivityItem(event: TodoEvent, show_link: bool) -> Element {
+    let action = event.action.label();
+    let source = event.source.label();
+
+    rsx! {
+        li {
+            span {
+                strong { "{event.actor}" }
+                " {action} "
+                if show_link {
+                    Link {
+                        to: Route::Activity {
+                            todo: event.todo_id.to_string(),
+                        },
+                        "#{event.todo_id}"
+                    }
+                } else {
+                    "#{event.todo_id}"
+                }
+                " {source}: {event.content}"
+            }
+            span { class: "activity-time", "{event.at}" }
+        }
+    }
+}
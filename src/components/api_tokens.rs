@@ -0,0 +1,209 @@
+use crate::backend::api_tokens::{
+    create_api_token, list_api_tokens, revoke_api_token, ApiScope, NewApiToken,
+};
+use crate::errors::AppError;
+use dioxus::prelude::*;
+
+// API Tokens section of the Account page: create, list and revoke personal tokens
+#[component]
+pub fn ApiTokens(username: String) -> Element {
+    let mut name = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut selected_scopes = use_signal(|| vec![ApiScope::TodosRead]);
+    let mut status = use_signal(String::new);
+    // Password the list was loaded with; listing and revoking need it too
+    let mut unlocked = use_signal(|| Option::<String>::None);
+
+    // The new token is only shown until the user dismisses it
+    let mut created = use_signal(|| Option::<NewApiToken>::None);
+
+    let username_for_list = username.clone();
+    let mut tokens = use_resource(move || {
+        let username = username_for_list.clone();
+        let current = unlocked.read().clone();
+        async move {
+            match current {
+                Some(current) => list_api_tokens(username, current).await.map(Some),
+                None => Ok(None),
+            }
+        }
+    });
+
+    let username_for_create = username.clone();
+    let username_for_revoke = username.clone();
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "API Tokens" }
+            p { "Personal tokens for the REST API at /api/v1 (see /api/v1/openapi.json)." }
+            p { "{status}" }
+
+            if let Some(new_token) = created.read().clone() {
+                div { class: "new-token",
+                    p { "Copy your new token now. It will not be shown again." }
+                    code { "{new_token.token}" }
+                    div {
+                        button { onclick: move |_| created.set(None), "Done" }
+                    }
+                }
+            }
+
+            match tokens.read().as_ref() {
+                Some(Ok(None)) => rsx! {
+                    p { "Enter your current password below to see your tokens." }
+                    button {
+                        onclick: move |_| {
+                            let current = password.read().clone();
+                            if current.is_empty() {
+                                status.set("Enter your current password.".to_string());
+                                return;
+                            }
+                            status.set(String::new());
+                            unlocked.set(Some(current));
+                        },
+                        "Show Tokens"
+                    }
+                },
+                Some(Ok(Some(list))) if list.is_empty() => rsx! {
+                    p { class: "no-todos-message", "No active tokens." }
+                },
+                Some(Ok(Some(list))) => rsx! {
+                    ul {
+                        for token in list.iter().cloned() {
+                            li { key: "{token.id}",
+                                span {
+                                    {
+                                        let scopes = token
+                                            .scopes
+                                            .iter()
+                                            .map(|scope| scope.as_str())
+                                            .collect::<Vec<_>>()
+                                            .join(", ");
+                                        let last_used = token
+                                            .last_used_at
+                                            .clone()
+                                            .unwrap_or_else(|| "never".to_string());
+                                        rsx! { "{token.name} ({scopes}), created {token.created_at}, last used {last_used}" }
+                                    }
+                                }
+                                button {
+                                    onclick: {
+                                        let username = username_for_revoke.clone();
+                                        move |_| {
+                                            let username = username.clone();
+                                            let Some(current) = unlocked.read().clone() else {
+                                                return;
+                                            };
+                                            spawn(async move {
+                                                match revoke_api_token(username, current, token.id).await {
+                                                    Ok(_) => {
+                                                        status.set("Token revoked.".to_string());
+                                                        tokens.restart();
+                                                    }
+                                                    Err(e) => {
+                                                        eprintln!("Revoke token error: {:?}", e);
+                                                        status.set(format!("Revoke failed: {}", e));
+                                                    }
+                                                }
+                                            });
+                                        }
+                                    },
+                                    "Revoke"
+                                }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Error loading tokens: {e}" }
+                    button { onclick: move |_| unlocked.set(None), "Try Again" }
+                },
+                None => rsx! {
+                    p { "Loading..." }
+                },
+            }
+
+            // --- Create Token Form ---
+            input {
+                r#type: "text",
+                placeholder: "Token name, e.g. laptop CLI",
+                value: "{name}",
+                oninput: move |evt| {
+                    name.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            for (scope, scope_name, scope_label) in ApiScope::ALL.map(|s| (s, s.as_str(), s.label())) {
+                label { key: "{scope_name}",
+                    input {
+                        r#type: "checkbox",
+                        checked: selected_scopes.read().contains(&scope),
+                        onchange: move |evt| {
+                            let mut scopes = selected_scopes.write();
+                            scopes.retain(|s| *s != scope);
+                            if evt.checked() {
+                                scopes.push(scope);
+                            }
+                        },
+                    }
+                    " {scope_label} ({scope_name})"
+                }
+            }
+            input {
+                r#type: "password",
+                placeholder: "Current password",
+                value: "{password}",
+                oninput: move |evt| {
+                    password.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            button {
+                onclick: move |_| {
+                    let token_name = name.read().trim().to_string();
+                    let current = password.read().clone();
+                    let scopes = selected_scopes.read().clone();
+                    if token_name.is_empty() {
+                        status.set("Give the token a name.".to_string());
+                        return;
+                    }
+                    if scopes.is_empty() {
+                        status.set("Pick at least one scope.".to_string());
+                        return;
+                    }
+                    if current.is_empty() {
+                        status.set("Enter your current password.".to_string());
+                        return;
+                    }
+                    status.set("Creating token...".to_string());
+                    let username = username_for_create.clone();
+                    let current_for_list = current.clone();
+                    spawn(async move {
+                        match create_api_token(username, current, token_name, scopes).await {
+                            Ok(new_token) => {
+                                status.set(String::new());
+                                name.set(String::new());
+                                password.set(String::new());
+                                created.set(Some(new_token));
+                                // The password just checked out, so show the list too
+                                if unlocked.read().is_none() {
+                                    unlocked.set(Some(current_for_list));
+                                } else {
+                                    tokens.restart();
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("Create token error: {:?}", e);
+                                match AppError::from_server_error(&e) {
+                                    Some(app_error) => status.set(app_error.to_string()),
+                                    None => status.set(format!("Create failed: {}", e)),
+                                }
+                            }
+                        }
+                    });
+                },
+                "Create Token"
+            }
+        }
+    }
+}
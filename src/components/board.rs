@@ -0,0 +1,386 @@
+use crate::backend::board::{
+    create_board_column, delete_board_column, get_board, move_todo_to_column,
+    reorder_board_columns, update_board_column, BoardColumn, COLUMN_NAME_MAX_LEN,
+};
+use crate::backend::TodoItem;
+use crate::errors::AppError;
+use crate::Route;
+use dioxus::prelude::*;
+
+// Offered when a board has no columns yet; the last one marks todos done
+const STARTER_COLUMNS: [(&str, bool); 3] = [("Backlog", false), ("Doing", false), ("Done", true)];
+
+// The todo being dragged, as the drop target needs it
+#[derive(Clone, Copy, PartialEq)]
+struct Dragged {
+    id: usize,
+    version: i32,
+    status_id: Option<i32>,
+}
+
+// Board Route: todos in the user's status columns, with "No status" first.
+// Dragging a card to another column changes its status.
+#[component]
+pub fn TodoBoard() -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
+
+    let mut board = use_resource(move || {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => get_board(username).await.map(Some),
+                None => Ok(None),
+            }
+        }
+    });
+    let mut dragging = use_signal(|| None::<Dragged>);
+    let mut status = use_signal(String::new);
+    let refresh = use_callback(move |_| board.restart());
+
+    let drop_on = use_callback(move |column_id: Option<i32>| {
+        let Some(dragged) = dragging.write().take() else {
+            return;
+        };
+        if dragged.status_id == column_id {
+            return;
+        }
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        status.set("Moving...".to_string());
+        spawn(async move {
+            match move_todo_to_column(username, dragged.id, dragged.version, column_id).await {
+                Ok(_) => status.set(String::new()),
+                Err(e) => {
+                    let message = match AppError::from_server_error(&e) {
+                        Some(conflict @ AppError::Conflict { .. }) => {
+                            format!("{} The latest version is shown now.", conflict)
+                        }
+                        _ => format!("Error moving todo: {}", e),
+                    };
+                    status.set(message);
+                }
+            }
+            board.restart();
+            todos.restart();
+        });
+    });
+
+    if logged_in_user.read().is_none() {
+        return rsx! {
+            p { "Please log in to see your board." }
+            Link { to: Route::Home, "Go to login" }
+        };
+    }
+
+    rsx! {
+        div { class: "board-page",
+            h2 { "Board" }
+            match board.read().as_ref() {
+                Some(Ok(Some(board))) => rsx! {
+                    {
+                        let order: Vec<i32> = board.columns.iter().map(|column| column.id).collect();
+                        // Todos whose column is gone count as "No status"
+                        let unsorted: Vec<TodoItem> = board
+                            .todos
+                            .iter()
+                            .filter(|todo| todo.status_id.is_none_or(|id| !order.contains(&id)))
+                            .cloned()
+                            .collect();
+                        rsx! {
+                            div { class: "board",
+                                BoardLane {
+                                    column: None,
+                                    todos: unsorted,
+                                    order: order.clone(),
+                                    dragging,
+                                    on_drop: drop_on,
+                                    on_change: refresh,
+                                }
+                                for column in board.columns.iter().cloned() {
+                                    BoardLane {
+                                        key: "{column.id}",
+                                        todos: board.todos.iter().filter(|todo| todo.status_id == Some(column.id)).cloned().collect::<Vec<_>>(),
+                                        column: Some(column),
+                                        order: order.clone(),
+                                        dragging,
+                                        on_drop: drop_on,
+                                        on_change: refresh,
+                                    }
+                                }
+                            }
+                            AddColumn { empty: order.is_empty(), on_change: refresh }
+                        }
+                    }
+                },
+                Some(Ok(None)) | None => rsx! {
+                    p { "Loading board..." }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Could not load board: {e}" }
+                },
+            }
+            p { class: "row-status", "{status}" }
+        }
+    }
+}
+
+// One column (None: "No status"). `order` is every column's id left to
+// right, which moving this one left or right rearranges.
+#[component]
+fn BoardLane(
+    column: Option<BoardColumn>,
+    todos: Vec<TodoItem>,
+    order: Vec<i32>,
+    dragging: Signal<Option<Dragged>>,
+    on_drop: EventHandler<Option<i32>>,
+    on_change: EventHandler,
+) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut editing = use_signal(|| false);
+    let mut name = use_signal(String::new);
+    let mut marks_done = use_signal(|| false);
+    let mut lane_status = use_signal(String::new);
+
+    let column_id = column.as_ref().map(|column| column.id);
+    let title = column
+        .as_ref()
+        .map(|column| column.name.clone())
+        .unwrap_or_else(|| "No status".to_string());
+    let saved_marks_done = column.as_ref().is_some_and(|column| column.marks_done);
+    let position = column_id.and_then(|id| order.iter().position(|other| *other == id));
+    let is_first = position == Some(0);
+    let is_last = position.is_none_or(|index| index + 1 == order.len());
+    let count = todos.len();
+
+    // Sends the columns in their new order, with this one moved by `step`
+    let shift = use_callback(move |step: isize| {
+        let Some(from) = position else {
+            return;
+        };
+        let to = from as isize + step;
+        if to < 0 || to as usize >= order.len() {
+            return;
+        }
+        let mut ids = order.clone();
+        ids.swap(from, to as usize);
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        spawn(async move {
+            match reorder_board_columns(username, ids).await {
+                Ok(()) => on_change.call(()),
+                Err(e) => lane_status.set(format!("Moving column failed: {}", e)),
+            }
+        });
+    });
+
+    rsx! {
+        section {
+            class: "board-column",
+            ondragover: move |evt| evt.prevent_default(),
+            ondrop: move |evt| {
+                evt.prevent_default();
+                on_drop.call(column_id);
+            },
+            div { class: "board-column-header",
+                h3 {
+                    "{title} "
+                    span { class: "board-count", "{count}" }
+                }
+                if column_id.is_some() {
+                    if saved_marks_done {
+                        span { class: "board-marks-done", title: "Todos moved here are marked done",
+                            "✓"
+                        }
+                    }
+                    div { class: "board-column-actions",
+                        button {
+                            class: "secondary",
+                            disabled: is_first,
+                            title: "Move left",
+                            onclick: move |_| shift(-1),
+                            "←"
+                        }
+                        button {
+                            class: "secondary",
+                            disabled: is_last,
+                            title: "Move right",
+                            onclick: move |_| shift(1),
+                            "→"
+                        }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                name.set(title.clone());
+                                marks_done.set(saved_marks_done);
+                                lane_status.set(String::new());
+                                editing.set(!editing());
+                            },
+                            "Edit"
+                        }
+                    }
+                }
+            }
+            if let (true, Some(id)) = (editing(), column_id) {
+                div { class: "board-column-edit",
+                    input {
+                        value: "{name}",
+                        maxlength: "{COLUMN_NAME_MAX_LEN}",
+                        oninput: move |evt| name.set(evt.value()),
+                    }
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            checked: marks_done(),
+                            onchange: move |evt| marks_done.set(evt.checked()),
+                        }
+                        " Marks todos done"
+                    }
+                    div { class: "notes-buttons",
+                        button {
+                            onclick: move |_| {
+                                let username = logged_in_user.read().clone().unwrap_or_default();
+                                let new_name = name.read().clone();
+                                let done = marks_done();
+                                spawn(async move {
+                                    match update_board_column(username, id, new_name, done).await {
+                                        Ok(_) => {
+                                            editing.set(false);
+                                            on_change.call(());
+                                        }
+                                        Err(e) => lane_status.set(format!("Saving column failed: {}", e)),
+                                    }
+                                });
+                            },
+                            "Save"
+                        }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                let username = logged_in_user.read().clone().unwrap_or_default();
+                                spawn(async move {
+                                    match delete_board_column(username, id).await {
+                                        Ok(()) => on_change.call(()),
+                                        Err(e) => lane_status.set(format!("Deleting column failed: {}", e)),
+                                    }
+                                });
+                            },
+                            "Delete"
+                        }
+                        button { class: "secondary", onclick: move |_| editing.set(false), "Cancel" }
+                    }
+                    p { class: "notes-empty", "Deleting a column moves its todos to No status." }
+                }
+            }
+            if !lane_status.read().is_empty() {
+                p { class: "row-status", "{lane_status}" }
+            }
+            if todos.is_empty() {
+                p { class: "notes-empty", "Drop todos here." }
+            }
+            for todo in todos {
+                BoardCard { key: "{todo.id}", todo, dragging }
+            }
+        }
+    }
+}
+
+// A todo that can be dragged to another column; clicking opens its details
+#[component]
+fn BoardCard(todo: TodoItem, dragging: Signal<Option<Dragged>>) -> Element {
+    let mut dragging = dragging;
+    let dragged = Dragged {
+        id: todo.id,
+        version: todo.version,
+        status_id: todo.status_id,
+    };
+    let mut class = "board-card".to_string();
+    if todo.completed {
+        class.push_str(" done");
+    }
+    if let Some(priority) = todo.priority {
+        class.push_str(&format!(" priority-{}", priority.label()));
+    }
+
+    rsx! {
+        div {
+            class,
+            draggable: "true",
+            ondragstart: move |_| dragging.set(Some(dragged)),
+            ondragend: move |_| dragging.set(None),
+            Link { to: Route::TodoDetail { id: todo.id }, "{todo.content}" }
+        }
+    }
+}
+
+// Adds a column on the right, or the starter columns to an empty board
+#[component]
+fn AddColumn(empty: bool, on_change: EventHandler) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut name = use_signal(String::new);
+    let mut marks_done = use_signal(|| false);
+    let mut status = use_signal(String::new);
+
+    rsx! {
+        div { class: "board-add-column",
+            input {
+                placeholder: "New column",
+                value: "{name}",
+                maxlength: "{COLUMN_NAME_MAX_LEN}",
+                oninput: move |evt| {
+                    name.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            label {
+                input {
+                    r#type: "checkbox",
+                    checked: marks_done(),
+                    onchange: move |evt| marks_done.set(evt.checked()),
+                }
+                " Marks todos done"
+            }
+            button {
+                onclick: move |_| {
+                    let new_name = name.read().trim().to_string();
+                    if new_name.is_empty() {
+                        status.set("Enter a name for the column.".to_string());
+                        return;
+                    }
+                    let username = logged_in_user.read().clone().unwrap_or_default();
+                    let done = marks_done();
+                    spawn(async move {
+                        match create_board_column(username, new_name, done).await {
+                            Ok(_) => {
+                                name.set(String::new());
+                                marks_done.set(false);
+                                on_change.call(());
+                            }
+                            Err(e) => status.set(format!("Adding column failed: {}", e)),
+                        }
+                    });
+                },
+                "Add Column"
+            }
+            if empty {
+                button {
+                    class: "secondary",
+                    onclick: move |_| {
+                        let username = logged_in_user.read().clone().unwrap_or_default();
+                        spawn(async move {
+                            for (column, done) in STARTER_COLUMNS {
+                                if let Err(e) = create_board_column(username.clone(), column.to_string(), done).await {
+                                    status.set(format!("Adding column failed: {}", e));
+                                    break;
+                                }
+                            }
+                            on_change.call(());
+                        });
+                    },
+                    "Start with Backlog, Doing and Done"
+                }
+            }
+            if !status.read().is_empty() {
+                p { class: "row-status", "{status}" }
+            }
+        }
+    }
+}
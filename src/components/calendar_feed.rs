@@ -0,0 +1,130 @@
+use crate::backend::calendar_feed::{
+    calendar_feed_info, disable_calendar_feed, regenerate_calendar_feed,
+};
+use crate::errors::AppError;
+use dioxus::prelude::*;
+
+// Calendar Feed section of the Account page: create, replace or turn off the
+// secret URL calendar apps subscribe to
+#[component]
+pub fn CalendarFeed(username: String) -> Element {
+    let mut password = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    // The new URL is only shown until the user dismisses it
+    let mut created = use_signal(|| Option::<String>::None);
+
+    let username_for_info = username.clone();
+    let mut feed = use_resource(move || {
+        let username = username_for_info.clone();
+        async move { calendar_feed_info(username).await }
+    });
+
+    let username_for_regenerate = username.clone();
+    let username_for_disable = username.clone();
+    let has_feed = matches!(feed.read().as_ref(), Some(Ok(Some(_))));
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Calendar Feed" }
+            p {
+                "A private URL for calendar apps to subscribe to, showing your todos with due dates. "
+                "Add ?kind=todos to the URL to get them as tasks instead of events."
+            }
+            p { "{status}" }
+
+            if let Some(url) = created.read().clone() {
+                div { class: "new-token",
+                    p { "Copy your feed URL now. It will not be shown again; anyone with it can see your due todos." }
+                    code { "{url}" }
+                    div {
+                        button { onclick: move |_| created.set(None), "Done" }
+                    }
+                }
+            }
+
+            match feed.read().as_ref() {
+                Some(Ok(Some(info))) => rsx! {
+                    {
+                        let last_used = info.last_used_at.clone().unwrap_or_else(|| "never".to_string());
+                        rsx! {
+                            p {
+                                "Feed URL created {info.created_at}, last fetched {last_used}."
+                            }
+                        }
+                    }
+                },
+                Some(Ok(None)) => rsx! {
+                    p { class: "no-todos-message", "No feed URL yet." }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Error loading calendar feed: {e}" }
+                },
+                None => rsx! {
+                    p { "Loading..." }
+                },
+            }
+
+            input {
+                r#type: "password",
+                placeholder: "Current password",
+                value: "{password}",
+                oninput: move |evt| {
+                    password.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            button {
+                onclick: move |_| {
+                    let current = password.read().clone();
+                    if current.is_empty() {
+                        status.set("Enter your current password.".to_string());
+                        return;
+                    }
+                    status.set("Creating feed URL...".to_string());
+                    let username = username_for_regenerate.clone();
+                    spawn(async move {
+                        match regenerate_calendar_feed(username, current).await {
+                            Ok(url) => {
+                                status.set(String::new());
+                                password.set(String::new());
+                                created.set(Some(url));
+                                feed.restart();
+                            }
+                            Err(e) => {
+                                eprintln!("Calendar feed error: {:?}", e);
+                                match AppError::from_server_error(&e) {
+                                    Some(app_error) => status.set(app_error.to_string()),
+                                    None => status.set(format!("Creating feed URL failed: {}", e)),
+                                }
+                            }
+                        }
+                    });
+                },
+                if has_feed { "Regenerate URL" } else { "Create Feed URL" }
+            }
+            if has_feed {
+                button {
+                    class: "secondary",
+                    onclick: move |_| {
+                        let username = username_for_disable.clone();
+                        spawn(async move {
+                            match disable_calendar_feed(username).await {
+                                Ok(()) => {
+                                    status.set("Calendar feed turned off.".to_string());
+                                    created.set(None);
+                                    feed.restart();
+                                }
+                                Err(e) => {
+                                    eprintln!("Calendar feed error: {:?}", e);
+                                    status.set(format!("Turning off the feed failed: {}", e));
+                                }
+                            }
+                        });
+                    },
+                    "Turn Off"
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::backend::comments::{add_comment, delete_comment, edit_comment, list_comments, Comment};
+use crate::components::markdown::Markdown;
+use dioxus::prelude::*;
+
+// Comment thread under a todo on its detail page
+#[component]
+pub fn CommentThread(todo_id: usize) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut comments = use_resource(use_reactive!(|todo_id| {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => list_comments(username, todo_id).await,
+                None => Ok(Vec::new()),
+            }
+        }
+    }));
+    let mut new_comment = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    let post = move |_| {
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        let body = new_comment.read().clone();
+        spawn(async move {
+            match add_comment(username, todo_id, body).await {
+                Ok(_) => {
+                    new_comment.set(String::new());
+                    status.set(String::new());
+                    comments.restart();
+                }
+                Err(e) => status.set(format!("Error adding comment: {}", e)),
+            }
+        });
+    };
+
+    rsx! {
+        match comments.read().as_ref() {
+            Some(Ok(thread)) if thread.is_empty() => rsx! {
+                p { class: "notes-empty", "No comments yet." }
+            },
+            Some(Ok(thread)) => rsx! {
+                ul { class: "comments",
+                    for comment in thread.iter().cloned() {
+                        CommentItem {
+                            key: "{comment.id}",
+                            comment,
+                            on_change: move |_| comments.restart(),
+                        }
+                    }
+                }
+            },
+            Some(Err(e)) => rsx! {
+                p { color: "red", "Could not load comments: {e}" }
+            },
+            None => rsx! {
+                p { "Loading comments..." }
+            },
+        }
+        textarea {
+            class: "comment-input",
+            rows: 3,
+            placeholder: "Add a comment... Markdown works here.",
+            value: "{new_comment}",
+            oninput: move |evt| new_comment.set(evt.value()),
+        }
+        button {
+            disabled: new_comment.read().trim().is_empty(),
+            onclick: post,
+            "Comment"
+        }
+        p { "{status}" }
+    }
+}
+
+// One comment; its author can edit or delete it
+#[component]
+fn CommentItem(comment: Comment, on_change: EventHandler) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    // Some(text) while the comment is being edited
+    let mut draft = use_signal(|| None::<String>);
+    let mut status = use_signal(String::new);
+
+    let id = comment.id;
+    let is_mine = logged_in_user.read().as_deref() == Some(comment.author.as_str());
+    let body = comment.body.clone();
+    let body_to_edit = comment.body.clone();
+    let edited = comment
+        .edited_at
+        .as_ref()
+        .map(|at| format!(" (edited {})", at))
+        .unwrap_or_default();
+
+    let save = move |_| {
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        let body = draft.read().clone().unwrap_or_default();
+        spawn(async move {
+            match edit_comment(username, id, body).await {
+                Ok(_) => {
+                    draft.set(None);
+                    status.set(String::new());
+                    on_change.call(());
+                }
+                Err(e) => status.set(format!("Error saving comment: {}", e)),
+            }
+        });
+    };
+    let remove = move |_| {
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        spawn(async move {
+            match delete_comment(username, id).await {
+                Ok(()) => on_change.call(()),
+                Err(e) => status.set(format!("Error deleting comment: {}", e)),
+            }
+        });
+    };
+
+    rsx! {
+        li { class: "comment",
+            div { class: "comment-meta",
+                strong { "{comment.author}" }
+                span { class: "activity-time", "{comment.created_at}{edited}" }
+            }
+            match draft.read().clone() {
+                Some(text) => rsx! {
+                    textarea {
+                        class: "comment-input",
+                        rows: 3,
+                        value: "{text}",
+                        oninput: move |evt| draft.set(Some(evt.value())),
+                    }
+                    div { class: "notes-buttons",
+                        button { onclick: save, "Save" }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                draft.set(None);
+                                status.set(String::new());
+                            },
+                            "Cancel"
+                        }
+                    }
+                },
+                None => rsx! {
+                    Markdown { content: body.clone() }
+                    if is_mine {
+                        div { class: "notes-buttons",
+                            button {
+                                class: "secondary",
+                                onclick: move |_| draft.set(Some(body_to_edit.clone())),
+                                "Edit"
+                            }
+                            button { class: "secondary", onclick: remove, "Delete" }
+                        }
+                    }
+                },
+            }
+            if !status.read().is_empty() {
+                p { class: "row-status", "{status}" }
+            }
+        }
+    }
+}
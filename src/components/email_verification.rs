@@ -0,0 +1,40 @@
+use crate::backend::email_verification::verify_email;
+use crate::Route;
+use dioxus::prelude::*;
+
+// Where the emailed verification link lands
+#[component]
+pub fn VerifyEmail(token: String) -> Element {
+    let mut status = use_signal(|| {
+        if token.is_empty() {
+            "This verification link is incomplete.".to_string()
+        } else {
+            "Verifying...".to_string()
+        }
+    });
+
+    // Effects only run in the browser, so server rendering can't spend the token
+    use_effect(move || {
+        if token.is_empty() {
+            return;
+        }
+        let token = token.clone();
+        spawn(async move {
+            match verify_email(token).await {
+                Ok(_) => status.set("Your email is verified.".to_string()),
+                Err(e) => {
+                    eprintln!("Email verification error: {:?}", e);
+                    status.set(format!("Verification failed: {}", e));
+                }
+            }
+        });
+    });
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Verify Email" }
+            p { "{status}" }
+            Link { to: Route::Home, "Back to the app" }
+        }
+    }
+}
@@ -1,17 +1,90 @@
 use dioxus::prelude::*;
 
-use crate::backend::login;
+use crate::backend::sso::sso_enabled;
+use crate::backend::two_factor::verify_two_factor;
+use crate::backend::{login, LoginOutcome};
+use crate::errors::AppError;
+use crate::Route;
 
 #[component]
 pub fn Login() -> Element {
     // Signals for input fields
-    let mut login_username = use_signal(|| String::new());
-    let mut login_password = use_signal(|| String::new());
-    let mut login_status = use_signal(|| String::new());
+    let mut login_username = use_signal(String::new);
+    let mut login_password = use_signal(String::new);
+    let mut login_status = use_signal(String::new);
+
+    // Set once the password is accepted for an account with 2FA enabled
+    let mut two_factor_challenge = use_signal(|| Option::<String>::None);
+    let mut two_factor_code = use_signal(String::new);
+
+    // The SSO button is only shown when the server has a provider configured
+    let sso_available = use_resource(|| async { sso_enabled().await.unwrap_or(false) });
 
     // Get the logged_in_user signal setter from context
     let logged_in_user = use_context::<Signal<Option<String>>>();
 
+    if let Some(challenge) = two_factor_challenge.read().clone() {
+        return rsx! {
+            // --- Two-Factor Step ---
+            div { class: "auth-form",
+                h2 { "Two-Factor Authentication" }
+                p { "Enter the 6-digit code from your authenticator app, or one of your recovery codes." }
+                p { "{login_status}" }
+                input {
+                    r#type: "text",
+                    placeholder: "Code",
+                    autocomplete: "one-time-code",
+                    value: "{two_factor_code}",
+                    oninput: move |evt| {
+                        two_factor_code.set(evt.value());
+                        login_status.set(String::new());
+                    },
+                }
+                button {
+                    onclick: move |_| {
+                        let code = two_factor_code.read().trim().to_string();
+                        let mut logged_in_user = logged_in_user;
+                        if code.is_empty() {
+                            login_status.set("Enter a code.".to_string());
+                            return;
+                        }
+                        login_status.set("Verifying...".to_string());
+                        let challenge = challenge.clone();
+                        spawn(async move {
+                            match verify_two_factor(challenge, code).await {
+                                Ok(username) => {
+                                    login_status.set("Login successful!".to_string());
+                                    two_factor_challenge.set(None);
+                                    logged_in_user.set(Some(username));
+                                }
+                                Err(e) => {
+                                    eprintln!("Two-factor error: {:?}", e);
+                                    match AppError::from_server_error(&e) {
+                                        Some(app_error) => {
+                                            login_status.set(app_error.to_string());
+                                        }
+                                        None => login_status.set(format!("Login failed: {}", e)),
+                                    }
+                                }
+                            }
+                        });
+                    },
+                    "Verify"
+                }
+                button {
+                    background_color: "#6c757d",
+                    margin_left: "10px",
+                    onclick: move |_| {
+                        two_factor_challenge.set(None);
+                        two_factor_code.set(String::new());
+                        login_status.set(String::new());
+                    },
+                    "Cancel"
+                }
+            }
+        };
+    }
+
     rsx! {
         div {
             // display: "grid", grid_template_columns: "1fr 1fr", gap: "20px",
@@ -42,7 +115,7 @@ pub fn Login() -> Element {
                     onclick: move |_| {
                         let username = login_username.read().clone();
                         let password = login_password.read().clone();
-                        let mut logged_in_user = logged_in_user.clone();
+                        let mut logged_in_user = logged_in_user;
                         if username.is_empty() || password.is_empty() {
                             login_status.set("Username and password are required.".to_string());
                             return;
@@ -50,19 +123,37 @@ pub fn Login() -> Element {
                         login_status.set("Logging in...".to_string());
                         spawn(async move {
                             match login(username.clone(), password).await {
-                                Ok(_) => {
-                                    login_status.set(format!("Login successful!"));
+                                Ok(LoginOutcome::LoggedIn) => {
+                                    login_status.set("Login successful!".to_string());
                                     logged_in_user.set(Some(username.clone()));
                                 }
+                                Ok(LoginOutcome::TwoFactorRequired { challenge }) => {
+                                    login_status.set(String::new());
+                                    login_password.set(String::new());
+                                    two_factor_code.set(String::new());
+                                    two_factor_challenge.set(Some(challenge));
+                                }
                                 Err(e) => {
                                     eprintln!("Login error: {:?}", e);
-                                    login_status.set(format!("Login failed: {}", e));
+                                    match AppError::from_server_error(&e) {
+                                        Some(app_error) => {
+                                            login_status.set(app_error.to_string());
+                                        }
+                                        None => login_status.set(format!("Login failed: {}", e)),
+                                    }
                                 }
                             }
                         });
                     },
                     "Login"
                 }
+                p {
+                    Link { to: Route::ForgotPassword, "Forgot password?" }
+                }
+                if sso_available().unwrap_or(false) {
+                    // A plain link: the browser has to leave the app for the provider
+                    a { class: "sso-button", href: "/auth/oidc/start", "Sign in with SSO" }
+                }
             }
         }
     }
@@ -0,0 +1,12 @@
+use crate::markdown;
+use dioxus::prelude::*;
+
+// Todo content rendered as sanitized HTML (see `markdown::render`)
+#[component]
+pub fn Markdown(content: String, #[props(default)] class: String) -> Element {
+    let html = markdown::render(&content);
+
+    rsx! {
+        div { class: "markdown {class}", dangerous_inner_html: html }
+    }
+}
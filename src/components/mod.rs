@@ -1,3 +1,19 @@
+pub mod account;
+pub mod activity;
+pub mod api_tokens;
+pub mod board;
+pub mod calendar_feed;
+pub mod comments;
+pub mod email_verification;
 pub mod login;
+pub mod markdown;
 pub mod nav;
+pub mod password_reset;
 pub mod register;
+pub mod schedule;
+pub mod sso;
+pub mod stats;
+pub mod sync_status;
+pub mod todo_detail;
+pub mod two_factor;
+pub mod webhooks;
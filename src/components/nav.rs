@@ -3,13 +3,36 @@ use dioxus::prelude::*;
 
 #[component]
 pub fn NavBar() -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+
     rsx! {
         div { id: "title",
-            Link { to: Route::Register,
-                h1 { "Register" }
-            }
-            Link { to: Route::Login,
-                h1 { "Login" }
+            if logged_in_user.read().is_some() {
+                Link { to: Route::Home,
+                    h1 { "Todos" }
+                }
+                // Board columns are kept on the server, by server ids
+                if cfg!(not(feature = "offline")) {
+                    Link { to: Route::TodoBoard,
+                        h1 { "Board" }
+                    }
+                }
+                Link { to: Route::Activity { todo: String::new() },
+                    h1 { "Activity" }
+                }
+                Link { to: Route::Stats,
+                    h1 { "Stats" }
+                }
+                Link { to: Route::Account,
+                    h1 { "Account" }
+                }
+            } else {
+                Link { to: Route::Register,
+                    h1 { "Register" }
+                }
+                Link { to: Route::Home,
+                    h1 { "Login" }
+                }
             }
         }
         Outlet::<Route> {}
@@ -0,0 +1,138 @@
+use crate::backend::password_reset::{request_password_reset, reset_password};
+use crate::errors::AppError;
+use crate::validation::validate_email;
+use crate::Route;
+use dioxus::prelude::*;
+
+// Forgot Password Route: ask for a reset email
+#[component]
+pub fn ForgotPassword() -> Element {
+    let mut email = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Forgot Password" }
+            p { "Enter the email address on your account and we'll send you a reset link." }
+            p { "{status}" }
+            input {
+                r#type: "text",
+                placeholder: "Email",
+                value: "{email}",
+                oninput: move |evt| {
+                    email.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            button {
+                onclick: move |_| {
+                    let address = email.read().clone();
+                    if let Err(e) = validate_email(&address) {
+                        status.set(e);
+                        return;
+                    }
+                    status.set("Sending...".to_string());
+                    spawn(async move {
+                        match request_password_reset(address).await {
+                            Ok(_) => {
+                                status
+                                    .set(
+                                        "If an account uses that address, a reset link is on its way."
+                                            .to_string(),
+                                    );
+                                email.set(String::new());
+                            }
+                            Err(e) => {
+                                eprintln!("Password reset request error: {:?}", e);
+                                match AppError::from_server_error(&e) {
+                                    Some(app_error) => status.set(app_error.to_string()),
+                                    None => status.set(format!("Request failed: {}", e)),
+                                }
+                            }
+                        }
+                    });
+                },
+                "Send Reset Link"
+            }
+        }
+    }
+}
+
+// Reset Password Route: the target of the emailed link
+#[component]
+pub fn ResetPassword(token: String) -> Element {
+    let mut new_password = use_signal(String::new);
+    let mut confirm_password = use_signal(String::new);
+    let mut status = use_signal(String::new);
+    let mut done = use_signal(|| false);
+
+    if token.is_empty() {
+        return rsx! {
+            div { class: "auth-form",
+                h2 { "Reset Password" }
+                p { "This reset link is incomplete. Request a new one." }
+                Link { to: Route::ForgotPassword, "Forgot password?" }
+            }
+        };
+    }
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Reset Password" }
+            p { "{status}" }
+            if done() {
+                Link { to: Route::Home, "Go to login" }
+            } else {
+                input {
+                    r#type: "password",
+                    placeholder: "New password",
+                    value: "{new_password}",
+                    oninput: move |evt| {
+                        new_password.set(evt.value());
+                        status.set(String::new());
+                    },
+                }
+                input {
+                    r#type: "password",
+                    placeholder: "Confirm new password",
+                    value: "{confirm_password}",
+                    oninput: move |evt| {
+                        confirm_password.set(evt.value());
+                        status.set(String::new());
+                    },
+                }
+                button {
+                    onclick: move |_| {
+                        let password = new_password.read().clone();
+                        if password.is_empty() {
+                            status.set("Enter a new password.".to_string());
+                            return;
+                        }
+                        if *confirm_password.read() != password {
+                            status.set("Passwords do not match.".to_string());
+                            return;
+                        }
+                        status.set("Resetting password...".to_string());
+                        let token = token.clone();
+                        spawn(async move {
+                            match reset_password(token, password).await {
+                                Ok(_) => {
+                                    status.set("Password reset. You can now login.".to_string());
+                                    done.set(true);
+                                }
+                                Err(e) => {
+                                    eprintln!("Password reset error: {:?}", e);
+                                    match AppError::from_server_error(&e) {
+                                        Some(app_error) => status.set(app_error.to_string()),
+                                        None => status.set(format!("Reset failed: {}", e)),
+                                    }
+                                }
+                            }
+                        });
+                    },
+                    "Reset Password"
+                }
+            }
+        }
+    }
+}
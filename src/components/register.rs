@@ -1,11 +1,36 @@
 use crate::backend::register;
+use crate::errors::AppError;
+use crate::validation::{
+    password_strength, validate_password, validate_username, MIN_PASSWORD_STRENGTH,
+};
 use dioxus::prelude::*;
 #[component]
 pub fn Register() -> Element {
     // Signals for input fields
-    let mut reg_username = use_signal(|| String::new());
-    let mut reg_password = use_signal(|| String::new());
-    let mut reg_status = use_signal(|| String::new());
+    let mut reg_username = use_signal(String::new);
+    let mut reg_password = use_signal(String::new);
+    let mut reg_confirm = use_signal(String::new);
+    let mut reg_status = use_signal(String::new);
+
+    // Live feedback, recomputed as the user types
+    let username_error = use_memo(move || {
+        let username = reg_username.read();
+        if username.is_empty() {
+            None
+        } else {
+            validate_username(&username).err()
+        }
+    });
+    let strength = use_memo(move || password_strength(&reg_password.read(), &reg_username.read()));
+    let confirm_mismatch = use_memo(move || {
+        let confirm = reg_confirm.read();
+        !confirm.is_empty() && *confirm != *reg_password.read()
+    });
+
+    let strength_label = strength().label();
+    let strength_class = strength().css_class();
+    let strength_percent = strength().percent();
+    let too_weak = strength() < MIN_PASSWORD_STRENGTH;
 
     rsx! {
         div {
@@ -24,6 +49,9 @@ pub fn Register() -> Element {
                         reg_status.set(String::new());
                     },
                 }
+                if let Some(error) = username_error() {
+                    p { class: "auth-status", "{error}" }
+                }
                 input {
                     r#type: "password",
                     placeholder: "Password",
@@ -33,6 +61,32 @@ pub fn Register() -> Element {
                         reg_status.set(String::new());
                     },
                 }
+                if !reg_password.read().is_empty() {
+                    div { class: "strength-meter",
+                        div {
+                            class: "strength-bar strength-{strength_class}",
+                            width: "{strength_percent}%",
+                        }
+                    }
+                    p { class: "strength-label",
+                        "Strength: {strength_label}"
+                        if too_weak {
+                            " (too weak)"
+                        }
+                    }
+                }
+                input {
+                    r#type: "password",
+                    placeholder: "Confirm password",
+                    value: "{reg_confirm}",
+                    oninput: move |evt| {
+                        reg_confirm.set(evt.value());
+                        reg_status.set(String::new());
+                    },
+                }
+                if confirm_mismatch() {
+                    p { class: "auth-status", "Passwords do not match." }
+                }
                 button {
                     onclick: move |_| {
                         let username = reg_username.read().clone();
@@ -41,18 +95,34 @@ pub fn Register() -> Element {
                             reg_status.set("Username and password are required.".to_string());
                             return;
                         }
+                        if let Err(e) = validate_username(&username) {
+                            reg_status.set(e);
+                            return;
+                        }
+                        if let Err(e) = validate_password(&password, &username) {
+                            reg_status.set(e);
+                            return;
+                        }
+                        if *reg_confirm.read() != password {
+                            reg_status.set("Passwords do not match.".to_string());
+                            return;
+                        }
                         reg_status.set("Registering...".to_string());
                         spawn(async move {
                             match register(username.clone(), password).await {
                                 Ok(_) => {
                                     reg_status
-                                        .set(format!("Registered successfully! You can now login."));
+                                        .set("Registered successfully! You can now login.".to_string());
                                     reg_username.set(String::new());
                                     reg_password.set(String::new());
+                                    reg_confirm.set(String::new());
                                 }
                                 Err(e) => {
                                     eprintln!("Registration error: {:?}", e);
-                                    reg_status.set(format!("Registration failed: {}", e));
+                                    match AppError::from_server_error(&e) {
+                                        Some(app_error) => reg_status.set(app_error.to_string()),
+                                        None => reg_status.set(format!("Registration failed: {}", e)),
+                                    }
                                 }
                             }
                         });
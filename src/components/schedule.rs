@@ -0,0 +1,350 @@
+use crate::backend::schedule::{reschedule_todo, todo_agenda, todos_due_between};
+use crate::backend::TodoItem;
+use crate::errors::AppError;
+use crate::Route;
+use chrono::{Datelike, Duration, Local, Months, NaiveDate};
+use dioxus::prelude::*;
+
+// The todo being dragged, as the drop target needs it
+#[derive(Clone, Copy, PartialEq)]
+struct Dragged {
+    id: usize,
+    version: i32,
+    due_date: Option<NaiveDate>,
+}
+
+// Agenda view: open todos due today (or overdue), upcoming, and someday.
+// Dropping a todo on a group moves it to today, tomorrow or no date.
+#[component]
+pub fn TodoAgenda() -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut agenda = use_resource(move || {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => todo_agenda(username, today()).await.map(Some),
+                None => Ok(None),
+            }
+        }
+    });
+    let dragging = use_signal(|| None::<Dragged>);
+    let status = use_signal(String::new);
+    let refresh = use_callback(move |_| agenda.restart());
+    let drop_on = use_drop(dragging, status, refresh);
+
+    let today = today();
+    let tomorrow = today + Duration::days(1);
+
+    rsx! {
+        match agenda.read().as_ref() {
+            Some(Ok(Some(agenda))) => rsx! {
+                div { class: "agenda",
+                    AgendaGroup {
+                        title: "Today",
+                        empty: "Nothing due today.",
+                        todos: agenda.today.clone(),
+                        target: Some(today),
+                        dragging,
+                        on_drop: drop_on,
+                    }
+                    AgendaGroup {
+                        title: "Upcoming",
+                        empty: "Nothing scheduled.",
+                        todos: agenda.upcoming.clone(),
+                        target: Some(tomorrow),
+                        dragging,
+                        on_drop: drop_on,
+                    }
+                    AgendaGroup {
+                        title: "Someday",
+                        empty: "Every open todo has a date.",
+                        todos: agenda.someday.clone(),
+                        target: None,
+                        dragging,
+                        on_drop: drop_on,
+                    }
+                }
+            },
+            Some(Ok(None)) | None => rsx! {
+                p { "Loading agenda..." }
+            },
+            Some(Err(e)) => rsx! {
+                p { color: "red", "Could not load agenda: {e}" }
+            },
+        }
+        p { class: "row-status", "{status}" }
+    }
+}
+
+// `target` is where a todo dropped here moves to. Todos already in the
+// group keep their date, so "Upcoming" only pulls todos forward to tomorrow
+// when they come from another group.
+#[component]
+fn AgendaGroup(
+    title: &'static str,
+    empty: &'static str,
+    todos: Vec<TodoItem>,
+    target: Option<NaiveDate>,
+    dragging: Signal<Option<Dragged>>,
+    on_drop: EventHandler<Option<NaiveDate>>,
+) -> Element {
+    let ids: Vec<usize> = todos.iter().map(|todo| todo.id).collect();
+    let today = today();
+
+    rsx! {
+        section {
+            class: "agenda-group",
+            ondragover: move |evt| evt.prevent_default(),
+            ondrop: move |evt| {
+                evt.prevent_default();
+                let already_here = dragging().is_some_and(|d| ids.contains(&d.id));
+                if !already_here {
+                    on_drop.call(target);
+                }
+            },
+            h3 { "{title}" }
+            if todos.is_empty() {
+                p { class: "notes-empty", "{empty}" }
+            }
+            for todo in todos {
+                ScheduleChip {
+                    key: "{todo.id}",
+                    todo: todo.clone(),
+                    dragging,
+                    show_date: todo.due_date.is_some_and(|date| date != today),
+                }
+            }
+        }
+    }
+}
+
+// Week or month of days with their todos; dropping a todo on a day moves it
+// there
+#[component]
+pub fn TodoCalendar(month: bool) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    // Any day in the week or month shown
+    let mut anchor = use_signal(today);
+
+    let (first, last) = if month {
+        month_grid(anchor())
+    } else {
+        week_of(anchor())
+    };
+    let mut todos = use_resource(use_reactive!(|first, last| {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => todos_due_between(username, first, last).await.map(Some),
+                None => Ok(None),
+            }
+        }
+    }));
+    let dragging = use_signal(|| None::<Dragged>);
+    let status = use_signal(String::new);
+    let refresh = use_callback(move |_| todos.restart());
+    let drop_on = use_drop(dragging, status, refresh);
+
+    let mut step = move |forward: bool| {
+        let current = anchor();
+        let moved = match (month, forward) {
+            (true, true) => current.checked_add_months(Months::new(1)),
+            (true, false) => current.checked_sub_months(Months::new(1)),
+            (false, true) => Some(current + Duration::days(7)),
+            (false, false) => Some(current - Duration::days(7)),
+        };
+        if let Some(moved) = moved {
+            anchor.set(moved);
+        }
+    };
+
+    let title = if month {
+        anchor().format("%B %Y").to_string()
+    } else {
+        format!("{} - {}", first.format("%-d %b"), last.format("%-d %b %Y"))
+    };
+    let today = today();
+    let shown_month = anchor().month();
+    let days: Vec<NaiveDate> = first.iter_days().take_while(|day| *day <= last).collect();
+    let loaded = todos.read().clone();
+    let grid_class = if month {
+        "calendar month"
+    } else {
+        "calendar week"
+    };
+
+    rsx! {
+        div { class: "calendar-header",
+            button { class: "secondary", onclick: move |_| step(false), "Previous" }
+            button { class: "secondary", onclick: move |_| anchor.set(today), "Today" }
+            button { class: "secondary", onclick: move |_| step(true), "Next" }
+            h3 { "{title}" }
+        }
+        match loaded {
+            Some(Ok(Some(todos))) => rsx! {
+                div { class: grid_class,
+                    for weekday in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+                        div { key: "{weekday}", class: "calendar-weekday", "{weekday}" }
+                    }
+                    for day in days {
+                        CalendarDay {
+                            key: "{day}",
+                            day,
+                            todos: todos.iter().filter(|todo| todo.due_date == Some(day)).cloned().collect::<Vec<_>>(),
+                            is_today: day == today,
+                            outside: month && day.month() != shown_month,
+                            dragging,
+                            on_drop: drop_on,
+                        }
+                    }
+                }
+            },
+            Some(Ok(None)) | None => rsx! {
+                p { "Loading calendar..." }
+            },
+            Some(Err(e)) => rsx! {
+                p { color: "red", "Could not load calendar: {e}" }
+            },
+        }
+        p { class: "row-status", "{status}" }
+    }
+}
+
+#[component]
+fn CalendarDay(
+    day: NaiveDate,
+    todos: Vec<TodoItem>,
+    is_today: bool,
+    // In the weeks around a month, shown dimmed
+    outside: bool,
+    dragging: Signal<Option<Dragged>>,
+    on_drop: EventHandler<Option<NaiveDate>>,
+) -> Element {
+    let mut class = "calendar-day".to_string();
+    if is_today {
+        class.push_str(" today");
+    }
+    if outside {
+        class.push_str(" outside");
+    }
+
+    rsx! {
+        div {
+            class,
+            ondragover: move |evt| evt.prevent_default(),
+            ondrop: move |evt| {
+                evt.prevent_default();
+                on_drop.call(Some(day));
+            },
+            span { class: "calendar-date", "{day.day()}" }
+            for todo in todos {
+                ScheduleChip {
+                    key: "{todo.id}",
+                    todo,
+                    dragging,
+                    show_date: false,
+                }
+            }
+        }
+    }
+}
+
+// A todo that can be dragged to another day; clicking opens its details.
+// Browsers start the drag from the link itself, which also makes Firefox
+// drag without any data set by us.
+#[component]
+fn ScheduleChip(todo: TodoItem, dragging: Signal<Option<Dragged>>, show_date: bool) -> Element {
+    let mut dragging = dragging;
+    let dragged = Dragged {
+        id: todo.id,
+        version: todo.version,
+        due_date: todo.due_date,
+    };
+    let mut class = "schedule-chip".to_string();
+    if todo.completed {
+        class.push_str(" done");
+    }
+    if let Some(priority) = todo.priority {
+        class.push_str(&format!(" priority-{}", priority.label()));
+    }
+    let when = match (show_date, todo.due_date, todo.due_time) {
+        (true, Some(date), time) => Some(crate::quick_add::due_label(date, time, today())),
+        (false, _, Some(time)) => Some(time.format("%H:%M").to_string()),
+        _ => None,
+    };
+
+    rsx! {
+        div {
+            class,
+            draggable: "true",
+            ondragstart: move |_| dragging.set(Some(dragged)),
+            ondragend: move |_| dragging.set(None),
+            Link { to: Route::TodoDetail { id: todo.id },
+                if let Some(when) = when {
+                    span { class: "schedule-when", "{when} " }
+                }
+                "{todo.content}"
+            }
+        }
+    }
+}
+
+// What dropping the dragged todo on a day (None: no date) does; `refresh`
+// reloads the view afterwards, along with the todo list
+fn use_drop(
+    dragging: Signal<Option<Dragged>>,
+    status: Signal<String>,
+    refresh: EventHandler,
+) -> EventHandler<Option<NaiveDate>> {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
+    let mut dragging = dragging;
+    let mut status = status;
+
+    use_callback(move |due_date: Option<NaiveDate>| {
+        let Some(dragged) = dragging.write().take() else {
+            return;
+        };
+        if dragged.due_date == due_date {
+            return;
+        }
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        status.set("Moving...".to_string());
+        spawn(async move {
+            match reschedule_todo(username, dragged.id, dragged.version, due_date).await {
+                Ok(_) => status.set(String::new()),
+                Err(e) => {
+                    let message = match AppError::from_server_error(&e) {
+                        Some(conflict @ AppError::Conflict { .. }) => {
+                            format!("{} The latest version is shown now.", conflict)
+                        }
+                        _ => format!("Error moving todo: {}", e),
+                    };
+                    status.set(message);
+                }
+            }
+            refresh.call(());
+            todos.restart();
+        });
+    })
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+// Monday to Sunday around `day`
+fn week_of(day: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+    (monday, monday + Duration::days(6))
+}
+
+// Whole weeks covering the month `day` is in
+fn month_grid(day: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let first = day.with_day(1).unwrap_or(day);
+    let last = first
+        .checked_add_months(Months::new(1))
+        .map(|next| next - Duration::days(1))
+        .unwrap_or(first);
+    (week_of(first).0, week_of(last).1)
+}
@@ -0,0 +1,122 @@
+use crate::backend::sso::{complete_sso_login, needs_password, set_sso_password};
+use crate::errors::AppError;
+use crate::validation::validate_password;
+use crate::Route;
+use dioxus::prelude::*;
+
+// Where /auth/oidc/callback sends the browser: redeem the ticket and log in
+#[component]
+pub fn SsoComplete(ticket: String, error: String) -> Element {
+    let mut logged_in_user = use_context::<Signal<Option<String>>>();
+    let nav = navigator();
+    let mut status = use_signal(|| match error.as_str() {
+        "" if ticket.is_empty() => "Missing sign-in ticket.".to_string(),
+        "" => "Signing in...".to_string(),
+        "denied" => "Sign-in was cancelled at the identity provider.".to_string(),
+        "expired" => "Sign-in took too long, please try again.".to_string(),
+        "unavailable" => "Single sign-on is not available right now.".to_string(),
+        _ => "Single sign-on failed, please try again.".to_string(),
+    });
+
+    // Effects only run in the browser, so server rendering can't spend the ticket
+    use_effect(move || {
+        if !error.is_empty() || ticket.is_empty() {
+            return;
+        }
+        let ticket = ticket.clone();
+        spawn(async move {
+            match complete_sso_login(ticket).await {
+                Ok(username) => {
+                    logged_in_user.set(Some(username));
+                    nav.replace(Route::Home);
+                }
+                Err(e) => {
+                    eprintln!("SSO login error: {:?}", e);
+                    status.set(format!("Login failed: {}", e));
+                }
+            }
+        });
+    });
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Single Sign-On" }
+            p { "{status}" }
+            Link { to: Route::Home, "Back to login" }
+        }
+    }
+}
+
+// Account page section for SSO-only accounts, hidden once a password exists
+#[component]
+pub fn SetSsoPassword(username: String) -> Element {
+    let mut new_password = use_signal(String::new);
+    let mut confirm_password = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    let username_for_check = username.clone();
+    let mut needed = use_resource(move || needs_password(username_for_check.clone()));
+    if !matches!(*needed.read(), Some(Ok(true))) {
+        return rsx! {};
+    }
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Set a Password" }
+            p {
+                "You signed in with SSO, so this account has no password yet. Set one to use the settings below that ask for it."
+            }
+            p { "{status}" }
+            input {
+                r#type: "password",
+                placeholder: "New password",
+                value: "{new_password}",
+                oninput: move |evt| {
+                    new_password.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            input {
+                r#type: "password",
+                placeholder: "Confirm new password",
+                value: "{confirm_password}",
+                oninput: move |evt| {
+                    confirm_password.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            button {
+                onclick: move |_| {
+                    let password = new_password.read().clone();
+                    if let Err(e) = validate_password(&password, &username) {
+                        status.set(e);
+                        return;
+                    }
+                    if *confirm_password.read() != password {
+                        status.set("Passwords do not match.".to_string());
+                        return;
+                    }
+                    status.set("Setting password...".to_string());
+                    let username = username.clone();
+                    spawn(async move {
+                        match set_sso_password(username, password).await {
+                            Ok(_) => {
+                                new_password.set(String::new());
+                                confirm_password.set(String::new());
+                                needed.restart();
+                            }
+                            Err(e) => {
+                                eprintln!("Set password error: {:?}", e);
+                                match AppError::from_server_error(&e) {
+                                    Some(app_error) => status.set(app_error.to_string()),
+                                    None => status.set(format!("Failed: {}", e)),
+                                }
+                            }
+                        }
+                    });
+                },
+                "Set Password"
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::backend::stats::{duration_label, todo_stats, DayCount, TodoStatsSummary};
+use crate::Route;
+use chrono::Local;
+use dioxus::prelude::*;
+
+const WEEK_CHOICES: [u32; 5] = [4, 8, 12, 26, 52];
+
+// Stats Route: counts, daily completions, time to complete and streaks
+#[component]
+pub fn Stats() -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut weeks = use_signal(|| 4u32);
+
+    let stats = use_resource(move || {
+        let current_user = logged_in_user.read().clone();
+        let weeks = weeks();
+        async move {
+            let now = Local::now();
+            let utc_offset_minutes = now.offset().local_minus_utc() / 60;
+            match current_user {
+                Some(username) => todo_stats(username, now.date_naive(), utc_offset_minutes, weeks)
+                    .await
+                    .map(Some),
+                None => Ok(None),
+            }
+        }
+    });
+
+    if logged_in_user.read().is_none() {
+        return rsx! {
+            p { "Please log in to see your stats." }
+            Link { to: Route::Home, "Go to login" }
+        };
+    }
+
+    rsx! {
+        div { class: "stats",
+            h2 { "Stats" }
+            match stats.read().as_ref() {
+                Some(Ok(Some(stats))) => rsx! {
+                    StatCards { stats: stats.clone() }
+                    div { class: "stats-chart-header",
+                        h3 { "Completed per day" }
+                        select {
+                            onchange: move |evt| {
+                                if let Ok(choice) = evt.value().parse() {
+                                    weeks.set(choice);
+                                }
+                            },
+                            for choice in WEEK_CHOICES {
+                                option {
+                                    value: "{choice}",
+                                    selected: choice == weeks(),
+                                    "Last {choice} weeks"
+                                }
+                            }
+                        }
+                    }
+                    CompletionChart { days: stats.completions_per_day.clone() }
+                },
+                Some(Ok(None)) | None => rsx! {
+                    p { "Loading stats..." }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Could not load stats: {e}" }
+                },
+            }
+        }
+    }
+}
+
+#[component]
+fn StatCards(stats: TodoStatsSummary) -> Element {
+    let average = stats
+        .average_seconds_to_complete
+        .map(duration_label)
+        .unwrap_or_else(|| "None yet".to_string());
+    let days = |count: i64| if count == 1 { "day" } else { "days" };
+
+    rsx! {
+        div { class: "stat-cards",
+            StatCard { label: "Open", value: stats.open.to_string() }
+            StatCard { label: "Completed", value: stats.completed.to_string() }
+            StatCard { label: "Overdue", value: stats.overdue.to_string() }
+            StatCard { label: "Average time to complete", value: average }
+            StatCard {
+                label: "Current streak",
+                value: format!("{} {}", stats.current_streak, days(stats.current_streak)),
+            }
+            StatCard {
+                label: "Longest streak",
+                value: format!("{} {}", stats.longest_streak, days(stats.longest_streak)),
+            }
+        }
+    }
+}
+
+#[component]
+fn StatCard(label: &'static str, value: String) -> Element {
+    rsx! {
+        div { class: "stat-card",
+            span { class: "stat-value", "{value}" }
+            span { class: "stat-label", "{label}" }
+        }
+    }
+}
+
+// One bar per day, scaled to the busiest day
+#[component]
+fn CompletionChart(days: Vec<DayCount>) -> Element {
+    let busiest = days
+        .iter()
+        .map(|day| day.completions)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let total: i64 = days.iter().map(|day| day.completions).sum();
+
+    rsx! {
+        div { class: "completion-chart",
+            for day in days {
+                div {
+                    key: "{day.date}",
+                    class: "chart-bar",
+                    title: format!("{}: {}", day.date.format("%a %-d %b"), day.completions),
+                    div {
+                        class: "chart-bar-fill",
+                        style: format!("height: {}%", day.completions * 100 / busiest),
+                    }
+                }
+            }
+        }
+        p { class: "chart-total", "{total} completed in this period" }
+    }
+}
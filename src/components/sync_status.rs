@@ -0,0 +1,39 @@
+#[cfg(feature = "offline")]
+use crate::backend::TodoItem;
+use dioxus::prelude::*;
+
+// Desktop/mobile: how many offline changes are still waiting for the server
+#[cfg(feature = "offline")]
+#[component]
+pub fn SyncStatus() -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
+
+    // Every list refresh runs a sync, so re-count whenever the list reloads
+    let pending = use_resource(move || {
+        let _ = todos.read();
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => crate::offline::pending_changes(&username).await,
+                None => 0,
+            }
+        }
+    });
+    let pending = pending.read().unwrap_or(0);
+
+    if pending == 0 {
+        return rsx! {};
+    }
+    let label = if pending == 1 { "change" } else { "changes" };
+    rsx! {
+        p { class: "sync-status", "Offline: {pending} {label} waiting to sync" }
+    }
+}
+
+// The web app talks to the server directly, so there is never anything queued
+#[cfg(not(feature = "offline"))]
+#[component]
+pub fn SyncStatus() -> Element {
+    rsx! {}
+}
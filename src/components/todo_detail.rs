@@ -0,0 +1,363 @@
+use crate::backend::activity::todo_history;
+use crate::backend::attachments::{
+    delete_attachment, encode_upload, upload_attachment, Attachment,
+};
+use crate::backend::{get_todo, update_todo_notes, TodoItem};
+use crate::components::activity::ActivityItem;
+use crate::components::comments::CommentThread;
+use crate::components::markdown::Markdown;
+use crate::errors::AppError;
+use crate::quick_add;
+use crate::validation;
+use crate::Route;
+use chrono::Local;
+use dioxus::prelude::*;
+
+// Todo detail Route: one todo with its notes, metadata, attachments,
+// comments and latest history
+#[component]
+pub fn TodoDetail(id: usize) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+
+    let mut todo = use_resource(use_reactive!(|id| {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => get_todo(username, id).await.map(Some),
+                None => Ok(None),
+            }
+        }
+    }));
+    let mut history = use_resource(use_reactive!(|id| {
+        let current_user = logged_in_user.read().clone();
+        async move {
+            match current_user {
+                Some(username) => todo_history(username, id).await,
+                None => Ok(Vec::new()),
+            }
+        }
+    }));
+
+    // Some(text) while the notes are being edited
+    let mut draft = use_signal(|| None::<String>);
+    let mut previewing = use_signal(|| false);
+    let mut notes_status = use_signal(String::new);
+
+    if logged_in_user.read().is_none() {
+        return rsx! {
+            p { "Please log in to see this todo." }
+            Link { to: Route::Home, "Go to login" }
+        };
+    }
+
+    let mut save_notes = move |version: i32| {
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        let notes = draft.read().clone().unwrap_or_default();
+        notes_status.set("Saving...".to_string());
+        spawn(async move {
+            match update_todo_notes(username, id, version, notes).await {
+                Ok(_) => {
+                    draft.set(None);
+                    previewing.set(false);
+                    notes_status.set(String::new());
+                    todo.restart();
+                    history.restart();
+                }
+                // Keep the draft; saving again after a reload overwrites theirs
+                Err(e) => {
+                    let message = match AppError::from_server_error(&e) {
+                        Some(conflict @ AppError::Conflict { .. }) => format!(
+                            "{} Reload to see the latest version; your draft is kept.",
+                            conflict
+                        ),
+                        _ => format!("Error saving notes: {}", e),
+                    };
+                    notes_status.set(message);
+                }
+            }
+        });
+    };
+
+    let current = todo.read().clone();
+    let item = match current {
+        Some(Ok(Some(item))) => item,
+        Some(Ok(None)) | None => {
+            return rsx! {
+                p { "Loading todo..." }
+            };
+        }
+        Some(Err(e)) => {
+            return rsx! {
+                p { color: "red", "Could not load todo #{id}: {e}" }
+                Link { to: Route::Home, "Back to my todos" }
+            };
+        }
+    };
+    let version = item.version;
+    let current_draft = draft.read().clone();
+    let notes = item.notes.clone();
+    let notes_to_edit = item.notes.clone();
+    let has_notes = !notes.trim().is_empty();
+
+    rsx! {
+        div { class: "todo-detail",
+            Link { to: Route::Home, "Back to my todos" }
+            h2 {
+                Markdown { content: item.content.clone() }
+            }
+            TodoFacts { todo: item.clone() }
+
+            h3 { "Notes" }
+            match current_draft {
+                Some(text) => rsx! {
+                    if previewing() {
+                        Markdown { class: "markdown-preview", content: text }
+                    } else {
+                        textarea {
+                            class: "notes-input",
+                            rows: 10,
+                            placeholder: "Details, links, checklists... Markdown works here.",
+                            value: "{text}",
+                            oninput: move |evt| draft.set(Some(evt.value())),
+                        }
+                    }
+                    div { class: "notes-buttons",
+                        button { onclick: move |_| save_notes(version), "Save notes" }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| previewing.set(!previewing()),
+                            if previewing() {
+                                "Write"
+                            } else {
+                                "Preview"
+                            }
+                        }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                notes_status.set(String::new());
+                                todo.restart();
+                            },
+                            "Reload"
+                        }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                draft.set(None);
+                                previewing.set(false);
+                                notes_status.set(String::new());
+                            },
+                            "Cancel"
+                        }
+                    }
+                },
+                None => rsx! {
+                    if has_notes {
+                        Markdown { class: "notes", content: notes }
+                    } else {
+                        p { class: "notes-empty", "No notes yet." }
+                    }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| draft.set(Some(notes_to_edit.clone())),
+                        if has_notes {
+                            "Edit notes"
+                        } else {
+                            "Add notes"
+                        }
+                    }
+                },
+            }
+            p { "{notes_status}" }
+
+            h3 { "Attachments" }
+            Attachments {
+                todo_id: id,
+                attachments: item.attachments.clone(),
+                on_change: move |_| todo.restart(),
+            }
+
+            h3 { "Comments" }
+            CommentThread { todo_id: id }
+
+            h3 { "History" }
+            match history.read().as_ref() {
+                Some(Ok(events)) => rsx! {
+                    ul {
+                        for event in events.iter().take(10).cloned() {
+                            ActivityItem { key: "{event.id}", event, show_link: false }
+                        }
+                    }
+                    if events.len() > 10 {
+                        Link {
+                            to: Route::Activity {
+                                todo: id.to_string(),
+                            },
+                            "Full history"
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Could not load history: {e}" }
+                },
+                None => rsx! {
+                    p { "Loading history..." }
+                },
+            }
+        }
+    }
+}
+
+// Files on the todo plus an upload button. Each picked file is checked here
+// first so a file that is too big is never sent.
+#[component]
+fn Attachments(todo_id: usize, attachments: Vec<Attachment>, on_change: EventHandler) -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut status = use_signal(String::new);
+    let mut uploading = use_signal(|| false);
+
+    let upload = move |evt: FormEvent| async move {
+        let Some(files) = evt.files() else {
+            return;
+        };
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        uploading.set(true);
+        let mut errors = Vec::new();
+        for path in files.files() {
+            // Desktop gives full paths
+            let file_name = path.rsplit(['/', '\\']).next().unwrap_or(&path).to_string();
+            let size = files.file_size(&path).await.unwrap_or(0) as usize;
+            if let Err(e) = validation::validate_attachment(&file_name, size) {
+                errors.push(format!("{}: {}", file_name, e));
+                continue;
+            }
+            status.set(format!("Uploading {}...", file_name));
+            let Some(data) = files.read_file(&path).await else {
+                errors.push(format!("{}: could not be read", file_name));
+                continue;
+            };
+            let result = upload_attachment(
+                username.clone(),
+                todo_id,
+                file_name.clone(),
+                encode_upload(&data),
+            )
+            .await;
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", file_name, e));
+            }
+        }
+        uploading.set(false);
+        status.set(errors.join(" "));
+        on_change.call(());
+    };
+
+    let remove = move |id: i32| {
+        let username = logged_in_user.read().clone().unwrap_or_default();
+        spawn(async move {
+            match delete_attachment(username, id).await {
+                Ok(()) => {
+                    status.set(String::new());
+                    on_change.call(());
+                }
+                Err(e) => status.set(format!("Error deleting attachment: {}", e)),
+            }
+        });
+    };
+
+    let max = validation::ATTACHMENTS_PER_TODO;
+    let full = attachments.len() >= max;
+
+    rsx! {
+        if attachments.is_empty() {
+            p { class: "notes-empty", "No attachments yet." }
+        }
+        ul { class: "attachments",
+            for attachment in attachments {
+                li { key: "{attachment.id}",
+                    a {
+                        href: attachment.url(),
+                        target: "_blank",
+                        rel: "noopener",
+                        if let Some(thumbnail) = attachment.thumbnail_url() {
+                            img {
+                                class: "attachment-thumb",
+                                src: thumbnail,
+                                alt: "",
+                            }
+                        }
+                        span { class: "attachment-name", "{attachment.file_name}" }
+                    }
+                    span { class: "attachment-size", {attachment.size_label()} }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| remove(attachment.id),
+                        "Remove"
+                    }
+                }
+            }
+        }
+        if full {
+            p { class: "notes-empty",
+                "This todo has the most attachments it can have ({max})."
+            }
+        } else {
+            label { class: "attachment-upload",
+                if uploading() {
+                    "Uploading..."
+                } else {
+                    "Attach files"
+                }
+                input {
+                    r#type: "file",
+                    multiple: true,
+                    disabled: uploading(),
+                    onchange: upload,
+                }
+            }
+        }
+        p { "{status}" }
+    }
+}
+
+// Status, due date, priority, list and tags
+#[component]
+fn TodoFacts(todo: TodoItem) -> Element {
+    let today = Local::now().date_naive();
+    let status = if todo.completed { "Done" } else { "Open" };
+    let due = todo
+        .due_date
+        .map(|date| quick_add::due_label(date, todo.due_time, today))
+        .unwrap_or_else(|| "No due date".to_string());
+    let priority = todo
+        .priority
+        .map(|priority| priority.label())
+        .unwrap_or("none");
+    let list = todo.list.clone().unwrap_or_else(|| "None".to_string());
+    let tags = if todo.tags.is_empty() {
+        "None".to_string()
+    } else {
+        todo.tags
+            .iter()
+            .map(|tag| format!("#{}", tag))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    rsx! {
+        dl { class: "todo-facts",
+            dt { "Status" }
+            dd { "{status}" }
+            dt { "Due" }
+            dd { "{due}" }
+            dt { "Priority" }
+            dd { "{priority}" }
+            dt { "List" }
+            dd { "{list}" }
+            dt { "Tags" }
+            dd { "{tags}" }
+            dt { "Version" }
+            dd { "{todo.version}" }
+        }
+    }
+}
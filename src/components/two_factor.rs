@@ -0,0 +1,192 @@
+use crate::backend::two_factor::{
+    begin_totp_enrollment, confirm_totp_enrollment, disable_two_factor, two_factor_enabled,
+    TotpEnrollment,
+};
+use crate::errors::AppError;
+use dioxus::prelude::*;
+
+// Two-factor section of the Account page: enroll an authenticator app or turn 2FA off
+#[component]
+pub fn TwoFactorSettings(username: String) -> Element {
+    let mut password = use_signal(String::new);
+    let mut code = use_signal(String::new);
+    let mut status = use_signal(String::new);
+
+    // Pending enrollment (QR code and secret) and the recovery codes shown once after it
+    let mut enrollment = use_signal(|| Option::<TotpEnrollment>::None);
+    let mut recovery_codes = use_signal(Vec::<String>::new);
+
+    let username_for_status = username.clone();
+    let mut enabled = use_resource(move || {
+        let username = username_for_status.clone();
+        async move { two_factor_enabled(username).await }
+    });
+
+    let username_for_begin = username.clone();
+    let username_for_confirm = username.clone();
+    let username_for_disable = username.clone();
+
+    let is_enabled = matches!(enabled.read().as_ref(), Some(Ok(true)));
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Two-Factor Authentication" }
+            match enabled.read().as_ref() {
+                Some(Ok(true)) => rsx! {
+                    p { "Two-factor authentication is on." }
+                },
+                Some(Ok(false)) => rsx! {
+                    p { "Protect your account with a code from an authenticator app." }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Error loading two-factor status: {e}" }
+                },
+                None => rsx! {
+                    p { "Loading..." }
+                },
+            }
+            p { "{status}" }
+
+            if !recovery_codes.read().is_empty() {
+                div { class: "recovery-codes",
+                    p { "Save these recovery codes somewhere safe. Each one can be used once if you lose your authenticator. They will not be shown again." }
+                    ul {
+                        for recovery_code in recovery_codes.read().iter() {
+                            li { key: "{recovery_code}", code { "{recovery_code}" } }
+                        }
+                    }
+                    button { onclick: move |_| recovery_codes.set(Vec::new()), "Done" }
+                }
+            } else if let Some(pending) = enrollment.read().clone() {
+                // --- Confirm Enrollment ---
+                div {
+                    p { "Scan this QR code with your authenticator app, then enter the code it shows." }
+                    img {
+                        src: "data:image/png;base64,{pending.qr_png_base64}",
+                        alt: "Authenticator QR code",
+                        width: "200",
+                        height: "200",
+                    }
+                    p { "Or enter this key manually: " code { "{pending.secret}" } }
+                    input {
+                        r#type: "text",
+                        placeholder: "6-digit code",
+                        autocomplete: "one-time-code",
+                        value: "{code}",
+                        oninput: move |evt| {
+                            code.set(evt.value());
+                            status.set(String::new());
+                        },
+                    }
+                    button {
+                        onclick: move |_| {
+                            let entered = code.read().trim().to_string();
+                            if entered.is_empty() {
+                                status.set("Enter the code from your authenticator app.".to_string());
+                                return;
+                            }
+                            status.set("Verifying...".to_string());
+                            let username = username_for_confirm.clone();
+                            spawn(async move {
+                                match confirm_totp_enrollment(username, entered).await {
+                                    Ok(codes) => {
+                                        status.set("Two-factor authentication enabled.".to_string());
+                                        enrollment.set(None);
+                                        code.set(String::new());
+                                        recovery_codes.set(codes);
+                                        enabled.restart();
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Confirm 2FA error: {:?}", e);
+                                        status.set(format!("Verification failed: {}", e));
+                                    }
+                                }
+                            });
+                        },
+                        "Confirm"
+                    }
+                    button {
+                        background_color: "#6c757d",
+                        margin_left: "10px",
+                        onclick: move |_| {
+                            enrollment.set(None);
+                            code.set(String::new());
+                            status.set(String::new());
+                        },
+                        "Cancel"
+                    }
+                }
+            } else {
+                input {
+                    r#type: "password",
+                    placeholder: "Current password",
+                    value: "{password}",
+                    oninput: move |evt| {
+                        password.set(evt.value());
+                        status.set(String::new());
+                    },
+                }
+                if is_enabled {
+                    button {
+                        background_color: "#dc3545",
+                        onclick: move |_| {
+                            let current = password.read().clone();
+                            if current.is_empty() {
+                                status.set("Enter your current password.".to_string());
+                                return;
+                            }
+                            status.set("Disabling...".to_string());
+                            let username = username_for_disable.clone();
+                            spawn(async move {
+                                match disable_two_factor(username, current).await {
+                                    Ok(_) => {
+                                        status.set("Two-factor authentication disabled.".to_string());
+                                        password.set(String::new());
+                                        enabled.restart();
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Disable 2FA error: {:?}", e);
+                                        match AppError::from_server_error(&e) {
+                                            Some(app_error) => status.set(app_error.to_string()),
+                                            None => status.set(format!("Disable failed: {}", e)),
+                                        }
+                                    }
+                                }
+                            });
+                        },
+                        "Disable Two-Factor"
+                    }
+                } else {
+                    button {
+                        onclick: move |_| {
+                            let current = password.read().clone();
+                            if current.is_empty() {
+                                status.set("Enter your current password.".to_string());
+                                return;
+                            }
+                            status.set("Starting setup...".to_string());
+                            let username = username_for_begin.clone();
+                            spawn(async move {
+                                match begin_totp_enrollment(username, current).await {
+                                    Ok(pending) => {
+                                        status.set(String::new());
+                                        password.set(String::new());
+                                        enrollment.set(Some(pending));
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Begin 2FA error: {:?}", e);
+                                        match AppError::from_server_error(&e) {
+                                            Some(app_error) => status.set(app_error.to_string()),
+                                            None => status.set(format!("Setup failed: {}", e)),
+                                        }
+                                    }
+                                }
+                            });
+                        },
+                        "Enable Two-Factor"
+                    }
+                }
+            }
+        }
+    }
+}
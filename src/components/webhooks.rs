@@ -0,0 +1,286 @@
+use crate::backend::webhooks::{
+    create_webhook, delete_webhook, list_webhook_deliveries, list_webhooks, send_test_webhook,
+    DeliveryStatus, NewWebhook, WebhookDelivery, WebhookEvent, WebhookInfo,
+};
+use crate::errors::AppError;
+use dioxus::prelude::*;
+
+// Webhooks section of the Account page: add, test and delete webhooks, and
+// see their deliveries
+#[component]
+pub fn Webhooks(username: String) -> Element {
+    let mut url = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let mut selected_events = use_signal(|| WebhookEvent::ALL.to_vec());
+    let mut status = use_signal(String::new);
+
+    // The new secret is only shown until the user dismisses it
+    let mut created = use_signal(|| Option::<NewWebhook>::None);
+
+    let username_for_list = username.clone();
+    let mut webhooks = use_resource(move || {
+        let username = username_for_list.clone();
+        async move { list_webhooks(username).await }
+    });
+
+    let username_for_create = username.clone();
+
+    rsx! {
+        div { class: "auth-form",
+            h2 { "Webhooks" }
+            p {
+                "URLs that get a signed JSON POST when your todos are added, completed, changed or deleted."
+            }
+            p { "{status}" }
+
+            if let Some(new_webhook) = created.read().clone() {
+                div { class: "new-token",
+                    p {
+                        "Copy the signing secret for {new_webhook.info.url} now. It will not be shown again."
+                    }
+                    code { "{new_webhook.secret}" }
+                    div {
+                        button { onclick: move |_| created.set(None), "Done" }
+                    }
+                }
+            }
+
+            match webhooks.read().as_ref() {
+                Some(Ok(list)) if list.is_empty() => rsx! {
+                    p { class: "no-todos-message", "No webhooks." }
+                },
+                Some(Ok(list)) => rsx! {
+                    ul { class: "webhooks",
+                        for webhook in list.iter().cloned() {
+                            WebhookItem {
+                                key: "{webhook.id}",
+                                username: username.clone(),
+                                webhook,
+                                on_delete: move |_| webhooks.restart(),
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Error loading webhooks: {e}" }
+                },
+                None => rsx! {
+                    p { "Loading..." }
+                },
+            }
+
+            // --- Add Webhook Form ---
+            input {
+                r#type: "url",
+                placeholder: "https://example.com/hooks/todo",
+                value: "{url}",
+                oninput: move |evt| {
+                    url.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            for (event , event_name , event_label) in WebhookEvent::ALL.map(|e| (e, e.as_str(), e.label())) {
+                label { key: "{event_name}",
+                    input {
+                        r#type: "checkbox",
+                        checked: selected_events.read().contains(&event),
+                        onchange: move |evt| {
+                            let mut events = selected_events.write();
+                            events.retain(|e| *e != event);
+                            if evt.checked() {
+                                events.push(event);
+                            }
+                        },
+                    }
+                    " {event_label} ({event_name})"
+                }
+            }
+            input {
+                r#type: "password",
+                placeholder: "Current password",
+                value: "{password}",
+                oninput: move |evt| {
+                    password.set(evt.value());
+                    status.set(String::new());
+                },
+            }
+            button {
+                onclick: move |_| {
+                    let target = url.read().trim().to_string();
+                    let current = password.read().clone();
+                    let events = selected_events.read().clone();
+                    if target.is_empty() {
+                        status.set("Enter the URL to send to.".to_string());
+                        return;
+                    }
+                    if events.is_empty() {
+                        status.set("Pick at least one event.".to_string());
+                        return;
+                    }
+                    if current.is_empty() {
+                        status.set("Enter your current password.".to_string());
+                        return;
+                    }
+                    status.set("Adding webhook...".to_string());
+                    let username = username_for_create.clone();
+                    spawn(async move {
+                        match create_webhook(username, current, target, events).await {
+                            Ok(new_webhook) => {
+                                status.set(String::new());
+                                url.set(String::new());
+                                password.set(String::new());
+                                created.set(Some(new_webhook));
+                                webhooks.restart();
+                            }
+                            Err(e) => {
+                                eprintln!("Create webhook error: {:?}", e);
+                                match AppError::from_server_error(&e) {
+                                    Some(app_error) => status.set(app_error.to_string()),
+                                    None => status.set(format!("Adding webhook failed: {}", e)),
+                                }
+                            }
+                        }
+                    });
+                },
+                "Add Webhook"
+            }
+        }
+    }
+}
+
+// One webhook, with its delivery log behind a toggle
+#[component]
+fn WebhookItem(username: String, webhook: WebhookInfo, on_delete: EventHandler) -> Element {
+    let mut show_log = use_signal(|| false);
+    let mut status = use_signal(String::new);
+
+    let id = webhook.id;
+    let username_for_log = username.clone();
+    let mut deliveries = use_resource(move || {
+        let username = username_for_log.clone();
+        let shown = show_log();
+        async move {
+            if shown {
+                list_webhook_deliveries(username, id).await.map(Some)
+            } else {
+                Ok(None)
+            }
+        }
+    });
+
+    let events = webhook
+        .events
+        .iter()
+        .map(|event| event.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let username_for_test = username.clone();
+    let username_for_delete = username.clone();
+
+    rsx! {
+        li { class: "webhook",
+            div {
+                code { "{webhook.url}" }
+                span { class: "activity-time", " {events}, added {webhook.created_at}" }
+            }
+            div { class: "notes-buttons",
+                button {
+                    class: "secondary",
+                    onclick: move |_| {
+                        let username = username_for_test.clone();
+                        spawn(async move {
+                            match send_test_webhook(username, id).await {
+                                Ok(()) => {
+                                    status.set("Test queued; it is sent within a few seconds.".to_string());
+                                    show_log.set(true);
+                                    deliveries.restart();
+                                }
+                                Err(e) => status.set(format!("Sending test failed: {}", e)),
+                            }
+                        });
+                    },
+                    "Send Test"
+                }
+                button {
+                    class: "secondary",
+                    onclick: move |_| show_log.set(!show_log()),
+                    if show_log() { "Hide Deliveries" } else { "Deliveries" }
+                }
+                button {
+                    class: "secondary",
+                    onclick: move |_| {
+                        let username = username_for_delete.clone();
+                        spawn(async move {
+                            match delete_webhook(username, id).await {
+                                Ok(()) => on_delete.call(()),
+                                Err(e) => status.set(format!("Delete failed: {}", e)),
+                            }
+                        });
+                    },
+                    "Delete"
+                }
+            }
+            if !status.read().is_empty() {
+                p { class: "row-status", "{status}" }
+            }
+            match deliveries.read().as_ref() {
+                Some(Ok(Some(list))) => rsx! {
+                    div { class: "delivery-log",
+                        button { class: "secondary", onclick: move |_| deliveries.restart(), "Refresh" }
+                        if list.is_empty() {
+                            p { class: "notes-empty", "Nothing sent yet." }
+                        } else {
+                            table {
+                                thead {
+                                    tr {
+                                        th { "Event" }
+                                        th { "Queued" }
+                                        th { "Status" }
+                                        th { "Tries" }
+                                        th { "Last result" }
+                                    }
+                                }
+                                tbody {
+                                    for delivery in list.iter().cloned() {
+                                        DeliveryRow { key: "{delivery.id}", delivery }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! {
+                    p { color: "red", "Error loading deliveries: {e}" }
+                },
+                Some(Ok(None)) | None => rsx! {},
+            }
+        }
+    }
+}
+
+#[component]
+fn DeliveryRow(delivery: WebhookDelivery) -> Element {
+    let status = match (delivery.status, &delivery.next_attempt_at) {
+        (DeliveryStatus::Pending, Some(next)) if delivery.attempts > 0 => {
+            format!("retrying at {}", next)
+        }
+        (status, _) => status.as_str().to_string(),
+    };
+    let result = match (&delivery.last_error, delivery.response_status) {
+        (Some(error), _) => error.clone(),
+        (None, Some(code)) => format!("HTTP {}", code),
+        (None, None) => String::new(),
+    };
+
+    let class = format!("delivery-{}", delivery.status.as_str());
+
+    rsx! {
+        tr { class,
+            td { "{delivery.event}" }
+            td { "{delivery.created_at}" }
+            td { "{status}" }
+            td { "{delivery.attempts}" }
+            td { class: "delivery-result", "{result}" }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use dioxus::prelude::*;
+use std::fmt;
+
+// Errors the UI needs to tell apart from a plain failure message.
+//
+// Server functions return the default `ServerFnError`, so these travel as
+// `ServerFnError::ServerError` with a machine-readable prefix that
+// `from_server_error` parses back on the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    // Too many attempts; the client may retry after this many seconds
+    RateLimited { retry_after_secs: u64 },
+    // A todo changed since the client loaded it. Holds the version it has
+    // now, or None if it was deleted.
+    Conflict { current_version: Option<i32> },
+}
+
+const RATE_LIMITED_TAG: &str = "rate_limited";
+const CONFLICT_TAG: &str = "conflict";
+const DELETED_VALUE: &str = "deleted";
+
+impl AppError {
+    #[cfg(any(feature = "server", feature = "offline"))]
+    pub fn into_server_error(self) -> ServerFnError {
+        let tagged = match &self {
+            AppError::RateLimited { retry_after_secs } => {
+                format!("{}:{}", RATE_LIMITED_TAG, retry_after_secs)
+            }
+            AppError::Conflict {
+                current_version: Some(version),
+            } => format!("{}:{}", CONFLICT_TAG, version),
+            AppError::Conflict {
+                current_version: None,
+            } => format!("{}:{}", CONFLICT_TAG, DELETED_VALUE),
+        };
+        ServerFnError::ServerError(format!("{}|{}", tagged, self))
+    }
+
+    pub fn from_server_error(error: &ServerFnError) -> Option<Self> {
+        let ServerFnError::ServerError(message) = error else {
+            return None;
+        };
+        let (tagged, _) = message.split_once('|')?;
+        let (tag, value) = tagged.split_once(':')?;
+        match tag {
+            RATE_LIMITED_TAG => Some(AppError::RateLimited {
+                retry_after_secs: value.parse().ok()?,
+            }),
+            CONFLICT_TAG if value == DELETED_VALUE => Some(AppError::Conflict {
+                current_version: None,
+            }),
+            CONFLICT_TAG => Some(AppError::Conflict {
+                current_version: Some(value.parse().ok()?),
+            }),
+            _ => None,
+        }
+    }
+
+    // Short label for metrics and logs
+    #[cfg(feature = "server")]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::RateLimited { .. } => RATE_LIMITED_TAG,
+            AppError::Conflict { .. } => CONFLICT_TAG,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::RateLimited { retry_after_secs } => write!(
+                f,
+                "Too many attempts. Try again in {} seconds.",
+                retry_after_secs
+            ),
+            AppError::Conflict {
+                current_version: Some(_),
+            } => write!(f, "This todo was changed somewhere else."),
+            AppError::Conflict {
+                current_version: None,
+            } => write!(f, "This todo was deleted somewhere else."),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+// Liveness/readiness probes and graceful shutdown.
+//
+// `/healthz` only says the process is serving requests. `/readyz` says the
+// database is reachable and our tables exist, and flips to 503 as soon as a
+// shutdown signal arrives. We keep accepting connections for a pre-stop delay
+// after that, so the orchestrator sees the 503 and stops routing to us before
+// connections are refused, and then let in-flight server function calls drain.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::backend::server_utils::{get_db_pool, MIGRATED_TABLES};
+
+// How long `/readyz` may spend talking to the database
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+// Default time `/readyz` reports 503 before we stop accepting connections
+const DEFAULT_PRE_STOP_DELAY_SECS: u64 = 5;
+// Default time allowed for in-flight calls once we stop accepting connections
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+static SHUTDOWN: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::channel(false).0);
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+// Held for the duration of a server function call by `telemetry::observe`
+pub struct InFlightCall(());
+
+impl Drop for InFlightCall {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn track_call() -> InFlightCall {
+    IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+    InFlightCall(())
+}
+
+// GET /healthz
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+// GET /readyz
+pub async fn readyz() -> Response {
+    if is_shutting_down() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "shutting down").into_response();
+    }
+
+    match tokio::time::timeout(READY_CHECK_TIMEOUT, check_database()).await {
+        Ok(Ok(())) => (StatusCode::OK, "ready").into_response(),
+        Ok(Err(reason)) => (StatusCode::SERVICE_UNAVAILABLE, reason).into_response(),
+        Err(_) => {
+            tracing::warn!("Readiness check timed out");
+            (StatusCode::SERVICE_UNAVAILABLE, "database check timed out").into_response()
+        }
+    }
+}
+
+// Resolves `SHUTDOWN_PRE_STOP_DELAY_SECS` after Ctrl+C or SIGTERM; passed to
+// `axum::serve(..).with_graceful_shutdown`
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for Ctrl+C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    begin_shutdown(pre_stop_delay()).await;
+}
+
+// Flip `/readyz` to 503, then keep serving for `delay` before axum stops
+// accepting connections
+async fn begin_shutdown(delay: Duration) {
+    SHUTDOWN.send_replace(true);
+    tracing::info!(
+        pre_stop_delay_secs = delay.as_secs(),
+        "Shutdown signal received, reporting not ready"
+    );
+    tokio::time::sleep(delay).await;
+    tracing::info!(
+        in_flight = IN_FLIGHT.load(Ordering::SeqCst),
+        "Draining in-flight calls"
+    );
+}
+
+// Resolves once the pre-stop delay and then the drain timeout
+// (`SHUTDOWN_DRAIN_TIMEOUT_SECS`) have passed after a shutdown signal, so a
+// stuck request can't block exit forever
+pub async fn drain_deadline() {
+    let timeout =
+        pre_stop_delay() + env_secs("SHUTDOWN_DRAIN_TIMEOUT_SECS", DEFAULT_DRAIN_TIMEOUT_SECS);
+
+    let mut shutdown = SHUTDOWN.subscribe();
+    if shutdown
+        .wait_for(|shutting_down| *shutting_down)
+        .await
+        .is_err()
+    {
+        std::future::pending::<()>().await;
+    }
+    tokio::time::sleep(timeout).await;
+    tracing::warn!(
+        in_flight = IN_FLIGHT.load(Ordering::SeqCst),
+        "Drain timeout elapsed, exiting with calls still in flight"
+    );
+}
+
+fn pre_stop_delay() -> Duration {
+    env_secs("SHUTDOWN_PRE_STOP_DELAY_SECS", DEFAULT_PRE_STOP_DELAY_SECS)
+}
+
+fn env_secs(name: &str, default: u64) -> Duration {
+    let secs = std::env::var(name)
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
+fn is_shutting_down() -> bool {
+    *SHUTDOWN.borrow()
+}
+
+// Initializes the pool on first use, which also runs the migrations
+async fn check_database() -> Result<(), String> {
+    let pool = get_db_pool().await.map_err(|e| {
+        tracing::warn!(error = %e, "Database pool unavailable");
+        "database unavailable".to_string()
+    })?;
+
+    for table in MIGRATED_TABLES {
+        let exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = $1)",
+        )
+        .bind(*table)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| {
+            tracing::warn!(error = %e, "Readiness query failed");
+            "database query failed".to_string()
+        })?;
+
+        if !exists {
+            tracing::warn!(table, "Readiness check found a missing table");
+            return Err(format!("missing table '{}'", table));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn healthz_is_always_ok() {
+        assert_eq!(healthz().await, "ok");
+    }
+
+    // The only test that sets the shutdown flag, since it is global
+    #[tokio::test]
+    async fn readyz_reports_shutdown_before_connections_stop() {
+        assert!(!is_shutting_down());
+        // No database in tests, but it must not be the shutdown answer
+        let response = readyz().await;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_ne!(&body[..], b"shutting down");
+
+        let shutdown = tokio::spawn(begin_shutdown(Duration::from_millis(300)));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Still inside the pre-stop delay: probes already see 503
+        let response = readyz().await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"shutting down");
+        assert!(!shutdown.is_finished());
+        assert_eq!(healthz().await, "ok");
+
+        shutdown.await.unwrap();
+        assert_eq!(readyz().await.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,347 @@
+// iCalendar feed of a user's todos with due dates at `/calendar/<token>.ics`,
+// for calendar apps to subscribe to. The token comes from
+// `backend::calendar_feed`; an unknown one gets a 404.
+//
+// By default every todo is a VEVENT, since most calendar apps ignore tasks:
+// all day on its due date, or EVENT_MINUTES long at its due time. Done todos
+// stay in the feed with a check mark. `?kind=todos` gives VTODOs instead,
+// with their completion as STATUS, for apps that show tasks. Due times are
+// wall-clock times, so they are written without a time zone ("floating").
+
+use axum::{
+    extract::{Path, Query},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+
+use crate::backend::password_reset::app_base_url;
+use crate::backend::server_utils::get_db_pool;
+use crate::backend::Priority;
+use crate::secrets;
+
+// How far back the feed goes, and the most todos it holds
+const HISTORY_DAYS: i32 = 90;
+const FEED_LIMIT: i64 = 1000;
+const EVENT_MINUTES: i64 = 30;
+// Lines longer than this many bytes are folded (RFC 5545, 3.1)
+const MAX_LINE_BYTES: usize = 75;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Events,
+    Todos,
+}
+
+#[derive(Deserialize)]
+struct FeedParams {
+    kind: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct FeedTodo {
+    id: i32,
+    content: String,
+    notes: String,
+    completed: bool,
+    version: i32,
+    due_date: NaiveDate,
+    due_time: Option<NaiveTime>,
+    priority: Option<i16>,
+    updated_at: DateTime<Utc>,
+}
+
+pub fn router() -> Router {
+    Router::new().route("/calendar/:file", get(feed))
+}
+
+// GET /calendar/{token}.ics
+async fn feed(Path(file): Path<String>, Query(params): Query<FeedParams>) -> Response {
+    let kind = match params.kind.as_deref() {
+        None | Some("events") => Kind::Events,
+        Some("todos") => Kind::Todos,
+        Some(_) => {
+            return (StatusCode::BAD_REQUEST, "kind must be events or todos").into_response()
+        }
+    };
+    let Some(token) = file.strip_suffix(".ics") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let todos = match feed_todos(token).await {
+        Ok(Some(todos)) => todos,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(e) => {
+            tracing::error!(error = ?e, "SQLx error");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "inline; filename=\"todos.ics\"",
+            ),
+            (header::CACHE_CONTROL, "private, max-age=300"),
+        ],
+        calendar(&todos, kind, Utc::now(), &app_base_url()),
+    )
+        .into_response()
+}
+
+// The feed owner's todos, or None for an unknown token
+async fn feed_todos(token: &str) -> Result<Option<Vec<FeedTodo>>, sqlx::Error> {
+    let pool = get_db_pool().await?;
+    let user_id: Option<i32> = sqlx::query_scalar(
+        "UPDATE calendar_feeds SET last_used_at = now() WHERE token_hash = $1 RETURNING user_id",
+    )
+    .bind(secrets::hash_token(token))
+    .fetch_optional(pool)
+    .await?;
+    let Some(user_id) = user_id else {
+        return Ok(None);
+    };
+
+    let todos = sqlx::query_as(
+        r#"
+        SELECT id, content, notes, completed, version, due_date, due_time, priority, updated_at
+        FROM todos
+        WHERE user_id = $1 AND due_date >= CURRENT_DATE - $2
+        ORDER BY due_date, due_time NULLS FIRST, id
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(HISTORY_DAYS)
+    .bind(FEED_LIMIT)
+    .fetch_all(pool)
+    .await?;
+    tracing::debug!(user_id, "Calendar feed served");
+    Ok(Some(todos))
+}
+
+fn calendar(todos: &[FeedTodo], kind: Kind, now: DateTime<Utc>, base_url: &str) -> String {
+    // UIDs must be globally unique, so they name this server
+    let host = base_url
+        .split_once("://")
+        .map_or(base_url, |(_, host)| host);
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Todo//Calendar feed//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        "X-WR-CALNAME:Todos".to_string(),
+        "REFRESH-INTERVAL;VALUE=DURATION:PT1H".to_string(),
+        "X-PUBLISHED-TTL:PT1H".to_string(),
+    ];
+
+    for todo in todos {
+        let component = match kind {
+            Kind::Events => "VEVENT",
+            Kind::Todos => "VTODO",
+        };
+        lines.push(format!("BEGIN:{}", component));
+        lines.push(format!("UID:todo-{}@{}", todo.id, host));
+        lines.push(format!("DTSTAMP:{}", utc_stamp(now)));
+        lines.push(format!("LAST-MODIFIED:{}", utc_stamp(todo.updated_at)));
+        lines.push(format!("SEQUENCE:{}", todo.version));
+        lines.push(format!("URL:{}/todo/{}", base_url, todo.id));
+        if !todo.notes.trim().is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&todo.notes)));
+        }
+
+        match kind {
+            Kind::Events => {
+                let mark = if todo.completed { "✓ " } else { "" };
+                lines.push(format!("SUMMARY:{}{}", mark, escape(&todo.content)));
+                match todo.due_time {
+                    Some(time) => {
+                        let start = todo.due_date.and_time(time);
+                        let end = start + Duration::minutes(EVENT_MINUTES);
+                        lines.push(format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")));
+                        lines.push(format!("DTEND:{}", end.format("%Y%m%dT%H%M%S")));
+                    }
+                    None => {
+                        let end = todo.due_date + Duration::days(1);
+                        lines.push(format!(
+                            "DTSTART;VALUE=DATE:{}",
+                            todo.due_date.format("%Y%m%d")
+                        ));
+                        lines.push(format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+                    }
+                }
+                // Todos don't make the user busy
+                lines.push("TRANSP:TRANSPARENT".to_string());
+            }
+            Kind::Todos => {
+                lines.push(format!("SUMMARY:{}", escape(&todo.content)));
+                match todo.due_time {
+                    Some(time) => lines.push(format!(
+                        "DUE:{}",
+                        todo.due_date.and_time(time).format("%Y%m%dT%H%M%S")
+                    )),
+                    None => {
+                        lines.push(format!("DUE;VALUE=DATE:{}", todo.due_date.format("%Y%m%d")))
+                    }
+                }
+                if todo.completed {
+                    lines.push("STATUS:COMPLETED".to_string());
+                    lines.push("PERCENT-COMPLETE:100".to_string());
+                } else {
+                    lines.push("STATUS:NEEDS-ACTION".to_string());
+                }
+                if let Some(priority) = todo.priority.and_then(Priority::from_i16) {
+                    lines.push(format!("PRIORITY:{}", ics_priority(priority)));
+                }
+            }
+        }
+        lines.push(format!("END:{}", component));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+fn utc_stamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// 1 is the highest and 9 the lowest
+fn ics_priority(priority: Priority) -> u8 {
+    match priority {
+        Priority::High => 1,
+        Priority::Medium => 5,
+        Priority::Low => 9,
+    }
+}
+
+// A TEXT value (RFC 5545, 3.3.11)
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// A content line ending in CRLF, split into lines of at most MAX_LINE_BYTES
+// (without splitting a character), each continuation starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_BYTES {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn todo(due_time: Option<NaiveTime>, completed: bool) -> FeedTodo {
+        FeedTodo {
+            id: 7,
+            content: "Pay rent, then call; Bob".to_string(),
+            notes: String::new(),
+            completed,
+            version: 3,
+            due_date: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+            due_time,
+            priority: Some(Priority::High.as_i16()),
+            updated_at: Utc.with_ymd_and_hms(2026, 10, 1, 8, 0, 0).unwrap(),
+        }
+    }
+
+    fn render(todos: &[FeedTodo], kind: Kind) -> String {
+        let now = Utc.with_ymd_and_hms(2026, 10, 19, 12, 0, 0).unwrap();
+        calendar(todos, kind, now, "https://todo.example.com")
+    }
+
+    #[test]
+    fn escapes_text_values() {
+        assert_eq!(escape(r"a\b;c,d"), r"a\\b\;c\,d");
+        assert_eq!(escape("line one\r\nline two"), r"line one\nline two");
+        assert_eq!(escape("plain: text"), "plain: text");
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        assert_eq!(fold("SUMMARY:milk"), "SUMMARY:milk\r\n");
+        let exact = "x".repeat(MAX_LINE_BYTES);
+        assert_eq!(fold(&exact), format!("{}\r\n", exact));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let folded = fold(&"x".repeat(160));
+        let lines: Vec<&str> = folded.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), MAX_LINE_BYTES);
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_BYTES));
+        // Unfolding gives the original line back
+        assert_eq!(lines.concat().replace(' ', ""), "x".repeat(160));
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold(&line);
+        for part in folded.trim_end_matches("\r\n").split("\r\n") {
+            assert!(part.len() <= MAX_LINE_BYTES);
+        }
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), line);
+    }
+
+    #[test]
+    fn all_day_events() {
+        let feed = render(&[todo(None, false)], Kind::Events);
+        assert!(feed.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(feed.ends_with("END:VCALENDAR\r\n"));
+        assert!(feed.contains("UID:todo-7@todo.example.com\r\n"));
+        assert!(feed.contains("SUMMARY:Pay rent\\, then call\\; Bob\r\n"));
+        assert!(feed.contains("DTSTART;VALUE=DATE:20261019\r\n"));
+        assert!(feed.contains("DTEND;VALUE=DATE:20261020\r\n"));
+        assert!(feed.contains("LAST-MODIFIED:20261001T080000Z\r\n"));
+        assert!(feed.contains("SEQUENCE:3\r\n"));
+    }
+
+    #[test]
+    fn timed_events_are_floating() {
+        let due = NaiveTime::from_hms_opt(15, 0, 0);
+        let feed = render(&[todo(due, true)], Kind::Events);
+        assert!(feed.contains("SUMMARY:✓ Pay rent"));
+        assert!(feed.contains("DTSTART:20261019T150000\r\n"));
+        assert!(feed.contains("DTEND:20261019T153000\r\n"));
+    }
+
+    #[test]
+    fn todos_carry_status_and_priority() {
+        let feed = render(&[todo(None, true)], Kind::Todos);
+        assert!(feed.contains("BEGIN:VTODO\r\n"));
+        assert!(feed.contains("DUE;VALUE=DATE:20261019\r\n"));
+        assert!(feed.contains("STATUS:COMPLETED\r\n"));
+        assert!(feed.contains("PRIORITY:1\r\n"));
+        assert!(!feed.contains("VEVENT"));
+    }
+}
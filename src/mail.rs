@@ -0,0 +1,235 @@
+// Outgoing mail behind a small trait so the transport can be swapped.
+//
+// `MAIL_TRANSPORT` picks the implementation:
+// - `smtp`: sends through `SMTP_HOST` (with optional `SMTP_PORT`,
+//   `SMTP_USERNAME`, `SMTP_PASSWORD`) from `MAIL_FROM`
+// - `file`: appends each message to `MAIL_FILE` (default `mail.log`)
+// - `stdout` (default): prints each message, for local testing
+//
+// Mails carry live reset links, so a broken `smtp` setup or an unknown
+// transport is fatal at startup (see `check_config`) rather than quietly
+// printing them to the logs.
+
+use async_trait::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::io::AsyncWriteExt;
+
+pub type MailError = Box<dyn std::error::Error + Send + Sync>;
+
+const DEFAULT_FROM: &str = "Todo list <no-reply@localhost>";
+
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), MailError>;
+}
+
+pub struct SmtpSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpSender {
+    pub fn from_env() -> Result<Self, MailError> {
+        let host = std::env::var("SMTP_HOST").map_err(|_| "SMTP_HOST is not set")?;
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?;
+        if let Ok(port) = std::env::var("SMTP_PORT") {
+            builder = builder.port(port.parse()?);
+        }
+        if let (Ok(username), Ok(password)) = (
+            std::env::var("SMTP_USERNAME"),
+            std::env::var("SMTP_PASSWORD"),
+        ) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Ok(SmtpSender {
+            transport: builder.build(),
+            from: mail_from().parse()?,
+        })
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpSender {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(email.subject)
+            .body(email.body)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+// Writes messages to a file, or to stdout when no path is given
+pub struct FileSender {
+    path: Option<PathBuf>,
+}
+
+impl FileSender {
+    pub fn stdout() -> Self {
+        FileSender { path: None }
+    }
+
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        FileSender {
+            path: Some(path.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl MailSender for FileSender {
+    async fn send(&self, email: Email) -> Result<(), MailError> {
+        let rendered = format!(
+            "From: {}\nTo: {}\nSubject: {}\n\n{}\n---\n",
+            mail_from(),
+            email.to,
+            email.subject,
+            email.body
+        );
+
+        match &self.path {
+            Some(path) => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(rendered.as_bytes()).await?;
+                // tokio finishes writes in the background; don't return
+                // before the message is actually in the file
+                file.flush().await?;
+            }
+            None => println!("{}", rendered),
+        }
+        Ok(())
+    }
+}
+
+// The configured sender, built on first use. If the configuration is broken
+// every send fails with the reason.
+pub fn mail_sender() -> &'static dyn MailSender {
+    match configured() {
+        Ok(sender) => sender.as_ref(),
+        Err(error) => error,
+    }
+}
+
+// Called before serving so a misconfigured transport stops the server
+pub fn check_config() -> Result<(), String> {
+    configured()
+        .as_ref()
+        .map(|_| ())
+        .map_err(|e| e.reason.clone())
+}
+
+fn configured() -> &'static Result<Box<dyn MailSender>, Misconfigured> {
+    static SENDER: OnceLock<Result<Box<dyn MailSender>, Misconfigured>> = OnceLock::new();
+
+    SENDER.get_or_init(|| {
+        let transport = std::env::var("MAIL_TRANSPORT").unwrap_or_else(|_| "stdout".into());
+        tracing::info!(%transport, "Initializing mail transport");
+        let sender: Box<dyn MailSender> = match transport.as_str() {
+            "smtp" => match SmtpSender::from_env() {
+                Ok(sender) => Box::new(sender),
+                Err(e) => {
+                    tracing::error!(error = %e, "SMTP setup failed");
+                    return Err(Misconfigured {
+                        reason: format!("MAIL_TRANSPORT=smtp but SMTP setup failed: {}", e),
+                    });
+                }
+            },
+            "file" => Box::new(FileSender::file(
+                std::env::var("MAIL_FILE").unwrap_or_else(|_| "mail.log".into()),
+            )),
+            "stdout" => Box::new(FileSender::stdout()),
+            other => {
+                return Err(Misconfigured {
+                    reason: format!(
+                        "Unknown MAIL_TRANSPORT '{}' (expected smtp, file or stdout)",
+                        other
+                    ),
+                })
+            }
+        };
+        Ok(sender)
+    })
+}
+
+// Stands in for the sender when the configuration is broken
+struct Misconfigured {
+    reason: String,
+}
+
+#[async_trait]
+impl MailSender for Misconfigured {
+    async fn send(&self, _email: Email) -> Result<(), MailError> {
+        Err(self.reason.clone().into())
+    }
+}
+
+fn mail_from() -> String {
+    std::env::var("MAIL_FROM").unwrap_or_else(|_| DEFAULT_FROM.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(to: &str) -> Email {
+        Email {
+            to: to.to_string(),
+            subject: "Reset your Todo list password".to_string(),
+            body: "Open this link:\nhttps://todo.example.com/reset-password?token=abc".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn file_sender_appends_each_message() {
+        let path = std::env::temp_dir().join(format!("todo-mail-{}.log", uuid::Uuid::new_v4()));
+        let sender = FileSender::file(&path);
+        sender.send(email("alice@example.com")).await.unwrap();
+        sender.send(email("bob@example.com")).await.unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let from = mail_from();
+        assert_eq!(
+            written,
+            format!(
+                "From: {from}\nTo: alice@example.com\nSubject: Reset your Todo list password\n\n\
+                 Open this link:\nhttps://todo.example.com/reset-password?token=abc\n---\n\
+                 From: {from}\nTo: bob@example.com\nSubject: Reset your Todo list password\n\n\
+                 Open this link:\nhttps://todo.example.com/reset-password?token=abc\n---\n"
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn file_sender_reports_unwritable_paths() {
+        let sender = FileSender::file(std::env::temp_dir().join("no-such-dir/mail.log"));
+        assert!(sender.send(email("alice@example.com")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn misconfigured_transport_fails_every_send() {
+        let sender = Misconfigured {
+            reason: "SMTP_HOST is not set".to_string(),
+        };
+        let error = sender.send(email("alice@example.com")).await.unwrap_err();
+        assert_eq!(error.to_string(), "SMTP_HOST is not set");
+    }
+}
@@ -1,23 +1,154 @@
-use backend::{delete_todo, list_todos, save_todo};
-use components::{login::Login, nav::NavBar, register::Register};
+#[cfg(not(feature = "offline"))]
+use backend::{bulk_update_todos, delete_todo, list_todos, save_todo, update_todo};
+use backend::{logout, BulkAction, NewTodo, Priority, TodoItem};
+use chrono::Local;
+use components::{
+    account::Account,
+    activity::Activity,
+    board::TodoBoard,
+    email_verification::VerifyEmail,
+    login::Login,
+    markdown::Markdown,
+    nav::NavBar,
+    password_reset::{ForgotPassword, ResetPassword},
+    register::Register,
+    schedule::{TodoAgenda, TodoCalendar},
+    sso::SsoComplete,
+    stats::Stats,
+    sync_status::SyncStatus,
+    todo_detail::TodoDetail,
+};
 use dioxus::prelude::*;
+use errors::AppError;
+#[cfg(feature = "offline")]
+use offline::{bulk_update_todos, delete_todo, list_todos, save_todo, update_todo};
+use std::collections::HashSet;
+#[cfg(feature = "server")]
+mod api;
+#[cfg(feature = "server")]
+mod attachments;
 mod backend;
 mod components;
+mod errors;
+#[cfg(feature = "server")]
+mod health;
+#[cfg(feature = "server")]
+mod ics;
+#[cfg(feature = "server")]
+mod mail;
+mod markdown;
+#[cfg(feature = "server")]
+mod metrics;
+#[cfg(feature = "offline")]
+mod offline;
+#[cfg(feature = "server")]
+mod oidc;
+mod quick_add;
+#[cfg(feature = "server")]
+mod rate_limit;
+#[cfg(feature = "server")]
+mod secrets;
+#[cfg(feature = "server")]
+mod session;
+#[cfg(feature = "server")]
+mod storage;
+#[cfg(feature = "server")]
+mod telemetry;
+mod validation;
+#[cfg(feature = "server")]
+mod webhooks;
 // use components::nav::NavBar;
 static CSS: Asset = asset!("/assets/main.css");
 static BACKGROUND_IMAGE: Asset = asset!("/assets/873441.png");
 
 fn main() {
+    #[cfg(feature = "server")]
+    {
+        // Must run before serving, otherwise Dioxus installs its default subscriber
+        telemetry::init();
+        tokio::runtime::Runtime::new()
+            .expect("failed to start tokio runtime")
+            .block_on(serve());
+    }
+
+    #[cfg(not(feature = "server"))]
     dioxus::launch(App);
 }
+
+// Server entry point: the Dioxus app plus our own operational routes
+#[cfg(feature = "server")]
+async fn serve() {
+    mail::check_config().expect("mail transport is misconfigured");
+    let address = dioxus::cli_config::fullstack_address_or_localhost();
+    let router = axum::Router::new()
+        .route("/healthz", axum::routing::get(health::healthz))
+        .route("/readyz", axum::routing::get(health::readyz))
+        .route("/metrics", axum::routing::get(metrics::handler))
+        .route("/auth/oidc/start", axum::routing::get(oidc::start))
+        .route("/auth/oidc/callback", axum::routing::get(oidc::callback))
+        .merge(api::router())
+        .merge(attachments::router())
+        .merge(ics::router())
+        .serve_dioxus_application(ServeConfig::new().expect("failed to load index.html"), App)
+        .layer(axum::middleware::from_fn(attachments::limit_upload_body));
+    tokio::spawn(attachments::sweep_orphans());
+    tokio::spawn(webhooks::deliver_pending());
+
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .expect("failed to bind server address");
+    tracing::info!(%address, "Server listening");
+    // Connect info gives the rate limiter the peer address
+    let server = axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(health::shutdown_signal());
+
+    // Stop accepting connections after the pre-stop delay that follows a
+    // shutdown signal, then wait for in-flight calls until the drain deadline
+    // passes
+    tokio::select! {
+        result = server => result.expect("server error"),
+        _ = health::drain_deadline() => {}
+    }
+    tracing::info!("Server stopped");
+}
 #[derive(Routable, Clone, PartialEq)]
 enum Route {
     #[layout(NavBar)]
     #[route("/")]
-    Login,
+    Home,
 
     #[route("/register")]
     Register,
+
+    #[route("/account")]
+    Account,
+
+    #[route("/activity?:todo")]
+    Activity { todo: String },
+
+    #[route("/todo/:id")]
+    TodoDetail { id: usize },
+
+    #[route("/board")]
+    TodoBoard,
+
+    #[route("/stats")]
+    Stats,
+
+    #[route("/forgot-password")]
+    ForgotPassword,
+
+    #[route("/reset-password?:token")]
+    ResetPassword { token: String },
+
+    #[route("/sso-complete?:ticket&:error")]
+    SsoComplete { ticket: String, error: String },
+
+    #[route("/verify-email?:token")]
+    VerifyEmail { token: String },
 }
 
 // Main Application Component
@@ -49,6 +180,22 @@ fn App() -> Element {
     use_context_provider(move || logged_in_user);
     use_context_provider(move || todos);
 
+    // Desktop/mobile: while changes are queued, keep retrying the sync that
+    // listing todos performs
+    #[cfg(feature = "offline")]
+    use_future(move || async move {
+        let mut todos = todos;
+        loop {
+            tokio::time::sleep(offline::SYNC_INTERVAL).await;
+            let current_user = logged_in_user.read().clone();
+            if let Some(username) = current_user {
+                if offline::pending_changes(&username).await > 0 {
+                    todos.restart();
+                }
+            }
+        }
+    });
+
     rsx! {
         document::Stylesheet { href: CSS }
         img {
@@ -67,27 +214,43 @@ fn App() -> Element {
 
             h1 { "Todo list" }
 
-            // Conditionally rergba(10, 10, 10, 0.8)s or Todo list based on login state
-            match logged_in_user.read().as_ref() {
-                Some(username) => rsx! {
-                    // --- Logged-in View ---
-                    p { "Logged in as: {username}" }
-                    button {
-                        onclick: move |_| {
-                            logged_in_user.set(None);
-                        },
-                        "Logout"
-                    }
-                    hr {} // Separator
-
-                    Todo_save {}
-                    Todo_show {}
-                },
-                None => rsx! {
-                    // --- Authentication View ---
-                    Router::<Route> {}
-                },
+            if let Some(username) = logged_in_user.read().as_ref() {
+                p { "Logged in as: {username}" }
+                button {
+                    onclick: move |_| {
+                        if let Some(username) = logged_in_user.read().clone() {
+                            spawn(async move {
+                                if let Err(e) = logout(username).await {
+                                    eprintln!("Error logging out: {:?}", e);
+                                }
+                            });
+                        }
+                        logged_in_user.set(None);
+                    },
+                    "Logout"
+                }
+                hr {} // Separator
             }
+
+            Router::<Route> {}
+        }
+    }
+}
+
+// Home Route: the todo list when logged in, otherwise the login form
+#[component]
+fn Home() -> Element {
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+
+    if logged_in_user.read().is_some() {
+        rsx! {
+            Todo_save {}
+            SyncStatus {}
+            Todo_show {}
+        }
+    } else {
+        rsx! {
+            Login {}
         }
     }
 }
@@ -95,12 +258,23 @@ fn App() -> Element {
 // Todo Save Component (Modified)
 #[component]
 pub fn Todo_save() -> Element {
-    let mut input_content = use_signal(|| String::new());
-    let mut save_status = use_signal(|| String::new());
+    let mut input_content = use_signal(String::new);
+    let mut save_status = use_signal(String::new);
+    // Show the content rendered as Markdown instead of the input
+    let mut previewing = use_signal(|| false);
 
     // Get the logged_in_user signal and todos resource from context
     let logged_in_user = use_context::<Signal<Option<String>>>();
-    let todos = use_context::<Resource<Result<Vec<(usize, String)>, ServerFnError>>>();
+    let todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
+
+    // What the quick-add syntax in the input will turn into
+    let preview = Some(input_content.read().clone())
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| quick_add::parse(&text, Local::now().naive_local()));
+    let preview_content = preview
+        .as_ref()
+        .map(|preview| preview.content.clone())
+        .unwrap_or_default();
 
     rsx! {
         div {
@@ -109,14 +283,27 @@ pub fn Todo_save() -> Element {
             padding_top: "20px",
             border_top: "1px solid #eee",
 
-            input {
-                r#type: "text",
-                placeholder: "Enter a new todo...",
-                value: "{input_content}",
-                oninput: move |evt| {
-                    save_status.set(String::new());
-                    input_content.set(evt.value());
-                },
+            if previewing() {
+                Markdown { class: "markdown-preview", content: preview_content }
+            } else {
+                input {
+                    r#type: "text",
+                    placeholder: "Enter a new todo, e.g. Call Sam fri 3pm !high #work @Home",
+                    value: "{input_content}",
+                    oninput: move |evt| {
+                        save_status.set(String::new());
+                        input_content.set(evt.value());
+                    },
+                }
+            }
+            button {
+                class: "secondary",
+                onclick: move |_| previewing.set(!previewing()),
+                if previewing() {
+                    "Write"
+                } else {
+                    "Preview"
+                }
             }
             button {
                 onclick: move |_| {
@@ -132,14 +319,16 @@ pub fn Todo_save() -> Element {
                     }
                     let username = current_user.unwrap();
                     save_status.set("Saving...".to_string());
-                    let mut todos_handle = todos.clone();
+                    let mut todos_handle = todos;
                     let username_for_save = username.clone();
-                    let content_for_save = current_content.clone();
+                    // Parsed again so the due date is relative to the moment of saving
+                    let new_todo = quick_add::parse(&current_content, Local::now().naive_local());
                     spawn(async move {
-                        match save_todo(username_for_save, content_for_save).await {
-                            Ok(_) => {
-                                save_status.set(format!("Saved: {}", current_content));
+                        match save_todo(username_for_save, new_todo).await {
+                            Ok(saved) => {
+                                save_status.set(format!("Saved: {}", saved.content));
                                 input_content.set(String::new());
+                                previewing.set(false);
                                 todos_handle.restart();
                             }
                             Err(e) => {
@@ -151,25 +340,84 @@ pub fn Todo_save() -> Element {
                 },
                 "Add Todo"
             }
+            if let Some(preview) = preview {
+                Quick_add_preview { preview }
+            }
             p { "{save_status}" }
         }
     }
 }
 
+// The fields `Todo_save` will send, shown while typing
+#[component]
+fn Quick_add_preview(preview: NewTodo) -> Element {
+    let NewTodo {
+        content,
+        due_date,
+        due_time,
+        priority,
+        list,
+        tags,
+    } = preview;
+    let today = Local::now().date_naive();
+    let due = due_date.map(|date| quick_add::due_label(date, due_time, today));
+    let priority = priority.map(Priority::label);
+
+    rsx! {
+        div { class: "quick-add-preview",
+            span { "{content}" }
+            if let Some(due) = due {
+                span { class: "todo-due", "due {due}" }
+            }
+            if let Some(priority) = priority {
+                span { class: "todo-priority priority-{priority}", "{priority}" }
+            }
+            if let Some(list) = list {
+                span { class: "todo-list-name", "{list}" }
+            }
+            for tag in tags {
+                span { key: "{tag}", class: "todo-tag", "#{tag}" }
+            }
+        }
+    }
+}
+
 // Todo Show Component (Modified)
+// How `Todo_show` lays out the todos
+#[derive(Clone, Copy, PartialEq)]
+enum TodoView {
+    List,
+    Agenda,
+    Week,
+    Month,
+}
+
+impl TodoView {
+    const ALL: [TodoView; 4] = [
+        TodoView::List,
+        TodoView::Agenda,
+        TodoView::Week,
+        TodoView::Month,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            TodoView::List => "List",
+            TodoView::Agenda => "Agenda",
+            TodoView::Week => "Week",
+            TodoView::Month => "Month",
+        }
+    }
+}
+
 #[component]
 pub fn Todo_show() -> Element {
-    // Get the logged_in_user signal and todos resource from context
-    let logged_in_user = use_context::<Signal<Option<String>>>();
-    let todos = use_context::<Resource<Result<Vec<(usize, String)>, ServerFnError>>>();
+    // Get the todos resource from context
+    let todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
 
-    let mut delete_status = use_signal(|| String::new());
-
-    // Read and clone the user state ONCE for this component's rendering cycle
-    // Since this component is only rendered when logged_in_user is Some,
-    // we can safely unwrap.
-    let current_user_clone = logged_in_user.read().clone();
-    let username = current_user_clone.expect("Todo_show rendered without logged_in_user");
+    // Ids ticked for a bulk action
+    let selected = use_signal(HashSet::<usize>::new);
+    let mut view = use_signal(|| TodoView::List);
 
     rsx! {
         div {
@@ -178,68 +426,501 @@ pub fn Todo_show() -> Element {
             border_top: "1px solid #eee",
 
             h2 { "My Todos" }
-            p { color: "red", "{delete_status}" } // Display delete status
 
-            // Match on the todos resource state
-            match todos.read().as_ref() {
-                Some(Ok(todo_list)) => {
-                    if todo_list.is_empty() {
-                        rsx! {
-                            p { "No todos yet!" }
+            // The other views query the server by due date, which offline
+            // builds can't do with their local ids
+            if cfg!(not(feature = "offline")) {
+                div { class: "view-tabs",
+                    for (option, label) in TodoView::ALL.map(|option| (option, option.label())) {
+                        button {
+                            key: "{label}",
+                            class: if view() == option { "view-tab active" } else { "view-tab" },
+                            onclick: move |_| view.set(option),
+                            "{label}"
                         }
-                    } else {
-                        rsx! {
-                            ul {
-                                {
-                                    todo_list
-                                        .iter()
-                                        .map(|(id, todo_content)| {
-                                            let todo_id = *id;
-                                            let todo_content_clone = todo_content.clone();
-                                            let username_for_delete = username.clone();
-                                            let mut todos_handle = todos.clone();
-                                            rsx! {
-                                                li { key: "{todo_id}",
-                                                    "{todo_content_clone}"
-                                                    button {
-                                                        onclick: move |_| {
-                                                            delete_status.set(format!("Deleting todo {}...", todo_id));
-                                                            let username_for_delete_clone = username_for_delete.clone();
-                                                            spawn(async move {
-                                                                match delete_todo(username_for_delete_clone, todo_id).await {
-                                                                    Ok(_) => {
-                                                                        delete_status.set(format!("Deleted todo with ID: {}", todo_id));
-                                                                        todos_handle.restart();
-                                                                    }
-                                                                    Err(e) => {
-                                                                        eprintln!("Error deleting todo {}: {:?}", todo_id, e);
-                                                                        delete_status.set(format!("Error deleting todo {}: {}", todo_id, e));
-                                                                    }
-                                                                }
-                                                            });
-                                                        },
-                                                        style: "margin-left: 10px; color: red; border: none; background: none; cursor: pointer;",
-                                                        "X" // The button text
-                                                    }
-                                                }
-                                            }
-                                        })
-                                }
+                    }
+                }
+            }
+
+            match view() {
+                TodoView::Agenda => rsx! {
+                    TodoAgenda {}
+                },
+                TodoView::Week => rsx! {
+                    TodoCalendar { month: false }
+                },
+                TodoView::Month => rsx! {
+                    TodoCalendar { month: true }
+                },
+                TodoView::List => rsx! {
+                    Todo_list { todos, selected }
+                },
+            }
+        }
+    }
+}
+
+// The flat list of the latest todos, with bulk actions
+#[component]
+fn Todo_list(
+    todos: Resource<Result<Vec<TodoItem>, ServerFnError>>,
+    selected: Signal<HashSet<usize>>,
+) -> Element {
+    rsx! {
+        // Match on the todos resource state
+        match todos.read().as_ref() {
+            Some(Ok(todo_list)) => {
+                if todo_list.is_empty() {
+                    rsx! {
+                        p { "No todos yet!" }
+                    }
+                } else {
+                    let all_ids: Vec<usize> = todo_list.iter().map(|todo| todo.id).collect();
+                    rsx! {
+                        Bulk_actions { selected, all_ids }
+                        ul {
+                            for todo in todo_list.iter().cloned() {
+                                Todo_row { key: "{todo.id}", todo, selected }
                             }
                         }
                     }
                 }
-                Some(Err(e)) => {
-                    rsx! {
-                        p { color: "red", "Error loading todos: {e}" }
+            }
+            Some(Err(e)) => {
+                rsx! {
+                    p { color: "red", "Error loading todos: {e}" }
+                }
+            }
+            None => {
+                rsx! {
+                    p { "Loading todos..." }
+                }
+            }
+        }
+    }
+}
+
+// Bulk Actions Component: applies one action to every ticked todo with a
+// single server call and a single refresh
+#[component]
+fn Bulk_actions(selected: Signal<HashSet<usize>>, all_ids: Vec<usize>) -> Element {
+    let mut selected = selected;
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
+
+    let mut list_name = use_signal(String::new);
+    let mut tag = use_signal(String::new);
+    let mut bulk_status = use_signal(String::new);
+    let mut busy = use_signal(|| false);
+
+    let run = use_callback(move |action: BulkAction| {
+        let Some(username) = logged_in_user.read().clone() else {
+            return;
+        };
+        let ids: Vec<usize> = selected.read().iter().copied().collect();
+        busy.set(true);
+        bulk_status.set("Working...".to_string());
+        spawn(async move {
+            match bulk_update_todos(username, ids, action).await {
+                Ok(results) => {
+                    let failed: Vec<String> = results
+                        .iter()
+                        .filter_map(|result| match &result.outcome {
+                            Ok(()) => None,
+                            Err(e) => Some(format!("#{} ({})", result.id, e)),
+                        })
+                        .collect();
+                    // Failed ones stay ticked so they can be looked at or retried
+                    selected.set(
+                        results
+                            .iter()
+                            .filter(|result| result.outcome.is_err())
+                            .map(|result| result.id)
+                            .collect(),
+                    );
+                    let done = results.len() - failed.len();
+                    if failed.is_empty() {
+                        bulk_status.set(format!("Updated {} todos.", done));
+                    } else {
+                        bulk_status.set(format!(
+                            "Updated {} of {} todos. Failed: {}",
+                            done,
+                            results.len(),
+                            failed.join(", ")
+                        ));
                     }
+                    todos.restart();
                 }
-                None => {
-                    rsx! {
-                        p { "Loading todos..." }
+                Err(e) => {
+                    eprintln!("Bulk action error: {:?}", e);
+                    bulk_status.set(format!("Error: {}", e));
+                }
+            }
+            busy.set(false);
+        });
+    });
+
+    let count = selected.read().len();
+    let all_selected = count == all_ids.len();
+    let disabled = busy();
+
+    rsx! {
+        div { class: "bulk-actions",
+            label {
+                input {
+                    r#type: "checkbox",
+                    checked: all_selected,
+                    onchange: move |evt| {
+                        if evt.checked() {
+                            selected.set(all_ids.iter().copied().collect());
+                        } else {
+                            selected.write().clear();
+                        }
+                    },
+                }
+                " Select all"
+            }
+            if count > 0 {
+                span { " {count} selected" }
+                div { class: "bulk-buttons",
+                    button {
+                        disabled,
+                        onclick: move |_| run.call(BulkAction::SetCompleted(true)),
+                        "Complete"
+                    }
+                    button {
+                        disabled,
+                        onclick: move |_| run.call(BulkAction::SetCompleted(false)),
+                        "Reopen"
+                    }
+                    button {
+                        class: "danger",
+                        disabled,
+                        onclick: move |_| run.call(BulkAction::Delete),
+                        "Delete"
+                    }
+                }
+                div { class: "bulk-buttons",
+                    input {
+                        r#type: "text",
+                        placeholder: "List (empty for none)",
+                        value: "{list_name}",
+                        oninput: move |evt| list_name.set(evt.value()),
+                    }
+                    button {
+                        disabled,
+                        onclick: move |_| {
+                            let name = list_name.read().trim().to_string();
+                            run.call(BulkAction::MoveToList(Some(name).filter(|n| !n.is_empty())));
+                        },
+                        "Move to list"
+                    }
+                }
+                div { class: "bulk-buttons",
+                    input {
+                        r#type: "text",
+                        placeholder: "Tag",
+                        value: "{tag}",
+                        oninput: move |evt| tag.set(evt.value()),
+                    }
+                    button {
+                        disabled,
+                        onclick: move |_| {
+                            let tag = validation::normalize_tag(&tag.read());
+                            match validation::validate_tag(&tag) {
+                                Ok(()) => run.call(BulkAction::AddTag(tag)),
+                                Err(e) => bulk_status.set(e),
+                            }
+                        },
+                        "Add tag"
+                    }
+                    button {
+                        disabled,
+                        onclick: move |_| {
+                            let tag = validation::normalize_tag(&tag.read());
+                            match validation::validate_tag(&tag) {
+                                Ok(()) => run.call(BulkAction::RemoveTag(tag)),
+                                Err(e) => bulk_status.set(e),
+                            }
+                        },
+                        "Remove tag"
                     }
                 }
             }
+            if !bulk_status.read().is_empty() {
+                p { "{bulk_status}" }
+            }
+        }
+    }
+}
+
+// A change that was refused because the todo changed somewhere else
+#[derive(Clone, PartialEq)]
+enum Refused {
+    Edit { draft: String, current_version: i32 },
+    Delete { current_version: i32 },
+    // The todo is gone; the draft can still be saved as a new todo
+    EditOfDeleted { draft: String },
+}
+
+// Todo Row Component: one todo with inline editing. Edits and deletes send
+// the version this row was rendered with; if another tab or device changed
+// the todo first, the row shows both sides and lets the user pick one.
+#[component]
+fn Todo_row(todo: TodoItem, selected: Signal<HashSet<usize>>) -> Element {
+    let mut selected = selected;
+    let logged_in_user = use_context::<Signal<Option<String>>>();
+    let mut todos = use_context::<Resource<Result<Vec<TodoItem>, ServerFnError>>>();
+
+    let mut draft = use_signal(|| None::<String>);
+    let mut previewing = use_signal(|| false);
+    let mut refused = use_signal(|| None::<Refused>);
+    let mut row_status = use_signal(String::new);
+
+    let todo_id = todo.id;
+    let username = logged_in_user
+        .read()
+        .clone()
+        .expect("Todo_row rendered without logged_in_user");
+
+    // Send an edit (Some(content)) or a delete (None) against `version`
+    let write = use_callback(move |(content, version): (Option<String>, i32)| {
+        let username = username.clone();
+        row_status.set(String::new());
+        spawn(async move {
+            let result = match content.clone() {
+                Some(content) => update_todo(username, todo_id, version, content)
+                    .await
+                    .map(|_| ()),
+                None => delete_todo(username, todo_id, version).await,
+            };
+            match result {
+                Ok(()) => {
+                    draft.set(None);
+                    previewing.set(false);
+                    refused.set(None);
+                    todos.restart();
+                }
+                Err(e) => match AppError::from_server_error(&e) {
+                    Some(AppError::Conflict { current_version }) => {
+                        let prompt = match (content, current_version) {
+                            (Some(mine), Some(current_version)) => Some(Refused::Edit {
+                                draft: mine,
+                                current_version,
+                            }),
+                            (Some(mine), None) => Some(Refused::EditOfDeleted { draft: mine }),
+                            (None, Some(current_version)) => {
+                                Some(Refused::Delete { current_version })
+                            }
+                            // Deleting something that is already gone is fine
+                            (None, None) => None,
+                        };
+                        // Reload so the row shows the todo as it is now, unless
+                        // it is gone and the draft would disappear with it
+                        if !matches!(prompt, Some(Refused::EditOfDeleted { .. })) {
+                            todos.restart();
+                        }
+                        refused.set(prompt);
+                    }
+                    _ => {
+                        eprintln!("Error changing todo {}: {:?}", todo_id, e);
+                        row_status.set(format!("Error: {}", e));
+                    }
+                },
+            }
+        });
+    });
+
+    let current_content = todo.content.clone();
+    let current_draft = draft.read().clone();
+    let is_selected = selected.read().contains(&todo_id);
+    let row_class = if todo.completed { "done" } else { "" };
+    let list = todo.list.clone();
+    let tags = todo.tags.clone();
+    let today = Local::now().date_naive();
+    let due = todo
+        .due_date
+        .map(|date| quick_add::due_label(date, todo.due_time, today));
+    let due_class = match todo.due_date {
+        Some(date) if date < today && !todo.completed => "todo-due overdue",
+        _ => "todo-due",
+    };
+    let priority = todo.priority.map(Priority::label);
+    let has_notes = !todo.notes.trim().is_empty();
+    // Images show as thumbnails, the rest only in the count
+    let thumbnails: Vec<(i32, String, String)> = todo
+        .attachments
+        .iter()
+        .filter_map(|a| Some((a.id, a.url(), a.thumbnail_url()?)))
+        .take(3)
+        .collect();
+    let attachment_count = match todo.attachments.len() {
+        0 => None,
+        1 => Some("1 file".to_string()),
+        count => Some(format!("{} files", count)),
+    };
+
+    rsx! {
+        li { class: row_class,
+            input {
+                r#type: "checkbox",
+                class: "select-todo",
+                checked: is_selected,
+                onchange: move |evt| {
+                    if evt.checked() {
+                        selected.write().insert(todo_id);
+                    } else {
+                        selected.write().remove(&todo_id);
+                    }
+                },
+            }
+            match current_draft {
+                Some(text) => rsx! {
+                    if previewing() {
+                        Markdown { class: "markdown-preview", content: text }
+                    } else {
+                        input {
+                            r#type: "text",
+                            value: "{text}",
+                            oninput: move |evt| draft.set(Some(evt.value())),
+                        }
+                    }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| previewing.set(!previewing()),
+                        if previewing() {
+                            "Write"
+                        } else {
+                            "Preview"
+                        }
+                    }
+                    button {
+                        class: "secondary",
+                        disabled: refused.read().is_some(),
+                        onclick: move |_| {
+                            let content = draft.read().clone().unwrap_or_default();
+                            if content.trim().is_empty() {
+                                row_status.set("Cannot save an empty todo.".to_string());
+                                return;
+                            }
+                            write.call((Some(content), todo.version));
+                        },
+                        "Save"
+                    }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| {
+                            draft.set(None);
+                            previewing.set(false);
+                            refused.set(None);
+                            row_status.set(String::new());
+                        },
+                        "Cancel"
+                    }
+                },
+                None => rsx! {
+                    div { class: "todo-text",
+                        Markdown { content: current_content.clone() }
+                        if let Some(due) = due {
+                            span { class: due_class, "due {due}" }
+                        }
+                        if let Some(priority) = priority {
+                            span { class: "todo-priority priority-{priority}", "{priority}" }
+                        }
+                        if let Some(list) = list {
+                            span { class: "todo-list-name", "{list}" }
+                        }
+                        for tag in tags {
+                            span { key: "{tag}", class: "todo-tag", "#{tag}" }
+                        }
+                        for (id, url, thumbnail) in thumbnails {
+                            a { key: "{id}", href: url, target: "_blank",
+                                img { class: "todo-thumb", src: thumbnail, alt: "" }
+                            }
+                        }
+                        if let Some(count) = attachment_count {
+                            span { class: "todo-attachments", "{count}" }
+                        }
+                    }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| draft.set(Some(todo.content.clone())),
+                        "Edit"
+                    }
+                    // Offline builds list todos by local id, which the server doesn't know
+                    if cfg!(not(feature = "offline")) {
+                        Link {
+                            class: "details-link",
+                            to: Route::TodoDetail { id: todo_id },
+                            if has_notes {
+                                "Notes & history"
+                            } else {
+                                "Details"
+                            }
+                        }
+                    }
+                    button {
+                        disabled: refused.read().is_some(),
+                        onclick: move |_| write.call((None, todo.version)),
+                        "X"
+                    }
+                },
+            }
+
+            if !row_status.read().is_empty() {
+                p { class: "row-status", "{row_status}" }
+            }
+
+            match refused.read().clone() {
+                Some(Refused::Edit { draft: mine, current_version }) => rsx! {
+                    div { class: "conflict-prompt",
+                        p { "This todo was changed somewhere else while you were editing it." }
+                        p { "Theirs: {current_content}" }
+                        p { "Yours: {mine}" }
+                        button {
+                            onclick: move |_| write.call((Some(mine.clone()), current_version)),
+                            "Keep mine"
+                        }
+                        button {
+                            onclick: move |_| {
+                                draft.set(None);
+                                refused.set(None);
+                            },
+                            "Use theirs"
+                        }
+                    }
+                },
+                Some(Refused::Delete { current_version }) => rsx! {
+                    div { class: "conflict-prompt",
+                        p { "This todo was changed somewhere else since you loaded it. It now reads: {current_content}" }
+                        button {
+                            onclick: move |_| write.call((None, current_version)),
+                            "Delete anyway"
+                        }
+                        button { onclick: move |_| refused.set(None), "Keep it" }
+                    }
+                },
+                Some(Refused::EditOfDeleted { draft: mine }) => rsx! {
+                    div { class: "conflict-prompt",
+                        p { "This todo was deleted somewhere else while you were editing it." }
+                        button {
+                            onclick: move |_| {
+                                let username = logged_in_user.read().clone().unwrap_or_default();
+                                let new_todo = NewTodo::plain(mine.clone());
+                                spawn(async move {
+                                    match save_todo(username, new_todo).await {
+                                        Ok(_) => todos.restart(),
+                                        Err(e) => row_status.set(format!("Error saving: {}", e)),
+                                    }
+                                });
+                            },
+                            "Save mine as a new todo"
+                        }
+                        button { onclick: move |_| todos.restart(), "Discard it" }
+                    }
+                },
+                None => rsx! {},
+            }
         }
     }
 }
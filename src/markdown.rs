@@ -0,0 +1,181 @@
+// Markdown for todo content, compiled into both the client and the server.
+//
+// Content is stored as typed and rendered on display: CommonMark plus
+// strikethrough and task lists (`- [ ] milk`), with bare URLs turned into
+// links. Raw HTML in the content is shown as text, and the output goes
+// through `ammonia` so nothing stored can run a script or restyle the page.
+
+use ammonia::Builder;
+use pulldown_cmark::{html, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd};
+use std::collections::HashSet;
+
+// Everything the renderer can produce; anything else is dropped
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "blockquote",
+    "br",
+    "code",
+    "del",
+    "em",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "input",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "strong",
+    "ul",
+];
+
+pub fn render(content: &str) -> String {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let events = autolink(Parser::new_ext(content, options))
+        .into_iter()
+        .map(|event| match event {
+            Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+            event => event,
+        });
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, events);
+    sanitizer().clean(&unsafe_html).to_string()
+}
+
+// Task list boxes are shown, not edited: ticking happens through the todo
+// itself, so they are always disabled
+fn sanitizer() -> Builder<'static> {
+    let mut builder = Builder::default();
+    builder
+        .tags(ALLOWED_TAGS.iter().copied().collect())
+        .tag_attributes(
+            [("a", ["href"].into()), ("input", ["checked"].into())]
+                .into_iter()
+                .collect(),
+        )
+        .set_tag_attribute_value("input", "type", "checkbox")
+        .set_tag_attribute_value("input", "disabled", "")
+        .url_schemes(
+            ["http", "https", "mailto"]
+                .into_iter()
+                .collect::<HashSet<_>>(),
+        )
+        .link_rel(Some("noopener noreferrer nofollow"));
+    builder
+}
+
+// Split text outside links and code blocks into text and links for the URLs
+// in it. Inline code is a separate event and never linked.
+fn autolink<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let finder = linkify::LinkFinder::new();
+    let mut out = Vec::new();
+    let mut verbatim = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Link { .. } | Tag::CodeBlock(_)) => verbatim = true,
+            Event::End(TagEnd::Link | TagEnd::CodeBlock) => verbatim = false,
+            Event::Text(ref text) if !verbatim => {
+                let text = text.to_string();
+                for span in finder.spans(&text) {
+                    let piece = CowStr::from(span.as_str().to_string());
+                    match span.kind() {
+                        Some(kind) => {
+                            let url = match kind {
+                                linkify::LinkKind::Email => format!("mailto:{}", span.as_str()),
+                                _ => span.as_str().to_string(),
+                            };
+                            out.push(Event::Start(Tag::Link {
+                                link_type: LinkType::Autolink,
+                                dest_url: url.into(),
+                                title: CowStr::from(""),
+                                id: CowStr::from(""),
+                            }));
+                            out.push(Event::Text(piece));
+                            out.push(Event::End(TagEnd::Link));
+                        }
+                        None => out.push(Event::Text(piece)),
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+        out.push(event);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_markdown() {
+        assert_eq!(
+            render("**milk** and ~~eggs~~"),
+            "<p><strong>milk</strong> and <del>eggs</del></p>\n"
+        );
+    }
+
+    #[test]
+    fn script_tags_are_shown_as_text() {
+        let html = render("hi <script>alert(1)</script>");
+        assert!(!html.contains("<script"), "{}", html);
+        assert!(html.contains("&lt;script&gt;"), "{}", html);
+    }
+
+    #[test]
+    fn html_blocks_are_shown_as_text() {
+        let html = render("<img src=x onerror=alert(1)>");
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(!html.contains("onerror=\""), "{}", html);
+    }
+
+    #[test]
+    fn javascript_links_are_dropped() {
+        let html = render("[click](javascript:alert(1))");
+        assert!(!html.contains("javascript:"), "{}", html);
+        assert!(html.contains("click"), "{}", html);
+
+        let html = render("<javascript:alert(1)>");
+        assert!(!html.contains("href"), "{}", html);
+    }
+
+    #[test]
+    fn links_are_nofollow() {
+        assert_eq!(
+            render("[docs](https://example.com)"),
+            "<p><a href=\"https://example.com\" rel=\"noopener noreferrer nofollow\">docs</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn bare_urls_and_emails_become_links() {
+        let html = render("see https://example.com or mail bob@example.com");
+        assert!(html.contains("<a href=\"https://example.com\""), "{}", html);
+        assert!(
+            html.contains("<a href=\"mailto:bob@example.com\""),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn urls_in_code_are_not_linked() {
+        let html = render("`https://example.com`\n\n```\nhttps://example.org\n```");
+        assert!(!html.contains("<a "), "{}", html);
+    }
+
+    #[test]
+    fn task_boxes_are_disabled() {
+        let html = render("- [x] milk\n- [ ] eggs");
+        assert_eq!(html.matches("disabled").count(), 2, "{}", html);
+        assert_eq!(html.matches("checked").count(), 1, "{}", html);
+    }
+}
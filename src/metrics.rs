@@ -0,0 +1,267 @@
+// Prometheus metrics, served as text from `/metrics`.
+//
+// Server function calls are counted and timed from `telemetry::observe`, so a
+// new server function only needs to go through `observe` to show up here.
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use dioxus::prelude::*;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::backend::server_utils::{try_get_db_pool, DATABASE_ERROR_MESSAGE, MAX_DB_CONNECTIONS};
+use crate::errors::AppError;
+
+static SERVER_FN_CALLS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "todo_server_fn_calls_total",
+        "Server function calls, by function",
+        &["function"]
+    )
+    .expect("register todo_server_fn_calls_total")
+});
+
+static SERVER_FN_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "todo_server_fn_errors_total",
+        "Server function calls that returned an error, by function and error kind",
+        &["function", "kind"]
+    )
+    .expect("register todo_server_fn_errors_total")
+});
+
+static SERVER_FN_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "todo_server_fn_duration_seconds",
+        "Server function latency, by function",
+        &["function"]
+    )
+    .expect("register todo_server_fn_duration_seconds")
+});
+
+static LOGIN_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "todo_login_failures_total",
+        "Rejected login attempts, by reason",
+        &["reason"]
+    )
+    .expect("register todo_login_failures_total")
+});
+
+static LOGINS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "todo_logins_total",
+        "Successful logins, by method",
+        &["method"]
+    )
+    .expect("register todo_logins_total")
+});
+
+static ACTIVE_SESSIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "todo_active_sessions",
+        "Unexpired server-side sessions (started by 2FA and SSO logins)"
+    )
+    .expect("register todo_active_sessions")
+});
+
+static DB_POOL_CONNECTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "todo_db_pool_connections",
+        "Open connections in the database pool"
+    )
+    .expect("register todo_db_pool_connections")
+});
+
+static DB_POOL_IDLE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "todo_db_pool_idle_connections",
+        "Idle connections in the database pool"
+    )
+    .expect("register todo_db_pool_idle_connections")
+});
+
+static DB_POOL_MAX: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "todo_db_pool_max_connections",
+        "Configured maximum size of the database pool"
+    )
+    .expect("register todo_db_pool_max_connections")
+});
+
+// Called by `telemetry::observe` once per finished server function call
+pub fn record_call(function: &'static str, elapsed: Duration, error: Option<&ServerFnError>) {
+    SERVER_FN_CALLS.with_label_values(&[function]).inc();
+    SERVER_FN_LATENCY
+        .with_label_values(&[function])
+        .observe(elapsed.as_secs_f64());
+    if let Some(e) = error {
+        SERVER_FN_ERRORS
+            .with_label_values(&[function, error_kind(e)])
+            .inc();
+    }
+}
+
+pub fn record_login_failure(reason: &'static str) {
+    LOGIN_FAILURES.with_label_values(&[reason]).inc();
+}
+
+// `method` is "password", "two_factor" or "sso"
+pub fn record_login(method: &'static str) {
+    LOGINS.with_label_values(&[method]).inc();
+}
+
+// GET /metrics
+pub async fn handler() -> Response {
+    refresh_pool_gauges();
+    refresh_session_gauge().await;
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    match encoder.encode(&prometheus::gather(), &mut buffer) {
+        Ok(()) => (
+            [(header::CONTENT_TYPE, encoder.format_type().to_string())],
+            buffer,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to encode metrics");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+// Pool stats are sampled at scrape time rather than tracked on every query
+fn refresh_pool_gauges() {
+    DB_POOL_MAX.set(MAX_DB_CONNECTIONS as i64);
+    match try_get_db_pool() {
+        Some(pool) => {
+            DB_POOL_CONNECTIONS.set(pool.size() as i64);
+            DB_POOL_IDLE.set(pool.num_idle() as i64);
+        }
+        // Pool is created lazily on the first server function call
+        None => {
+            DB_POOL_CONNECTIONS.set(0);
+            DB_POOL_IDLE.set(0);
+        }
+    }
+}
+
+// Counted from the table, so expired sessions and logouts from any client or
+// instance are reflected
+async fn refresh_session_gauge() {
+    // Scrapes shouldn't be the thing that opens the pool
+    let Some(pool) = try_get_db_pool() else {
+        ACTIVE_SESSIONS.set(0);
+        return;
+    };
+    let active: Result<i64, _> =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE expires_at > now()")
+            .fetch_one(pool)
+            .await;
+    match active {
+        Ok(active) => ACTIVE_SESSIONS.set(active),
+        Err(e) => tracing::warn!(error = %e, "Counting active sessions failed"),
+    }
+}
+
+fn error_kind(error: &ServerFnError) -> &'static str {
+    if let Some(app_error) = AppError::from_server_error(error) {
+        return app_error.kind();
+    }
+    match error {
+        ServerFnError::ServerError(message) if message == DATABASE_ERROR_MESSAGE => "database",
+        ServerFnError::ServerError(_) => "rejected",
+        ServerFnError::Request(_) | ServerFnError::Response(_) => "transport",
+        ServerFnError::Args(_)
+        | ServerFnError::MissingArg(_)
+        | ServerFnError::Deserialization(_)
+        | ServerFnError::Serialization(_) => "bad_request",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The registry is global, so each test uses its own label values
+    async fn scrape() -> String {
+        let response = handler().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let rate_limited = AppError::RateLimited {
+            retry_after_secs: 5,
+        }
+        .into_server_error();
+        let conflict = AppError::Conflict {
+            current_version: None,
+        }
+        .into_server_error();
+        let database: ServerFnError = ServerFnError::ServerError(DATABASE_ERROR_MESSAGE.into());
+        let rejected: ServerFnError = ServerFnError::ServerError("Todo not found".into());
+        let transport: ServerFnError = ServerFnError::Request("connection reset".into());
+        let bad_request: ServerFnError = ServerFnError::Args("missing field".into());
+
+        assert_eq!(error_kind(&rate_limited), "rate_limited");
+        assert_eq!(error_kind(&conflict), "conflict");
+        assert_eq!(error_kind(&database), "database");
+        assert_eq!(error_kind(&rejected), "rejected");
+        assert_eq!(error_kind(&transport), "transport");
+        assert_eq!(error_kind(&bad_request), "bad_request");
+    }
+
+    #[tokio::test]
+    async fn counts_calls_and_errors_by_function() {
+        let failed: ServerFnError = ServerFnError::ServerError("Todo not found".into());
+        record_call("metrics_test_fn", Duration::from_millis(5), None);
+        record_call("metrics_test_fn", Duration::from_millis(7), Some(&failed));
+
+        let text = scrape().await;
+        assert!(text.contains(r#"todo_server_fn_calls_total{function="metrics_test_fn"} 2"#));
+        assert!(text.contains(
+            r#"todo_server_fn_errors_total{function="metrics_test_fn",kind="rejected"} 1"#
+        ));
+        assert!(
+            text.contains(r#"todo_server_fn_duration_seconds_count{function="metrics_test_fn"} 2"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn counts_logins_and_failures() {
+        record_login("metrics_test_method");
+        record_login_failure("metrics_test_reason");
+
+        let text = scrape().await;
+        assert!(text.contains(r#"todo_logins_total{method="metrics_test_method"} 1"#));
+        assert!(text.contains(r#"todo_login_failures_total{reason="metrics_test_reason"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn samples_gauges_without_a_pool() {
+        let text = scrape().await;
+        assert!(text.contains(&format!(
+            "todo_db_pool_max_connections {}",
+            MAX_DB_CONNECTIONS
+        )));
+        assert!(text.contains("todo_db_pool_connections 0"));
+        assert!(text.contains("todo_active_sessions 0"));
+    }
+}
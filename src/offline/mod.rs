@@ -0,0 +1,260 @@
+// Offline-first todo storage for the desktop and mobile apps.
+//
+// `list_todos`, `save_todo` and `delete_todo` mirror the server functions of
+// the same name, so `Todo_save`/`Todo_show` work unchanged, but they read and
+// write the on-device store (see `store`). Changes are queued and replayed
+// against the server by `sync`, which runs on every list and periodically
+// from `App`, so the app keeps working without a connection.
+//
+// Conflicts are resolved as follows:
+// - queued changes are replayed in the order they were made
+// - creates carry an idempotency key, so one the server saw but whose reply
+//   was lost is not added twice when replayed
+// - a create the server rejects (e.g. invalid content) is dropped locally
+// - edits and deletes are sent with the version they were made against; if
+//   the todo changed or was deleted on the server since, the server's copy
+//   wins and the local change is dropped (see `SyncReport::rejected`)
+// - otherwise the server's copy wins: todos deleted or edited elsewhere are
+//   removed or updated locally on the next sync
+
+pub mod store;
+
+use dioxus::prelude::*;
+use server_fn::error::NoCustomError;
+
+use crate::backend::{self, BulkAction, BulkResult, NewTodo, TodoItem};
+use crate::errors::AppError;
+use crate::validation;
+use store::ChangeKind;
+
+// How often `App` retries syncing in the background
+pub const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+// Only one sync per process at a time, so a change is never sent twice
+static SYNC_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncReport {
+    // The server could not be reached; queued changes were kept
+    pub offline: bool,
+    // Changes the server refused and that were dropped
+    pub rejected: Vec<String>,
+}
+
+pub async fn list_todos(username: String) -> Result<Vec<TodoItem>, ServerFnError> {
+    let report = sync(&username).await?;
+    if !report.rejected.is_empty() {
+        tracing::warn!(rejected = ?report.rejected, "Server rejected offline changes");
+    }
+    Ok(store::load(&username).await.map_err(storage_err)?.visible())
+}
+
+// The returned todo has a local id and is created on the server by `sync`
+pub async fn save_todo(username: String, todo: NewTodo) -> Result<TodoItem, ServerFnError> {
+    let todo = todo
+        .checked()
+        .map_err(ServerFnError::<NoCustomError>::ServerError)?;
+    store::update(&username, |store| store.add(todo))
+        .await
+        .map_err(storage_err)
+}
+
+pub async fn update_todo(
+    username: String,
+    id: usize,
+    expected_version: i32,
+    content: String,
+) -> Result<TodoItem, ServerFnError> {
+    validation::validate_todo_content(&content)
+        .map_err(ServerFnError::<NoCustomError>::ServerError)?;
+    store::update(&username, |store| {
+        store.edit(id as u64, expected_version, content)
+    })
+    .await
+    .map_err(storage_err)?
+    .map_err(AppError::into_server_error)
+}
+
+pub async fn delete_todo(
+    username: String,
+    id: usize,
+    expected_version: i32,
+) -> Result<(), ServerFnError> {
+    store::update(&username, |store| store.remove(id as u64, expected_version))
+        .await
+        .map_err(storage_err)?
+        .map_err(AppError::into_server_error)
+}
+
+// Bulk actions are not queued: they go straight to the server after a sync,
+// so they need a connection and only apply to todos that reached the server
+pub async fn bulk_update_todos(
+    username: String,
+    ids: Vec<usize>,
+    action: BulkAction,
+) -> Result<Vec<BulkResult>, ServerFnError> {
+    let report = sync(&username).await?;
+    if report.offline {
+        return Err(ServerFnError::ServerError(
+            "Bulk changes need a connection to the server.".to_string(),
+        ));
+    }
+
+    let local = store::load(&username).await.map_err(storage_err)?;
+    let mut results = Vec::new();
+    let mut server_ids = Vec::new();
+    for id in ids {
+        let todo = local.todos.iter().find(|t| t.local_id as usize == id);
+        match todo.and_then(|t| t.server_id) {
+            Some(server_id) => server_ids.push((id, server_id)),
+            None => results.push(BulkResult {
+                id,
+                outcome: Err("Not synced yet".to_string()),
+            }),
+        }
+    }
+    if server_ids.is_empty() {
+        return Ok(results);
+    }
+
+    let server_results = backend::bulk_update_todos(
+        username.clone(),
+        server_ids.iter().map(|&(_, server_id)| server_id).collect(),
+        action,
+    )
+    .await?;
+    for (id, server_id) in server_ids {
+        let outcome = server_results
+            .iter()
+            .find(|result| result.id == server_id)
+            .map(|result| result.outcome.clone())
+            .unwrap_or_else(|| Err("Todo not found".to_string()));
+        results.push(BulkResult { id, outcome });
+    }
+
+    // Pull the changes into the local copy
+    sync(&username).await?;
+    Ok(results)
+}
+
+// Number of changes waiting for the server
+pub async fn pending_changes(username: &str) -> usize {
+    store::load(username)
+        .await
+        .map(|store| store.queue.len())
+        .unwrap_or(0)
+}
+
+// Replay queued changes, then refresh the local copy from the server. Only
+// errors from the local store are returned; being offline is not an error.
+pub async fn sync(username: &str) -> Result<SyncReport, ServerFnError> {
+    let _guard = SYNC_LOCK.lock().await;
+    let mut report = SyncReport::default();
+
+    let local = store::load(username).await.map_err(storage_err)?;
+    for change in local.queue.clone() {
+        let change_id = change.change_id;
+        let user = username.to_string();
+        match change.kind {
+            ChangeKind::Create { local_id, content } => {
+                let todo = local.new_todo(local_id, content);
+                // Saved with the store so every retry sends the same key
+                let key = store::update(username, |store| store.create_key(local_id))
+                    .await
+                    .map_err(storage_err)?;
+                match backend::save_todo_once(user, todo, key).await {
+                    Ok(saved) => {
+                        store::update(username, |store| store.synced(change_id, local_id, &saved))
+                            .await
+                            .map_err(storage_err)?;
+                    }
+                    Err(e) if is_offline(&e) => {
+                        report.offline = true;
+                        return Ok(report);
+                    }
+                    Err(e) => {
+                        report.rejected.push(rejection_message(&e));
+                        store::update(username, |store| store.rejected(change_id, local_id))
+                            .await
+                            .map_err(storage_err)?;
+                    }
+                }
+            }
+            ChangeKind::Update {
+                local_id,
+                server_id,
+                expected_version,
+                content,
+            } => match backend::update_todo(user, server_id, expected_version, content).await {
+                Ok(saved) => {
+                    store::update(username, |store| store.synced(change_id, local_id, &saved))
+                        .await
+                        .map_err(storage_err)?;
+                }
+                Err(e) if is_offline(&e) => {
+                    report.offline = true;
+                    return Ok(report);
+                }
+                // The server's copy comes back with the refresh below
+                Err(e) => {
+                    report.rejected.push(rejection_message(&e));
+                    store::update(username, |store| store.finish(change_id))
+                        .await
+                        .map_err(storage_err)?;
+                }
+            },
+            ChangeKind::Delete {
+                server_id,
+                expected_version,
+                ..
+            } => {
+                match backend::delete_todo(user, server_id, expected_version).await {
+                    Ok(_) => {}
+                    Err(e) if is_offline(&e) => {
+                        report.offline = true;
+                        return Ok(report);
+                    }
+                    // Already gone is as good as deleted
+                    Err(e)
+                        if AppError::from_server_error(&e)
+                            == Some(AppError::Conflict {
+                                current_version: None,
+                            }) => {}
+                    Err(e) => report.rejected.push(rejection_message(&e)),
+                }
+                store::update(username, |store| store.finish(change_id))
+                    .await
+                    .map_err(storage_err)?;
+            }
+        }
+    }
+
+    match backend::list_todos(username.to_string()).await {
+        Ok(server_todos) => {
+            store::update(username, |store| store.merge_server(server_todos))
+                .await
+                .map_err(storage_err)?;
+        }
+        Err(e) if is_offline(&e) => report.offline = true,
+        Err(e) => tracing::warn!(error = %e, "Could not refresh todos from the server"),
+    }
+    Ok(report)
+}
+
+// Conflicts carry a readable message behind their machine-readable tag
+fn rejection_message(error: &ServerFnError) -> String {
+    match AppError::from_server_error(error) {
+        Some(app_error) => app_error.to_string(),
+        None => error.to_string(),
+    }
+}
+
+// Transport failures mean "try again later"; anything else is the server's answer
+fn is_offline(error: &ServerFnError) -> bool {
+    matches!(error, ServerFnError::Request(_))
+}
+
+fn storage_err(e: String) -> ServerFnError {
+    tracing::error!(error = %e, "Offline store error");
+    ServerFnError::ServerError(format!("Local storage error: {}", e))
+}
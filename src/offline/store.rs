@@ -0,0 +1,719 @@
+// The on-device copy of a user's todos plus the queue of changes not yet
+// sent to the server, kept as one JSON file per user.
+//
+// Todos are addressed by a local id that never changes, so the UI can show
+// and delete a todo before the server has assigned it an id.
+
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::backend::{NewTodo, Priority, TodoItem};
+use crate::errors::AppError;
+
+// Serializes load-modify-save cycles between the UI and the sync task
+static FILE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LocalStore {
+    // In display order, newest first
+    pub todos: Vec<LocalTodo>,
+    // Oldest first; replayed against the server in this order
+    pub queue: Vec<PendingChange>,
+    next_id: u64,
+    // Random per store; with a local id it forms the idempotency key of a
+    // queued create, so a replay never adds the todo twice on the server
+    #[serde(default)]
+    device_id: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LocalTodo {
+    pub local_id: u64,
+    // None until the create has been synced
+    pub server_id: Option<usize>,
+    pub content: String,
+    // Only edited on the server (the todo's page), so as of the last sync
+    #[serde(default)]
+    pub notes: String,
+    // The server's version as of the last sync; 0 until created there
+    pub version: i32,
+    // Only changed on the server (bulk actions), so always as of the last sync
+    pub completed: bool,
+    pub list: Option<String>,
+    pub tags: Vec<String>,
+    // Set when the todo is created; not editable offline
+    pub due_date: Option<NaiveDate>,
+    pub due_time: Option<NaiveTime>,
+    pub priority: Option<Priority>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingChange {
+    pub change_id: u64,
+    pub kind: ChangeKind,
+}
+
+// Edits and deletes carry the server version they were made against, so the
+// server can refuse them if the todo changed there in the meantime
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Create {
+        local_id: u64,
+        content: String,
+    },
+    Update {
+        local_id: u64,
+        server_id: usize,
+        expected_version: i32,
+        content: String,
+    },
+    Delete {
+        local_id: u64,
+        server_id: usize,
+        expected_version: i32,
+    },
+}
+
+impl ChangeKind {
+    fn local_id(&self) -> u64 {
+        match self {
+            ChangeKind::Create { local_id, .. }
+            | ChangeKind::Update { local_id, .. }
+            | ChangeKind::Delete { local_id, .. } => *local_id,
+        }
+    }
+}
+
+impl LocalTodo {
+    fn item(&self) -> TodoItem {
+        TodoItem {
+            id: self.local_id as usize,
+            content: self.content.clone(),
+            notes: self.notes.clone(),
+            version: self.version,
+            completed: self.completed,
+            list: self.list.clone(),
+            tags: self.tags.clone(),
+            due_date: self.due_date,
+            due_time: self.due_time,
+            priority: self.priority,
+            attachments: Vec::new(),
+            status_id: None,
+        }
+    }
+}
+
+impl LocalStore {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn create_key(&mut self, local_id: u64) -> String {
+        if self.device_id.is_empty() {
+            self.device_id = uuid::Uuid::new_v4().to_string();
+        }
+        format!("{}:{}", self.device_id, local_id)
+    }
+
+    // What the todo list shows, addressed by local id
+    pub fn visible(&self) -> Vec<TodoItem> {
+        self.todos.iter().map(LocalTodo::item).collect()
+    }
+
+    pub fn add(&mut self, new_todo: NewTodo) -> TodoItem {
+        let local_id = self.next_id();
+        let change_id = self.next_id();
+        let content = new_todo.content;
+        let todo = LocalTodo {
+            local_id,
+            server_id: None,
+            content: content.clone(),
+            notes: String::new(),
+            version: 0,
+            completed: false,
+            list: new_todo.list,
+            tags: new_todo.tags,
+            due_date: new_todo.due_date,
+            due_time: new_todo.due_time,
+            priority: new_todo.priority,
+        };
+        let item = todo.item();
+        self.todos.insert(0, todo);
+        self.queue.push(PendingChange {
+            change_id,
+            kind: ChangeKind::Create { local_id, content },
+        });
+        item
+    }
+
+    // What a queued create sends: its content plus the todo's other fields
+    pub fn new_todo(&self, local_id: u64, content: String) -> NewTodo {
+        match self.todos.iter().find(|t| t.local_id == local_id) {
+            Some(todo) => NewTodo {
+                content,
+                due_date: todo.due_date,
+                due_time: todo.due_time,
+                priority: todo.priority,
+                list: todo.list.clone(),
+                tags: todo.tags.clone(),
+            },
+            None => NewTodo::plain(content),
+        }
+    }
+
+    pub fn edit(
+        &mut self,
+        local_id: u64,
+        expected_version: i32,
+        content: String,
+    ) -> Result<TodoItem, AppError> {
+        let todo = self.checked(local_id, expected_version)?;
+        todo.content = content.clone();
+        let (item, server_id, version) = (todo.item(), todo.server_id, todo.version);
+
+        // Fold into a change that is already queued, so a todo edited twice
+        // offline is still sent against the version the first edit saw
+        let queued = self
+            .queue
+            .iter_mut()
+            .find_map(|change| match &mut change.kind {
+                ChangeKind::Create {
+                    local_id: id,
+                    content,
+                }
+                | ChangeKind::Update {
+                    local_id: id,
+                    content,
+                    ..
+                } if *id == local_id => Some(content),
+                _ => None,
+            });
+        if let Some(queued) = queued {
+            *queued = content;
+        } else if let Some(server_id) = server_id {
+            let change_id = self.next_id();
+            self.queue.push(PendingChange {
+                change_id,
+                kind: ChangeKind::Update {
+                    local_id,
+                    server_id,
+                    expected_version: version,
+                    content,
+                },
+            });
+        }
+        Ok(item)
+    }
+
+    pub fn remove(&mut self, local_id: u64, expected_version: i32) -> Result<(), AppError> {
+        let todo = self.checked(local_id, expected_version)?.clone();
+        self.todos.retain(|t| t.local_id != local_id);
+
+        // Whatever was queued for this todo is superseded by the delete
+        let mut base_version = todo.version;
+        self.queue.retain(|change| match change.kind {
+            ChangeKind::Update {
+                local_id: id,
+                expected_version,
+                ..
+            } if id == local_id => {
+                base_version = expected_version;
+                false
+            }
+            ref kind => kind.local_id() != local_id,
+        });
+
+        // Never reached the server: dropping the queued create is enough
+        if let Some(server_id) = todo.server_id {
+            let change_id = self.next_id();
+            self.queue.push(PendingChange {
+                change_id,
+                kind: ChangeKind::Delete {
+                    local_id,
+                    server_id,
+                    expected_version: base_version,
+                },
+            });
+        }
+        Ok(())
+    }
+
+    // The todo, if it is still at the version the caller saw
+    fn checked(
+        &mut self,
+        local_id: u64,
+        expected_version: i32,
+    ) -> Result<&mut LocalTodo, AppError> {
+        let todo = self
+            .todos
+            .iter_mut()
+            .find(|t| t.local_id == local_id)
+            .ok_or(AppError::Conflict {
+                current_version: None,
+            })?;
+        if todo.version != expected_version {
+            return Err(AppError::Conflict {
+                current_version: Some(todo.version),
+            });
+        }
+        Ok(todo)
+    }
+
+    // A queued create or edit went through. The todo may have been edited or
+    // deleted locally while the request was in flight; those changes are
+    // queued again against the version the server just returned.
+    pub fn synced(&mut self, change_id: u64, local_id: u64, saved: &TodoItem) {
+        let todo = self.todos.iter_mut().find(|t| t.local_id == local_id);
+        let Some(todo) = todo else {
+            self.finish(change_id);
+            let queued_delete = self.queue.iter_mut().find_map(|c| match &mut c.kind {
+                ChangeKind::Delete {
+                    local_id: id,
+                    expected_version,
+                    ..
+                } if *id == local_id => Some(expected_version),
+                _ => None,
+            });
+            match queued_delete {
+                Some(expected_version) => *expected_version = saved.version,
+                None => {
+                    let change_id = self.next_id();
+                    self.queue.push(PendingChange {
+                        change_id,
+                        kind: ChangeKind::Delete {
+                            local_id,
+                            server_id: saved.id,
+                            expected_version: saved.version,
+                        },
+                    });
+                }
+            }
+            return;
+        };
+        todo.server_id = Some(saved.id);
+        todo.version = saved.version;
+
+        let Some(change) = self.queue.iter_mut().find(|c| c.change_id == change_id) else {
+            return;
+        };
+        let newer_content = match &change.kind {
+            ChangeKind::Create { content, .. } | ChangeKind::Update { content, .. }
+                if *content != saved.content =>
+            {
+                Some(content.clone())
+            }
+            _ => None,
+        };
+        match newer_content {
+            Some(content) => {
+                change.kind = ChangeKind::Update {
+                    local_id,
+                    server_id: saved.id,
+                    expected_version: saved.version,
+                    content,
+                }
+            }
+            None => self.finish(change_id),
+        }
+    }
+
+    // The server refused a create; drop the todo instead of retrying forever
+    pub fn rejected(&mut self, change_id: u64, local_id: u64) {
+        self.todos.retain(|t| t.local_id != local_id);
+        self.finish(change_id);
+    }
+
+    pub fn finish(&mut self, change_id: u64) {
+        self.queue.retain(|change| change.change_id != change_id);
+    }
+
+    // Reconcile with the server's list. The server wins for todos it knows
+    // about (including ones deleted or edited elsewhere), except that queued
+    // edits stay visible and queued deletes stay hidden until they are sent.
+    // Todos still waiting to be created stay on top.
+    pub fn merge_server(&mut self, server_todos: Vec<TodoItem>) {
+        let mut merged: Vec<LocalTodo> = self
+            .todos
+            .iter()
+            .filter(|todo| todo.server_id.is_none())
+            .cloned()
+            .collect();
+
+        for server_todo in server_todos {
+            let server_id = server_todo.id;
+            let pending_delete = self.queue.iter().any(|change| {
+                matches!(change.kind, ChangeKind::Delete { server_id: id, .. } if id == server_id)
+            });
+            if pending_delete {
+                continue;
+            }
+            let pending_edit = self.queue.iter().find_map(|change| match &change.kind {
+                ChangeKind::Update {
+                    server_id: id,
+                    content,
+                    ..
+                } if *id == server_id => Some(content.clone()),
+                _ => None,
+            });
+
+            let known = self
+                .todos
+                .iter()
+                .find(|todo| todo.server_id == Some(server_id))
+                .map(|todo| todo.local_id);
+            let local_id = match known {
+                Some(local_id) => local_id,
+                None => self.next_id(),
+            };
+            merged.push(LocalTodo {
+                local_id,
+                server_id: Some(server_id),
+                content: pending_edit.unwrap_or(server_todo.content),
+                notes: server_todo.notes,
+                version: server_todo.version,
+                completed: server_todo.completed,
+                list: server_todo.list,
+                tags: server_todo.tags,
+                due_date: server_todo.due_date,
+                due_time: server_todo.due_time,
+                priority: server_todo.priority,
+            });
+        }
+
+        self.todos = merged;
+    }
+}
+
+// Load the store, let `change` modify it, and write it back. The file is
+// read and written on the blocking pool so the UI thread never waits on disk.
+pub async fn update<T>(
+    username: &str,
+    change: impl FnOnce(&mut LocalStore) -> T,
+) -> Result<T, String> {
+    let _guard = FILE_LOCK.lock().await;
+    let path = store_path(username);
+    let mut store = read(path.clone()).await?;
+    let result = change(&mut store);
+    write(path, &store).await?;
+    Ok(result)
+}
+
+pub async fn load(username: &str) -> Result<LocalStore, String> {
+    let _guard = FILE_LOCK.lock().await;
+    read(store_path(username)).await
+}
+
+async fn read(path: PathBuf) -> Result<LocalStore, String> {
+    let contents = tokio::task::spawn_blocking(move || std::fs::read_to_string(path))
+        .await
+        .map_err(|e| e.to_string())?;
+    match contents {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LocalStore::default()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Written to a temporary file first so a crash never leaves half a store
+async fn write(path: PathBuf, store: &LocalStore) -> Result<(), String> {
+    let contents = serde_json::to_string(store).map_err(|e| e.to_string())?;
+    tokio::task::spawn_blocking(move || {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, contents)?;
+        std::fs::rename(&temp_path, &path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+// `TODO_OFFLINE_DIR` overrides the platform's local data directory
+fn store_path(username: &str) -> PathBuf {
+    let dir = std::env::var("TODO_OFFLINE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            dirs::data_local_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("todo_list_rs")
+        });
+    dir.join(format!("offline-{}.json", file_name_part(username)))
+}
+
+// Usernames are case-insensitive. Accounts from before the username rules
+// may contain '/' or other characters that aren't safe in a file name, so
+// anything outside [a-z0-9_.-] is escaped as %xx; escaping '%' as well keeps
+// two different names from ever sharing a file. Names that follow the rules
+// keep the file they always had.
+fn file_name_part(username: &str) -> String {
+    let mut escaped = String::new();
+    for byte in username.trim().to_lowercase().bytes() {
+        match byte {
+            b'a'..=b'z' | b'0'..=b'9' | b'_' | b'.' | b'-' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("%{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The server's copy of a todo after a create or edit went through
+    fn saved(server_id: usize, version: i32, content: &str) -> TodoItem {
+        TodoItem {
+            id: server_id,
+            content: content.to_string(),
+            notes: String::new(),
+            version,
+            completed: false,
+            list: None,
+            tags: Vec::new(),
+            due_date: None,
+            due_time: None,
+            priority: None,
+            attachments: Vec::new(),
+            status_id: None,
+        }
+    }
+
+    // A store holding one todo the server has as `server_id` at version 1
+    fn synced_store(content: &str, server_id: usize) -> (LocalStore, u64) {
+        let mut store = LocalStore::default();
+        let local_id = store.add(NewTodo::plain(content.to_string())).id as u64;
+        let change_id = store.queue[0].change_id;
+        store.synced(change_id, local_id, &saved(server_id, 1, content));
+        assert!(store.queue.is_empty());
+        (store, local_id)
+    }
+
+    fn only_change(store: &LocalStore) -> &ChangeKind {
+        assert_eq!(store.queue.len(), 1, "{:?}", store.queue);
+        &store.queue[0].kind
+    }
+
+    #[test]
+    fn an_edit_is_folded_into_a_queued_create() {
+        let mut store = LocalStore::default();
+        let local_id = store.add(NewTodo::plain("Buy milk".to_string())).id as u64;
+        store.edit(local_id, 0, "Buy oat milk".to_string()).unwrap();
+
+        assert!(matches!(
+            only_change(&store),
+            ChangeKind::Create { local_id: id, content } if *id == local_id && content == "Buy oat milk"
+        ));
+        assert_eq!(store.visible()[0].content, "Buy oat milk");
+    }
+
+    #[test]
+    fn a_second_edit_keeps_the_first_edits_version() {
+        let (mut store, local_id) = synced_store("Buy milk", 7);
+        store.edit(local_id, 1, "Buy oat milk".to_string()).unwrap();
+        store.edit(local_id, 1, "Buy soy milk".to_string()).unwrap();
+
+        assert!(matches!(
+            only_change(&store),
+            ChangeKind::Update { server_id: 7, expected_version: 1, content, .. } if content == "Buy soy milk"
+        ));
+    }
+
+    #[test]
+    fn edits_of_a_changed_or_missing_todo_conflict() {
+        let (mut store, local_id) = synced_store("Buy milk", 7);
+        assert_eq!(
+            store.edit(local_id, 0, "stale".to_string()).unwrap_err(),
+            AppError::Conflict {
+                current_version: Some(1)
+            }
+        );
+        assert_eq!(
+            store.remove(local_id + 100, 1).unwrap_err(),
+            AppError::Conflict {
+                current_version: None
+            }
+        );
+        assert!(store.queue.is_empty());
+    }
+
+    #[test]
+    fn deleting_an_unsynced_todo_just_drops_its_create() {
+        let mut store = LocalStore::default();
+        let local_id = store.add(NewTodo::plain("Buy milk".to_string())).id as u64;
+        store.remove(local_id, 0).unwrap();
+
+        assert!(store.todos.is_empty());
+        assert!(store.queue.is_empty());
+    }
+
+    #[test]
+    fn a_delete_replaces_a_queued_update_and_keeps_its_base_version() {
+        let (mut store, local_id) = synced_store("Buy milk", 7);
+        store.edit(local_id, 1, "Buy oat milk".to_string()).unwrap();
+        // Someone else edited it meanwhile; the refresh shows version 2 but
+        // the queued edit was still made against version 1
+        store.merge_server(vec![saved(7, 2, "Buy whole milk")]);
+        store.remove(local_id, 2).unwrap();
+
+        assert!(matches!(
+            only_change(&store),
+            ChangeKind::Delete {
+                server_id: 7,
+                expected_version: 1,
+                ..
+            }
+        ));
+        assert!(store.visible().is_empty());
+    }
+
+    #[test]
+    fn a_delete_during_an_in_flight_create_is_queued_once_it_lands() {
+        let mut store = LocalStore::default();
+        let local_id = store.add(NewTodo::plain("Buy milk".to_string())).id as u64;
+        let create_id = store.queue[0].change_id;
+
+        // Deleted while the create is on its way to the server
+        store.remove(local_id, 0).unwrap();
+        store.synced(create_id, local_id, &saved(7, 1, "Buy milk"));
+
+        assert!(matches!(
+            only_change(&store),
+            ChangeKind::Delete {
+                local_id: id,
+                server_id: 7,
+                expected_version: 1,
+            } if *id == local_id
+        ));
+        assert!(store.visible().is_empty());
+    }
+
+    #[test]
+    fn a_delete_during_an_in_flight_update_moves_to_the_new_version() {
+        let (mut store, local_id) = synced_store("Buy milk", 7);
+        store.edit(local_id, 1, "Buy oat milk".to_string()).unwrap();
+        let update_id = store.queue[0].change_id;
+
+        store.remove(local_id, 1).unwrap();
+        store.synced(update_id, local_id, &saved(7, 2, "Buy oat milk"));
+
+        assert!(matches!(
+            only_change(&store),
+            ChangeKind::Delete {
+                server_id: 7,
+                expected_version: 2,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn an_edit_during_an_in_flight_create_is_sent_as_an_update() {
+        let mut store = LocalStore::default();
+        let local_id = store.add(NewTodo::plain("Buy milk".to_string())).id as u64;
+        let create_id = store.queue[0].change_id;
+
+        store.edit(local_id, 0, "Buy oat milk".to_string()).unwrap();
+        store.synced(create_id, local_id, &saved(7, 1, "Buy milk"));
+
+        assert!(matches!(
+            only_change(&store),
+            ChangeKind::Update { server_id: 7, expected_version: 1, content, .. } if content == "Buy oat milk"
+        ));
+        assert_eq!(store.visible()[0].version, 1);
+    }
+
+    #[test]
+    fn merging_keeps_pending_edits_and_hides_pending_deletes() {
+        let (mut store, edited) = synced_store("Buy milk", 7);
+        let deleted = store.add(NewTodo::plain("Walk dog".to_string())).id as u64;
+        let change_id = store.queue[0].change_id;
+        store.synced(change_id, deleted, &saved(8, 1, "Walk dog"));
+        let unsynced = store.add(NewTodo::plain("Call mum".to_string())).id as u64;
+
+        store.edit(edited, 1, "Buy oat milk".to_string()).unwrap();
+        store.remove(deleted, 1).unwrap();
+        store.merge_server(vec![
+            saved(9, 1, "Made elsewhere"),
+            saved(8, 1, "Walk dog"),
+            saved(7, 3, "Buy whole milk"),
+        ]);
+
+        let visible = store.visible();
+        let shown: Vec<(&str, i32)> = visible
+            .iter()
+            .map(|todo| (todo.content.as_str(), todo.version))
+            .collect();
+        assert_eq!(
+            shown,
+            vec![("Call mum", 0), ("Made elsewhere", 1), ("Buy oat milk", 3)]
+        );
+        // Local ids survive the merge, so open views keep pointing at the todo
+        assert_eq!(visible[0].id as u64, unsynced);
+        assert_eq!(visible[2].id as u64, edited);
+        assert_eq!(store.queue.len(), 3);
+    }
+
+    #[test]
+    fn merging_drops_todos_deleted_on_the_server() {
+        let (mut store, _) = synced_store("Buy milk", 7);
+        store.merge_server(Vec::new());
+        assert!(store.visible().is_empty());
+    }
+
+    #[test]
+    fn a_rejected_create_drops_the_todo_and_its_change() {
+        let mut store = LocalStore::default();
+        let kept = store.add(NewTodo::plain("Buy milk".to_string())).id as u64;
+        let rejected = store.add(NewTodo::plain("x".repeat(10_000))).id as u64;
+        let change_id = store.queue[1].change_id;
+
+        store.rejected(change_id, rejected);
+
+        assert_eq!(store.visible().len(), 1);
+        assert_eq!(store.visible()[0].id as u64, kept);
+        assert!(
+            matches!(only_change(&store), ChangeKind::Create { local_id, .. } if *local_id == kept)
+        );
+    }
+
+    #[test]
+    fn usernames_are_safe_file_names() {
+        assert_eq!(file_name_part(" Alice_01 "), "alice_01");
+        assert_eq!(file_name_part("john.doe-2"), "john.doe-2");
+        assert_eq!(file_name_part("../etc/passwd"), "..%2fetc%2fpasswd");
+        assert_eq!(file_name_part("a/b"), "a%2fb");
+        // Escaping '%' too keeps these apart
+        assert_ne!(file_name_part("a%2fb"), file_name_part("a/b"));
+        assert!(!file_name_part("ünïcode\\name").contains(['/', '\\']));
+    }
+
+    #[tokio::test]
+    async fn the_store_round_trips_through_its_file() {
+        let dir = std::env::temp_dir().join(format!("todo-offline-{}", uuid::Uuid::new_v4()));
+        std::env::set_var("TODO_OFFLINE_DIR", &dir);
+
+        let username = "legacy/user";
+        assert!(load(username).await.unwrap().todos.is_empty());
+        let added = update(username, |store| {
+            store.add(NewTodo::plain("Buy milk".to_string()))
+        })
+        .await
+        .unwrap();
+
+        let reloaded = load(username).await.unwrap();
+        assert_eq!(reloaded.visible(), vec![added]);
+        assert_eq!(reloaded.queue.len(), 1);
+        // One file, right in the directory, without a leftover temporary
+        let files: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["offline-legacy%2fuser.json"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
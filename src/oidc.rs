@@ -0,0 +1,299 @@
+// OpenID Connect single sign-on (authorization code flow with PKCE).
+//
+// Configured with `OIDC_ISSUER_URL`, `OIDC_CLIENT_ID` and
+// `OIDC_CLIENT_SECRET`; SSO is off unless all three are set.
+// `OIDC_REDIRECT_URL` defaults to `{APP_BASE_URL}/auth/oidc/callback` and must
+// be registered with the provider.
+//
+// `GET /auth/oidc/start` redirects to the provider. The provider sends the
+// browser back to `GET /auth/oidc/callback`, which exchanges the code,
+// verifies the ID token, finds or creates the local user, and redirects to
+// `/sso-complete?ticket=...`. The app then trades that single-use ticket for
+// the username with the `complete_sso_login` server function.
+
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Redirect, Response},
+};
+use openidconnect::core::{CoreAuthenticationFlow, CoreClient, CoreProviderMetadata};
+use openidconnect::reqwest::async_http_client;
+use openidconnect::{
+    AuthorizationCode, ClientId, ClientSecret, CsrfToken, IssuerUrl, Nonce, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, Scope, TokenResponse,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+use crate::backend::password_reset::app_base_url;
+use crate::backend::sso::{find_or_create_sso_user, SsoIdentity};
+use crate::secrets;
+
+// How long the user may spend at the provider, and how long the app has to
+// redeem the ticket it is redirected back with
+const LOGIN_TTL: Duration = Duration::from_secs(10 * 60);
+const TICKET_TTL: Duration = Duration::from_secs(2 * 60);
+
+// Logins sent to the provider, keyed by the `state` parameter
+struct PendingLogin {
+    pkce_verifier: PkceCodeVerifier,
+    nonce: Nonce,
+    expires_at: Instant,
+}
+
+// Finished logins waiting for the app to pick them up, keyed by ticket hash
+struct Ticket {
+    user_id: i32,
+    username: String,
+    expires_at: Instant,
+}
+
+// In memory like the 2FA challenges; a restart just means signing in again
+static PENDING: LazyLock<Mutex<HashMap<String, PendingLogin>>> = LazyLock::new(Default::default);
+static TICKETS: LazyLock<Mutex<HashMap<String, Ticket>>> = LazyLock::new(Default::default);
+static CLIENT: OnceCell<CoreClient> = OnceCell::const_new();
+
+#[derive(Deserialize)]
+pub struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+struct OidcConfig {
+    issuer_url: String,
+    client_id: String,
+    client_secret: String,
+    redirect_url: String,
+}
+
+impl OidcConfig {
+    fn from_env() -> Option<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Some(OidcConfig {
+            issuer_url: var("OIDC_ISSUER_URL")?,
+            client_id: var("OIDC_CLIENT_ID")?,
+            client_secret: var("OIDC_CLIENT_SECRET")?,
+            redirect_url: var("OIDC_REDIRECT_URL")
+                .unwrap_or_else(|| format!("{}/auth/oidc/callback", app_base_url())),
+        })
+    }
+}
+
+pub fn is_configured() -> bool {
+    OidcConfig::from_env().is_some()
+}
+
+// GET /auth/oidc/start
+pub async fn start() -> Response {
+    let client = match client().await {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::error!(error = %e, "OIDC provider unavailable");
+            return finish_with_error("unavailable");
+        }
+    };
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (auth_url, csrf_token, nonce) = client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .add_scope(Scope::new("email".to_string()))
+        .add_scope(Scope::new("profile".to_string()))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    let now = Instant::now();
+    let mut pending = PENDING.lock().unwrap();
+    pending.retain(|_, login| login.expires_at > now);
+    pending.insert(
+        csrf_token.secret().clone(),
+        PendingLogin {
+            pkce_verifier,
+            nonce,
+            expires_at: now + LOGIN_TTL,
+        },
+    );
+
+    Redirect::to(auth_url.as_str()).into_response()
+}
+
+// GET /auth/oidc/callback
+pub async fn callback(Query(params): Query<CallbackParams>) -> Response {
+    if let Some(error) = params.error {
+        tracing::info!(%error, "OIDC login rejected by provider");
+        return finish_with_error("denied");
+    }
+    let (Some(code), Some(state)) = (params.code, params.state) else {
+        return finish_with_error("failed");
+    };
+
+    // Unknown or stale state means a forged or replayed callback
+    let pending = PENDING.lock().unwrap().remove(&state);
+    let Some(pending) = pending.filter(|login| login.expires_at > Instant::now()) else {
+        return finish_with_error("expired");
+    };
+
+    match exchange(code, pending).await {
+        Ok(identity) => match find_or_create_sso_user(&identity).await {
+            Ok((user_id, username)) => Redirect::to(&format!(
+                "/sso-complete?ticket={}",
+                issue_ticket(user_id, username)
+            ))
+            .into_response(),
+            Err(e) => {
+                tracing::error!(error = %e, "Could not link OIDC identity");
+                finish_with_error("failed")
+            }
+        },
+        Err(e) => {
+            tracing::warn!(error = %e, "OIDC code exchange failed");
+            finish_with_error("failed")
+        }
+    }
+}
+
+// Single use: the ticket is gone once redeemed
+pub fn redeem_ticket(ticket: &str) -> Option<(i32, String)> {
+    let now = Instant::now();
+    let mut tickets = TICKETS.lock().unwrap();
+    tickets.retain(|_, pending| pending.expires_at > now);
+    tickets
+        .remove(&secrets::hash_token(ticket))
+        .map(|ticket| (ticket.user_id, ticket.username))
+}
+
+fn issue_ticket(user_id: i32, username: String) -> String {
+    let ticket = secrets::generate_token();
+    TICKETS.lock().unwrap().insert(
+        secrets::hash_token(&ticket),
+        Ticket {
+            user_id,
+            username,
+            expires_at: Instant::now() + TICKET_TTL,
+        },
+    );
+    ticket
+}
+
+// Error codes are mapped to messages by the SsoComplete page
+fn finish_with_error(code: &str) -> Response {
+    Redirect::to(&format!("/sso-complete?error={}", code)).into_response()
+}
+
+// Provider metadata is discovered once; a failed discovery is retried on the
+// next login instead of being cached
+async fn client() -> Result<&'static CoreClient, String> {
+    CLIENT
+        .get_or_try_init(|| async {
+            let config =
+                OidcConfig::from_env().ok_or_else(|| "OIDC is not configured".to_string())?;
+            let issuer_url = IssuerUrl::new(config.issuer_url).map_err(|e| e.to_string())?;
+            tracing::info!(issuer = %issuer_url.as_str(), "Discovering OIDC provider");
+            let metadata = CoreProviderMetadata::discover_async(issuer_url, async_http_client)
+                .await
+                .map_err(|e| e.to_string())?;
+            let redirect_url = RedirectUrl::new(config.redirect_url).map_err(|e| e.to_string())?;
+            let client = CoreClient::from_provider_metadata(
+                metadata,
+                ClientId::new(config.client_id),
+                Some(ClientSecret::new(config.client_secret)),
+            )
+            .set_redirect_uri(redirect_url);
+            Ok::<_, String>(client)
+        })
+        .await
+}
+
+// Trade the code for tokens and check the ID token's signature, issuer,
+// audience, expiry and nonce
+async fn exchange(code: String, pending: PendingLogin) -> Result<SsoIdentity, String> {
+    let client = client().await?;
+    let token_response = client
+        .exchange_code(AuthorizationCode::new(code))
+        .set_pkce_verifier(pending.pkce_verifier)
+        .request_async(async_http_client)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let id_token = token_response
+        .id_token()
+        .ok_or("provider did not return an ID token")?;
+    let claims = id_token
+        .claims(&client.id_token_verifier(), &pending.nonce)
+        .map_err(|e| e.to_string())?;
+
+    Ok(SsoIdentity {
+        issuer: claims.issuer().as_str().to_string(),
+        subject: claims.subject().as_str().to_string(),
+        // Only trust addresses the provider has verified
+        email: claims
+            .email()
+            .filter(|_| claims.email_verified() == Some(true))
+            .map(|email| email.as_str().to_string()),
+        preferred_username: claims
+            .preferred_username()
+            .map(|username| username.as_str().to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::LOCATION;
+
+    fn location(response: &Response) -> &str {
+        response.headers()[LOCATION].to_str().unwrap()
+    }
+
+    #[test]
+    fn tickets_are_single_use() {
+        let ticket = issue_ticket(7, "alice".to_string());
+        assert_eq!(redeem_ticket(&ticket), Some((7, "alice".to_string())));
+        assert_eq!(redeem_ticket(&ticket), None);
+    }
+
+    #[test]
+    fn unknown_tickets_are_rejected() {
+        assert_eq!(redeem_ticket("not-a-ticket"), None);
+    }
+
+    #[tokio::test]
+    async fn callback_reports_provider_errors() {
+        let response = callback(Query(CallbackParams {
+            code: None,
+            state: None,
+            error: Some("access_denied".to_string()),
+        }))
+        .await;
+        assert_eq!(location(&response), "/sso-complete?error=denied");
+    }
+
+    #[tokio::test]
+    async fn callback_rejects_unknown_state() {
+        // A forged or replayed callback never reaches the provider
+        let response = callback(Query(CallbackParams {
+            code: Some("code".to_string()),
+            state: Some("unknown".to_string()),
+            error: None,
+        }))
+        .await;
+        assert_eq!(location(&response), "/sso-complete?error=expired");
+    }
+
+    #[tokio::test]
+    async fn callback_requires_code_and_state() {
+        let response = callback(Query(CallbackParams {
+            code: None,
+            state: Some("state".to_string()),
+            error: None,
+        }))
+        .await;
+        assert_eq!(location(&response), "/sso-complete?error=failed");
+    }
+}
@@ -0,0 +1,448 @@
+// Quick-add syntax for the `Todo_save` input, e.g.
+// "Call Bob tomorrow 3pm !high #work @Personal".
+//
+// Recognised anywhere in the text (the first of each kind wins):
+// - due dates: today, tomorrow, weekdays ("fri", "next friday"), "next
+//   week", "in 3 days", "in 2 weeks", 2025-03-14, "14 mar", "march 14th",
+//   optionally after "on" or "due"
+// - times: 3pm, 3:30pm, "3 pm", 15:30, noon, midnight, optionally after "at".
+//   A time without a date means its next occurrence.
+// - priority: !low, !medium, !high, or !, !!, !!!
+// - tags: #word, lists: @word
+//
+// Everything else is the todo's text. Parsing runs on the client so relative
+// dates follow the user's clock; the server only sees the structured fields.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use crate::backend::{NewTodo, Priority};
+use crate::validation;
+
+pub fn parse(input: &str, now: NaiveDateTime) -> NewTodo {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let today = now.date();
+    let mut todo = NewTodo::default();
+    let mut text = Vec::new();
+
+    let mut i = 0;
+    while i < words.len() {
+        let rest = &words[i..];
+        let word = clean(rest[0]);
+
+        if todo.priority.is_none() {
+            if let Some(priority) = parse_priority(&word) {
+                todo.priority = Some(priority);
+                i += 1;
+                continue;
+            }
+        }
+        if let Some(tag) = word.strip_prefix('#') {
+            let tag = validation::normalize_tag(tag);
+            if validation::validate_tag(&tag).is_ok() {
+                if !todo.tags.contains(&tag) {
+                    todo.tags.push(tag);
+                }
+                i += 1;
+                continue;
+            }
+        }
+        if todo.list.is_none() {
+            let name = rest[0].trim_end_matches([',', '.']);
+            if let Some(name) = name.strip_prefix('@') {
+                if validation::validate_list_name(name).is_ok() {
+                    todo.list = Some(name.to_string());
+                    i += 1;
+                    continue;
+                }
+            }
+        }
+        if todo.due_date.is_none() {
+            if let Some((date, used)) =
+                with_connector(rest, &["on", "due"], |words| parse_date(words, today))
+            {
+                todo.due_date = Some(date);
+                i += used;
+                continue;
+            }
+        }
+        if todo.due_time.is_none() {
+            if let Some((time, used)) = with_connector(rest, &["at"], parse_time) {
+                todo.due_time = Some(time);
+                i += used;
+                continue;
+            }
+        }
+
+        text.push(rest[0]);
+        i += 1;
+    }
+
+    if let (None, Some(time)) = (todo.due_date, todo.due_time) {
+        todo.due_date = Some(if time > now.time() {
+            today
+        } else {
+            today + Duration::days(1)
+        });
+    }
+
+    // Nothing but markers: keep the text as typed rather than saving nothing
+    todo.content = if text.is_empty() {
+        input.trim().to_string()
+    } else {
+        text.join(" ")
+    };
+    todo
+}
+
+// "today", "tomorrow 15:00", "Fri 14 Mar", "14 Mar 2027 09:30"
+pub fn due_label(date: NaiveDate, time: Option<NaiveTime>, today: NaiveDate) -> String {
+    let day = if date == today {
+        "today".to_string()
+    } else if date == today + Duration::days(1) {
+        "tomorrow".to_string()
+    } else if date > today && date < today + Duration::days(7) {
+        date.format("%a %-d %b").to_string()
+    } else if date.year() == today.year() {
+        date.format("%-d %b").to_string()
+    } else {
+        date.format("%-d %b %Y").to_string()
+    };
+    match time {
+        Some(time) => format!("{} {}", day, time.format("%H:%M")),
+        None => day,
+    }
+}
+
+// Lowercase, without trailing punctuation
+fn clean(word: &str) -> String {
+    word.trim_end_matches([',', '.']).to_lowercase()
+}
+
+// Try `parse` at the start of `words`, or after one of `connectors`
+fn with_connector<T>(
+    words: &[&str],
+    connectors: &[&str],
+    parse: impl Fn(&[&str]) -> Option<(T, usize)>,
+) -> Option<(T, usize)> {
+    if let Some(found) = parse(words) {
+        return Some(found);
+    }
+    if words.len() > 1 && connectors.contains(&clean(words[0]).as_str()) {
+        return parse(&words[1..]).map(|(value, used)| (value, used + 1));
+    }
+    None
+}
+
+fn parse_priority(word: &str) -> Option<Priority> {
+    match word {
+        "!" | "!low" => Some(Priority::Low),
+        "!!" | "!med" | "!medium" => Some(Priority::Medium),
+        "!!!" | "!high" => Some(Priority::High),
+        _ => None,
+    }
+}
+
+// A date at the start of `words` and how many words it took
+fn parse_date(words: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let first = clean(words[0]);
+    let second = words.get(1).map(|w| clean(w));
+
+    match first.as_str() {
+        "today" => return Some((today, 1)),
+        "tomorrow" | "tmr" | "tmrw" => return Some((today + Duration::days(1), 1)),
+        _ => {}
+    }
+    if let Some(weekday) = parse_weekday(&first) {
+        return Some((next_weekday(today, weekday), 1));
+    }
+    if first == "next" {
+        match second.as_deref() {
+            Some("week") => return Some((next_weekday(today, Weekday::Mon), 2)),
+            Some(day) => {
+                // "next friday" is the Friday of next week
+                let weekday = parse_weekday(day)?;
+                let next_monday = next_weekday(today, Weekday::Mon);
+                let offset = weekday.num_days_from_monday() as i64;
+                return Some((next_monday + Duration::days(offset), 2));
+            }
+            None => return None,
+        }
+    }
+    if first == "in" {
+        let count: i64 = second.as_deref()?.parse().ok()?;
+        let unit = clean(words.get(2)?);
+        let days = match unit.as_str() {
+            "day" | "days" => count,
+            "week" | "weeks" => count * 7,
+            _ => return None,
+        };
+        if !(0..=3650).contains(&days) {
+            return None;
+        }
+        return Some((today + Duration::days(days), 3));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&first, "%Y-%m-%d") {
+        return Some((date, 1));
+    }
+
+    // "14 mar" / "mar 14" / "march 14th"
+    let second = second?;
+    let (day, month) = match (parse_day(&first), parse_month(&second)) {
+        (Some(day), Some(month)) => (day, month),
+        _ => (parse_day(&second)?, parse_month(&first)?),
+    };
+    let this_year = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    // Dates that already passed this year mean next year
+    let date = if this_year < today {
+        NaiveDate::from_ymd_opt(today.year() + 1, month, day)?
+    } else {
+        this_year
+    };
+    Some((date, 2))
+}
+
+// A time at the start of `words` and how many words it took
+fn parse_time(words: &[&str]) -> Option<(NaiveTime, usize)> {
+    let first = clean(words[0]);
+    match first.as_str() {
+        "noon" => return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1)),
+        "midnight" => return Some((NaiveTime::from_hms_opt(0, 0, 0)?, 1)),
+        _ => {}
+    }
+
+    // "3pm", "3:30pm", then "3 pm"
+    for suffix in ["am", "pm"] {
+        if let Some(clock) = first.strip_suffix(suffix) {
+            return Some((twelve_hour(clock, suffix)?, 1));
+        }
+    }
+    if let Some(suffix) = words.get(1).map(|w| clean(w)) {
+        if suffix == "am" || suffix == "pm" {
+            return Some((twelve_hour(&first, &suffix)?, 2));
+        }
+    }
+
+    // 24-hour "15:30"; a bare number is too ambiguous to be a time
+    let (hour, minute) = first.split_once(':')?;
+    if minute.len() != 2 {
+        return None;
+    }
+    let time = NaiveTime::from_hms_opt(hour.parse().ok()?, minute.parse().ok()?, 0)?;
+    Some((time, 1))
+}
+
+fn twelve_hour(clock: &str, suffix: &str) -> Option<NaiveTime> {
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+    if !(1..=12).contains(&hour) {
+        return None;
+    }
+    let hour = match (hour, suffix) {
+        (12, "am") => 0,
+        (12, _) => 12,
+        (hour, "pm") => hour + 12,
+        (hour, _) => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let weekday = match word {
+        "mon" | "monday" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" => Weekday::Tue,
+        "wednesday" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" => Weekday::Thu,
+        "fri" | "friday" => Weekday::Fri,
+        // No "wed", "sat" or "sun": too likely to be meant as words
+        "saturday" => Weekday::Sat,
+        "sunday" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+// The next `weekday` after today (a week ahead if today is that day)
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() as i64
+        - today.weekday().num_days_from_monday() as i64)
+        .rem_euclid(7);
+    today + Duration::days(if ahead == 0 { 7 } else { ahead })
+}
+
+fn parse_month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(word) && (word.len() == 3 || *month == word))
+        .map(|index| index as u32 + 1)
+}
+
+// "14", "14th", "1st"
+fn parse_day(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    let day: u32 = digits.parse().ok()?;
+    (1..=31).contains(&day).then_some(day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monday 19 Oct 2026, 10:00
+    fn now() -> NaiveDateTime {
+        date(2026, 10, 19).and_hms_opt(10, 0, 0).unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn due(input: &str) -> (Option<NaiveDate>, Option<NaiveTime>) {
+        let todo = parse(input, now());
+        (todo.due_date, todo.due_time)
+    }
+
+    #[test]
+    fn parses_every_marker() {
+        let todo = parse("Call Bob tomorrow 3pm !high #work @Personal", now());
+        assert_eq!(
+            todo,
+            NewTodo {
+                content: "Call Bob".to_string(),
+                due_date: Some(date(2026, 10, 20)),
+                due_time: Some(time(15, 0)),
+                priority: Some(Priority::High),
+                list: Some("Personal".to_string()),
+                tags: vec!["work".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn plain_text_is_left_alone() {
+        assert_eq!(
+            parse("  Buy 2 apples  ", now()),
+            NewTodo::plain("Buy 2 apples".to_string())
+        );
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(due("Pay rent today").0, Some(date(2026, 10, 19)));
+        assert_eq!(due("Pay rent fri").0, Some(date(2026, 10, 23)));
+        // Today's weekday means a week ahead
+        assert_eq!(due("Pay rent monday").0, Some(date(2026, 10, 26)));
+        assert_eq!(due("Pay rent next friday").0, Some(date(2026, 10, 30)));
+        assert_eq!(due("Pay rent next week").0, Some(date(2026, 10, 26)));
+        assert_eq!(due("Pay rent in 3 days").0, Some(date(2026, 10, 22)));
+        assert_eq!(due("Pay rent in 2 weeks").0, Some(date(2026, 11, 2)));
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(due("Taxes due 2027-04-15").0, Some(date(2027, 4, 15)));
+        assert_eq!(due("Party on 14 dec").0, Some(date(2026, 12, 14)));
+        assert_eq!(due("Party December 14th").0, Some(date(2026, 12, 14)));
+        // Already passed this year
+        assert_eq!(due("Party 14 mar").0, Some(date(2027, 3, 14)));
+        assert_eq!(due("Party 31 feb").0, None);
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(due("Call tomorrow at 3:30pm").1, Some(time(15, 30)));
+        assert_eq!(due("Call tomorrow 3 pm").1, Some(time(15, 0)));
+        assert_eq!(due("Call tomorrow 15:30").1, Some(time(15, 30)));
+        assert_eq!(due("Call tomorrow 12am").1, Some(time(0, 0)));
+        assert_eq!(due("Call tomorrow noon").1, Some(time(12, 0)));
+        assert_eq!(due("Call tomorrow 13pm"), (Some(date(2026, 10, 20)), None));
+    }
+
+    #[test]
+    fn time_without_date_is_next_occurrence() {
+        assert_eq!(
+            due("Lunch at noon"),
+            (Some(date(2026, 10, 19)), Some(time(12, 0)))
+        );
+        assert_eq!(
+            due("Standup 9:30"),
+            (Some(date(2026, 10, 20)), Some(time(9, 30)))
+        );
+    }
+
+    #[test]
+    fn priorities() {
+        assert_eq!(parse("Fix it !", now()).priority, Some(Priority::Low));
+        assert_eq!(parse("Fix it !!", now()).priority, Some(Priority::Medium));
+        assert_eq!(
+            parse("Fix it !medium", now()).priority,
+            Some(Priority::Medium)
+        );
+        // The first marker wins; later ones stay in the text
+        let todo = parse("Fix it !low !high", now());
+        assert_eq!(todo.priority, Some(Priority::Low));
+        assert_eq!(todo.content, "Fix it !high");
+    }
+
+    #[test]
+    fn tags_and_lists() {
+        let todo = parse("Read #Books #books #fun @Home @Work", now());
+        assert_eq!(todo.tags, vec!["books".to_string(), "fun".to_string()]);
+        assert_eq!(todo.list, Some("Home".to_string()));
+        assert_eq!(todo.content, "Read @Work");
+    }
+
+    #[test]
+    fn ambiguous_words_are_text() {
+        let todo = parse("Wed plans for sat", now());
+        assert_eq!(todo.due_date, None);
+        assert_eq!(todo.content, "Wed plans for sat");
+    }
+
+    #[test]
+    fn markers_only_keep_the_input() {
+        let todo = parse("#work tomorrow", now());
+        assert_eq!(todo.content, "#work tomorrow");
+        assert_eq!(todo.tags, vec!["work".to_string()]);
+        assert_eq!(todo.due_date, Some(date(2026, 10, 20)));
+    }
+
+    #[test]
+    fn due_labels() {
+        let today = date(2026, 10, 19);
+        assert_eq!(due_label(today, None, today), "today");
+        assert_eq!(
+            due_label(date(2026, 10, 20), Some(time(15, 0)), today),
+            "tomorrow 15:00"
+        );
+        assert_eq!(due_label(date(2026, 10, 23), None, today), "Fri 23 Oct");
+        assert_eq!(due_label(date(2026, 12, 14), None, today), "14 Dec");
+        assert_eq!(due_label(date(2027, 3, 14), None, today), "14 Mar 2027");
+    }
+}
@@ -0,0 +1,356 @@
+// In-memory rate limiting and lockout for `login` and `register`.
+//
+// Attempts are counted in a sliding window per client IP and per username.
+// Failed logins additionally lock the username out for a period that doubles
+// with each failure past the threshold. State lives in this process only, so
+// it resets on restart and is not shared between replicas.
+
+use dioxus::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::ConnectInfo;
+
+use crate::errors::AppError;
+
+const WINDOW: Duration = Duration::from_secs(5 * 60);
+const MAX_ATTEMPTS_PER_IP: usize = 20;
+const MAX_ATTEMPTS_PER_USERNAME: usize = 10;
+
+// Per-IP limit for actions with no username to key on
+const MAX_ANONYMOUS_ATTEMPTS_PER_IP: usize = 10;
+
+// Consecutive failed logins before the account is locked
+const LOCKOUT_THRESHOLD: u32 = 5;
+const BASE_LOCKOUT: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Login,
+    Register,
+    // Requesting a reset email (keyed by email) and redeeming a reset token
+    PasswordReset,
+    // Redeeming an emailed address verification token
+    EmailVerification,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Login => "login",
+            Action::Register => "register",
+            Action::PasswordReset => "password_reset",
+            Action::EmailVerification => "email_verification",
+        }
+    }
+}
+
+struct Failures {
+    consecutive: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Limiter {
+    // Attempt timestamps keyed by "<action>:ip:<addr>" or "<action>:user:<name>"
+    attempts: HashMap<String, VecDeque<Instant>>,
+    // Failed logins keyed by normalized username
+    failures: HashMap<String, Failures>,
+}
+
+static LIMITER: LazyLock<Mutex<Limiter>> = LazyLock::new(Default::default);
+
+// Count an attempt and reject it if the caller is over a limit or the
+// account is locked. Call before touching the database.
+pub fn check(action: Action, username: &str) -> Result<(), ServerFnError> {
+    let now = Instant::now();
+    let username = normalize(username);
+    let mut limiter = LIMITER.lock().unwrap();
+
+    if let Action::Login = action {
+        if let Some(locked_until) = limiter.locked_until(&username, now) {
+            tracing::info!(reason = "locked_out", "Attempt rejected");
+            return Err(rate_limited(locked_until - now));
+        }
+    }
+
+    let mut keys = vec![(
+        format!("{}:user:{}", action.as_str(), username),
+        MAX_ATTEMPTS_PER_USERNAME,
+    )];
+    if let Some(ip) = client_ip() {
+        keys.push((
+            format!("{}:ip:{}", action.as_str(), ip),
+            MAX_ATTEMPTS_PER_IP,
+        ));
+    }
+
+    limiter.admit(keys, now)
+}
+
+// Like `check`, but only counts attempts per client IP. For actions such as
+// redeeming a reset token where there is no username to key on.
+pub fn check_ip(action: Action) -> Result<(), ServerFnError> {
+    let Some(ip) = client_ip() else {
+        return Ok(());
+    };
+    let keys = vec![(
+        format!("{}:ip:{}", action.as_str(), ip),
+        MAX_ANONYMOUS_ATTEMPTS_PER_IP,
+    )];
+    LIMITER.lock().unwrap().admit(keys, Instant::now())
+}
+
+// A failed login; locks the account once the threshold is reached
+pub fn record_failure(username: &str) {
+    LIMITER
+        .lock()
+        .unwrap()
+        .record_failure(normalize(username), Instant::now());
+}
+
+// A successful login clears the failure streak
+pub fn record_success(username: &str) {
+    LIMITER
+        .lock()
+        .unwrap()
+        .failures
+        .remove(&normalize(username));
+}
+
+impl Limiter {
+    fn locked_until(&self, username: &str, now: Instant) -> Option<Instant> {
+        self.failures
+            .get(username)
+            .and_then(|failures| failures.locked_until)
+            .filter(|locked_until| *locked_until > now)
+    }
+
+    fn record_failure(&mut self, username: String, now: Instant) {
+        let failures = self.failures.entry(username).or_insert(Failures {
+            consecutive: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        failures.consecutive += 1;
+        failures.last_failure = now;
+
+        if failures.consecutive >= LOCKOUT_THRESHOLD {
+            let doublings = (failures.consecutive - LOCKOUT_THRESHOLD).min(16);
+            let lockout = (BASE_LOCKOUT * 2u32.pow(doublings)).min(MAX_LOCKOUT);
+            failures.locked_until = Some(now + lockout);
+            tracing::warn!(
+                failures = failures.consecutive,
+                lockout_secs = lockout.as_secs(),
+                "Account temporarily locked"
+            );
+        }
+    }
+
+    // Reject if any key is over its limit, otherwise record the attempt on all
+    fn admit(&mut self, keys: Vec<(String, usize)>, now: Instant) -> Result<(), ServerFnError> {
+        for (key, limit) in &keys {
+            let attempts = self.attempts.entry(key.clone()).or_default();
+            while attempts.front().is_some_and(|at| now - *at > WINDOW) {
+                attempts.pop_front();
+            }
+            if attempts.len() >= *limit {
+                let oldest = *attempts.front().unwrap();
+                tracing::info!(reason = "too_many_attempts", "Attempt rejected");
+                return Err(rate_limited(WINDOW - (now - oldest)));
+            }
+        }
+        for (key, _) in keys {
+            self.attempts.entry(key).or_default().push_back(now);
+        }
+
+        self.prune(now);
+        Ok(())
+    }
+
+    // Drop windows, failure streaks and lockouts that no longer affect
+    // anything. Failures are recorded for unknown usernames too, so a streak
+    // without a recent failure or an active lockout must not be kept around.
+    fn prune(&mut self, now: Instant) {
+        self.attempts
+            .retain(|_, attempts| attempts.back().is_some_and(|at| now - *at <= WINDOW));
+        self.failures.retain(|_, failures| {
+            now - failures.last_failure <= WINDOW
+                || failures
+                    .locked_until
+                    .is_some_and(|locked_until| now < locked_until + WINDOW)
+        });
+    }
+}
+
+fn rate_limited(retry_after: Duration) -> ServerFnError {
+    // Round up so the client never retries a moment too early
+    let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    AppError::RateLimited { retry_after_secs }.into_server_error()
+}
+
+fn normalize(username: &str) -> String {
+    username.trim().to_lowercase()
+}
+
+// Peer address of the current request. Behind a reverse proxy set
+// `TRUST_PROXY_HEADERS=1` to use the address the proxy added to
+// `X-Forwarded-For` instead.
+fn client_ip() -> Option<IpAddr> {
+    let context = server_context();
+    let parts = context.request_parts();
+
+    let trust_proxy = std::env::var("TRUST_PROXY_HEADERS").is_ok_and(|v| v == "1" || v == "true");
+    if trust_proxy {
+        let forwarded = forwarded_client(
+            parts
+                .headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok()),
+        );
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+
+    parts
+        .extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+// The right-most `X-Forwarded-For` hop, which our proxy appended. Everything
+// before it came from the client and can be anything.
+fn forwarded_client<'a>(values: impl Iterator<Item = &'a str>) -> Option<IpAddr> {
+    values
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|hop| !hop.is_empty())
+        .last()
+        .and_then(|hop| hop.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retry_after_secs(result: Result<(), ServerFnError>) -> u64 {
+        match AppError::from_server_error(&result.unwrap_err()) {
+            Some(AppError::RateLimited { retry_after_secs }) => retry_after_secs,
+            other => panic!("expected a rate limit, got {:?}", other),
+        }
+    }
+
+    fn ip_key() -> Vec<(String, usize)> {
+        vec![("login:ip:192.0.2.1".to_string(), MAX_ATTEMPTS_PER_IP)]
+    }
+
+    #[test]
+    fn limits_attempts_per_ip_within_the_window() {
+        let mut limiter = Limiter::default();
+        let start = Instant::now();
+        for i in 0..MAX_ATTEMPTS_PER_IP {
+            let at = start + Duration::from_secs(i as u64);
+            assert!(limiter.admit(ip_key(), at).is_ok());
+        }
+
+        // The oldest attempt leaves the window 300s after it was made
+        let now = start + Duration::from_secs(100);
+        assert_eq!(retry_after_secs(limiter.admit(ip_key(), now)), 200);
+        assert!(limiter.admit(ip_key(), start + WINDOW).is_err());
+        assert!(limiter
+            .admit(ip_key(), start + WINDOW + Duration::from_millis(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn limits_attempts_per_username_across_ips() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+        let keys = |ip: usize| {
+            vec![
+                ("login:user:alice".to_string(), MAX_ATTEMPTS_PER_USERNAME),
+                (format!("login:ip:10.0.0.{}", ip), MAX_ATTEMPTS_PER_IP),
+            ]
+        };
+        for ip in 0..MAX_ATTEMPTS_PER_USERNAME {
+            assert!(limiter.admit(keys(ip), now).is_ok());
+        }
+        assert!(limiter.admit(keys(99), now).is_err());
+
+        // A rejected attempt isn't counted against the IP either
+        assert_eq!(limiter.attempts.get("login:ip:10.0.0.99"), None);
+        // Other usernames are unaffected
+        let other = vec![("login:user:bob".to_string(), MAX_ATTEMPTS_PER_USERNAME)];
+        assert!(limiter.admit(other, now).is_ok());
+    }
+
+    #[test]
+    fn lockout_doubles_with_each_failure_up_to_the_cap() {
+        let mut limiter = Limiter::default();
+        let now = Instant::now();
+        for _ in 1..LOCKOUT_THRESHOLD {
+            limiter.record_failure("alice".to_string(), now);
+        }
+        assert_eq!(limiter.locked_until("alice", now), None);
+
+        for expected in [30, 60, 120, 240, 480, 900, 900] {
+            limiter.record_failure("alice".to_string(), now);
+            assert_eq!(
+                limiter.locked_until("alice", now),
+                Some(now + Duration::from_secs(expected))
+            );
+        }
+        assert_eq!(limiter.locked_until("alice", now + MAX_LOCKOUT), None);
+        assert_eq!(limiter.locked_until("bob", now), None);
+    }
+
+    #[test]
+    fn prunes_what_no_longer_matters() {
+        let mut limiter = Limiter::default();
+        let start = Instant::now();
+        limiter.admit(ip_key(), start).unwrap();
+        limiter.record_failure("typo".to_string(), start);
+        for _ in 0..LOCKOUT_THRESHOLD {
+            limiter.record_failure("locked".to_string(), start);
+        }
+
+        // Still inside the window: everything is kept
+        limiter.prune(start + WINDOW);
+        assert_eq!(limiter.attempts.len(), 1);
+        assert_eq!(limiter.failures.len(), 2);
+
+        // Past the window, only the locked account's streak survives, so the
+        // next failure still doubles its lockout
+        let later = start + WINDOW + Duration::from_secs(1);
+        limiter.prune(later);
+        assert!(limiter.attempts.is_empty());
+        assert!(limiter.failures.contains_key("locked"));
+        assert!(!limiter.failures.contains_key("typo"));
+
+        limiter.prune(start + BASE_LOCKOUT + WINDOW + Duration::from_secs(1));
+        assert!(limiter.failures.is_empty());
+    }
+
+    #[test]
+    fn trusts_only_the_hop_our_proxy_added() {
+        let client = |values: &[&str]| forwarded_client(values.iter().copied());
+        let proxy_added: IpAddr = "203.0.113.7".parse().unwrap();
+
+        assert_eq!(client(&["203.0.113.7"]), Some(proxy_added));
+        // Whatever the client sent comes first and is ignored
+        assert_eq!(client(&["1.2.3.4, 203.0.113.7"]), Some(proxy_added));
+        assert_eq!(
+            client(&["1.2.3.4", "5.6.7.8,203.0.113.7"]),
+            Some(proxy_added)
+        );
+        assert_eq!(client(&["1.2.3.4, 203.0.113.7, "]), Some(proxy_added));
+        assert_eq!(client(&["203.0.113.7, not-an-ip"]), None);
+        assert_eq!(client(&[]), None);
+    }
+}
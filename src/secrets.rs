@@ -0,0 +1,154 @@
+// Random tokens, token hashing, and encryption of stored secrets.
+//
+// Tokens are shown to the user once and only their SHA-256 hash is kept in
+// the database, so a leaked table can't be used to redeem them.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+// 32 random bytes, hex encoded
+pub fn generate_token() -> String {
+    hex::encode(random_bytes(32))
+}
+
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+// Encryption for secrets we must be able to read back (TOTP seeds).
+// AES-256-GCM keyed by `DATA_ENCRYPTION_KEY` (64 hex characters); stored as
+// "<nonce hex>:<ciphertext hex>".
+pub fn encrypt(plaintext: &[u8]) -> Result<String, String> {
+    encrypt_with(&cipher()?, plaintext)
+}
+
+pub fn decrypt(stored: &str) -> Result<Vec<u8>, String> {
+    decrypt_with(&cipher()?, stored)
+}
+
+fn encrypt_with(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<String, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "encryption failed".to_string())?;
+    Ok(format!(
+        "{}:{}",
+        hex::encode(nonce),
+        hex::encode(ciphertext)
+    ))
+}
+
+fn decrypt_with(cipher: &Aes256Gcm, stored: &str) -> Result<Vec<u8>, String> {
+    let (nonce, ciphertext) = stored
+        .split_once(':')
+        .ok_or_else(|| "malformed encrypted value".to_string())?;
+    let nonce = hex::decode(nonce).map_err(|e| e.to_string())?;
+    let ciphertext = hex::decode(ciphertext).map_err(|e| e.to_string())?;
+    if nonce.len() != 12 {
+        return Err("malformed encrypted value".to_string());
+    }
+    cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| "decryption failed (wrong DATA_ENCRYPTION_KEY?)".to_string())
+}
+
+fn cipher() -> Result<Aes256Gcm, String> {
+    let key = std::env::var("DATA_ENCRYPTION_KEY")
+        .map_err(|_| "DATA_ENCRYPTION_KEY is not set".to_string())?;
+    cipher_from_hex(&key)
+}
+
+fn cipher_from_hex(key: &str) -> Result<Aes256Gcm, String> {
+    let key = hex::decode(key.trim()).map_err(|_| "DATA_ENCRYPTION_KEY must be hex".to_string())?;
+    if key.len() != 32 {
+        return Err("DATA_ENCRYPTION_KEY must be 32 bytes (64 hex characters)".to_string());
+    }
+    Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_random_hex() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn token_hash_is_sha256_hex() {
+        // SHA-256("abc"), FIPS 180-2 example
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Pasted links often bring whitespace along
+        assert_eq!(hash_token(" abc\n"), hash_token("abc"));
+        assert_ne!(hash_token("abd"), hash_token("abc"));
+    }
+
+    fn test_cipher(fill: char) -> Aes256Gcm {
+        cipher_from_hex(&fill.to_string().repeat(64)).unwrap()
+    }
+
+    #[test]
+    fn encryption_round_trips() {
+        let cipher = test_cipher('a');
+        let stored = encrypt_with(&cipher, b"totp seed").unwrap();
+        assert_eq!(decrypt_with(&cipher, &stored).unwrap(), b"totp seed");
+        // A fresh nonce every time
+        assert_ne!(encrypt_with(&cipher, b"totp seed").unwrap(), stored);
+        assert!(!stored.contains(&hex::encode(b"totp seed")));
+    }
+
+    #[test]
+    fn tampered_values_are_rejected() {
+        let cipher = test_cipher('a');
+        let stored = encrypt_with(&cipher, b"totp seed").unwrap();
+        let (nonce, ciphertext) = stored.split_once(':').unwrap();
+
+        let mut flipped = hex::decode(ciphertext).unwrap();
+        flipped[0] ^= 1;
+        let tampered = format!("{}:{}", nonce, hex::encode(flipped));
+        assert!(decrypt_with(&cipher, &tampered).is_err());
+
+        let other_nonce = format!("{}:{}", "00".repeat(12), ciphertext);
+        assert!(decrypt_with(&cipher, &other_nonce).is_err());
+
+        let truncated = &stored[..stored.len() - 2];
+        assert!(decrypt_with(&cipher, truncated).is_err());
+    }
+
+    #[test]
+    fn the_wrong_key_is_rejected() {
+        let stored = encrypt_with(&test_cipher('a'), b"totp seed").unwrap();
+        assert_eq!(
+            decrypt_with(&test_cipher('b'), &stored),
+            Err("decryption failed (wrong DATA_ENCRYPTION_KEY?)".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_values_and_keys_are_rejected() {
+        let cipher = test_cipher('a');
+        for stored in ["", "no-separator", "zz:00", "0011:2233"] {
+            assert!(decrypt_with(&cipher, stored).is_err(), "{}", stored);
+        }
+        assert!(cipher_from_hex("not hex").is_err());
+        assert!(cipher_from_hex(&"a".repeat(62)).is_err());
+        assert!(cipher_from_hex(&format!(" {} ", "a".repeat(64))).is_ok());
+    }
+}
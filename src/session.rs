@@ -0,0 +1,127 @@
+// Server-side login sessions, carried in an HttpOnly cookie.
+//
+// Server functions identify the user by the `username` argument the client
+// sends. For accounts with two-factor authentication that is not enough, so
+// completing the second factor (or an SSO login) starts a session here and
+// `get_user_id` refuses those accounts unless the request carries a matching
+// session.
+// Like reset tokens, only the SHA-256 hash of the session token is stored.
+
+use axum::http::{header, HeaderValue};
+use dioxus::prelude::*;
+use sqlx::PgPool;
+
+use crate::backend::server_utils::sqlx_err_to_server_err;
+use crate::secrets;
+
+const COOKIE_NAME: &str = "todo_session";
+const SESSION_TTL_DAYS: i32 = 30;
+
+// Store a new session for `user_id` and send its cookie with the response
+pub async fn start(pool: &PgPool, user_id: i32) -> Result<(), ServerFnError> {
+    let token = secrets::generate_token();
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (user_id, token_hash, expires_at)
+        VALUES ($1, $2, now() + make_interval(days => $3))
+        "#,
+    )
+    .bind(user_id)
+    .bind(secrets::hash_token(&token))
+    .bind(SESSION_TTL_DAYS)
+    .execute(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)?;
+
+    // Drop expired sessions while we're here
+    sqlx::query("DELETE FROM sessions WHERE expires_at < now()")
+        .execute(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)?;
+
+    set_cookie(&token, SESSION_TTL_DAYS as i64 * 24 * 60 * 60);
+    tracing::info!("Session started");
+    Ok(())
+}
+
+// The user the request's session cookie belongs to, if it is still valid
+pub async fn current_user(pool: &PgPool) -> Result<Option<i32>, ServerFnError> {
+    let Some(token) = request_token() else {
+        return Ok(None);
+    };
+    sqlx::query_scalar("SELECT user_id FROM sessions WHERE token_hash = $1 AND expires_at > now()")
+        .bind(secrets::hash_token(&token))
+        .fetch_optional(pool)
+        .await
+        .map_err(sqlx_err_to_server_err)
+}
+
+// Like `current_user`, but only if the session was started in the last
+// `minutes`, for changes that need a fresh login
+pub async fn recent_user(pool: &PgPool, minutes: i32) -> Result<Option<i32>, ServerFnError> {
+    let Some(token) = request_token() else {
+        return Ok(None);
+    };
+    sqlx::query_scalar(
+        r#"
+        SELECT user_id FROM sessions
+        WHERE token_hash = $1 AND expires_at > now()
+            AND created_at > now() - make_interval(mins => $2)
+        "#,
+    )
+    .bind(secrets::hash_token(&token))
+    .bind(minutes)
+    .fetch_optional(pool)
+    .await
+    .map_err(sqlx_err_to_server_err)
+}
+
+// Forget the request's session and clear its cookie
+pub async fn end(pool: &PgPool) -> Result<(), ServerFnError> {
+    if let Some(token) = request_token() {
+        sqlx::query("DELETE FROM sessions WHERE token_hash = $1")
+            .bind(secrets::hash_token(&token))
+            .execute(pool)
+            .await
+            .map_err(sqlx_err_to_server_err)?;
+    }
+    set_cookie("", 0);
+    Ok(())
+}
+
+fn request_token() -> Option<String> {
+    let context = server_context();
+    let parts = context.request_parts();
+    parts
+        .headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == COOKIE_NAME)
+        .map(|(_, token)| token.to_string())
+        .filter(|token| !token.is_empty())
+}
+
+fn set_cookie(token: &str, max_age_secs: i64) {
+    // Only mark the cookie Secure when the app is served over HTTPS, so plain
+    // http://localhost development keeps working
+    let secure = std::env::var("APP_BASE_URL").is_ok_and(|url| url.starts_with("https://"));
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        COOKIE_NAME,
+        token,
+        max_age_secs,
+        if secure { "; Secure" } else { "" }
+    );
+    match HeaderValue::from_str(&cookie) {
+        Ok(value) => {
+            server_context()
+                .response_parts_mut()
+                .headers
+                .append(header::SET_COOKIE, value);
+        }
+        Err(e) => tracing::error!(error = %e, "Invalid session cookie"),
+    }
+}
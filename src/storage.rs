@@ -0,0 +1,385 @@
+// Blob storage for attachments behind a small trait so the backend can be
+// swapped.
+//
+// `ATTACHMENT_STORAGE` picks the implementation:
+// - `local` (default): files under `ATTACHMENT_DIR` (default `attachments`)
+// - `s3`: an S3-compatible bucket `S3_BUCKET` at `S3_ENDPOINT` (e.g.
+//   `https://s3.eu-west-1.amazonaws.com` or a local MinIO), signed with
+//   `S3_ACCESS_KEY_ID`/`S3_SECRET_ACCESS_KEY` for `S3_REGION` (default
+//   `us-east-1`). Objects are addressed path-style.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+pub type StorageError = Box<dyn std::error::Error + Send + Sync>;
+
+// Keys are generated by the server (see `attachments`), never taken from
+// user input, and only use [A-Za-z0-9._/-]
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), StorageError>;
+    // None if there is no such blob
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+    // Deleting a missing blob is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalStore { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StorageError> {
+        if key
+            .split('/')
+            .any(|part| part.is_empty() || part.starts_with('.'))
+        {
+            return Err(format!("invalid blob key {:?}", key).into());
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalStore {
+    async fn put(&self, key: &str, data: Vec<u8>, _content_type: &str) -> Result<(), StorageError> {
+        let path = self.path(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // Written to a temporary file first so readers never see half a blob
+        let temp_path = path.with_extension("tmp");
+        tokio::fs::write(&temp_path, data).await?;
+        tokio::fs::rename(&temp_path, &path).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub struct S3Store {
+    client: reqwest::Client,
+    endpoint: reqwest::Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Store {
+    pub fn from_env() -> Result<Self, StorageError> {
+        let var = |name: &str| std::env::var(name).map_err(|_| format!("{} is not set", name));
+        Ok(S3Store {
+            client: reqwest::Client::new(),
+            endpoint: var("S3_ENDPOINT")?.parse()?,
+            bucket: var("S3_BUCKET")?,
+            region: std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".into()),
+            access_key_id: var("S3_ACCESS_KEY_ID")?,
+            secret_access_key: var("S3_SECRET_ACCESS_KEY")?,
+        })
+    }
+
+    // A request for `key` signed with AWS Signature Version 4
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::RequestBuilder, StorageError> {
+        let path = format!("/{}/{}", self.bucket, key);
+        let url = self.endpoint.join(&path)?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let secret = format!("AWS4{}", self.secret_access_key);
+        let mut signing_key = hmac_sha256(secret.as_bytes(), date.as_bytes());
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = hmac_sha256(&signing_key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        );
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(reqwest::header::AUTHORIZATION, authorization)
+            .body(body))
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3Store {
+    async fn put(&self, key: &str, data: Vec<u8>, content_type: &str) -> Result<(), StorageError> {
+        self.request(reqwest::Method::PUT, key, data)?
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let response = self
+            .request(reqwest::Method::GET, key, Vec::new())?
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        // S3 answers 204 whether or not the object existed
+        self.request(reqwest::Method::DELETE, key, Vec::new())?
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// The configured store, built on first use
+pub fn blob_store() -> &'static dyn BlobStore {
+    static STORE: OnceLock<Box<dyn BlobStore>> = OnceLock::new();
+
+    STORE
+        .get_or_init(|| {
+            let backend = std::env::var("ATTACHMENT_STORAGE").unwrap_or_else(|_| "local".into());
+            tracing::info!(%backend, "Initializing attachment storage");
+            let local = || -> Box<dyn BlobStore> {
+                Box::new(LocalStore::new(
+                    std::env::var("ATTACHMENT_DIR").unwrap_or_else(|_| "attachments".into()),
+                ))
+            };
+            match backend.as_str() {
+                "s3" => match S3Store::from_env() {
+                    Ok(store) => Box::new(store),
+                    Err(e) => {
+                        tracing::error!(error = %e, "S3 setup failed, storing attachments locally");
+                        local()
+                    }
+                },
+                _ => local(),
+            }
+        })
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Bytes,
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        routing::put,
+        Router,
+    };
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    type Objects = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    fn temp_root() -> PathBuf {
+        std::env::temp_dir().join(format!("todo-blobs-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn local_store_round_trip() {
+        let root = temp_root();
+        let store = LocalStore::new(&root);
+
+        store
+            .put("files/abc", b"hello".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get("files/abc").await.unwrap(),
+            Some(b"hello".to_vec())
+        );
+
+        store.delete("files/abc").await.unwrap();
+        assert_eq!(store.get("files/abc").await.unwrap(), None);
+        // Deleting again is fine
+        store.delete("files/abc").await.unwrap();
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn local_store_rejects_keys_leaving_the_root() {
+        let store = LocalStore::new(temp_root());
+        for key in [
+            "../secret",
+            "files/../../secret",
+            "/etc/passwd",
+            "files/.hidden",
+        ] {
+            assert!(store.get(key).await.is_err(), "{}", key);
+        }
+    }
+
+    // A stand-in for an S3 endpoint that checks each request is signed for
+    // the right key and payload, then stores objects in memory
+    async fn s3_stand_in(
+        State(objects): State<Objects>,
+        method: axum::http::Method,
+        Path((bucket, key)): Path<(String, String)>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> (StatusCode, Vec<u8>) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+        };
+        let signed = bucket == "todo"
+            && header("authorization").starts_with("AWS4-HMAC-SHA256 Credential=test-key/")
+            && header("authorization")
+                .contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date")
+            && header("x-amz-content-sha256") == hex::encode(Sha256::digest(&body))
+            && header("x-amz-date").len() == 16;
+        if !signed {
+            return (StatusCode::FORBIDDEN, Vec::new());
+        }
+
+        let mut objects = objects.lock().unwrap();
+        match method {
+            axum::http::Method::PUT => {
+                objects.insert(key, body.to_vec());
+                (StatusCode::OK, Vec::new())
+            }
+            axum::http::Method::GET => match objects.get(&key) {
+                Some(data) => (StatusCode::OK, data.clone()),
+                None => (StatusCode::NOT_FOUND, Vec::new()),
+            },
+            _ => {
+                objects.remove(&key);
+                (StatusCode::NO_CONTENT, Vec::new())
+            }
+        }
+    }
+
+    async fn start_s3_stand_in() -> (S3Store, Objects) {
+        let objects = Objects::default();
+        let app = Router::new()
+            .route(
+                "/:bucket/*key",
+                put(s3_stand_in).get(s3_stand_in).delete(s3_stand_in),
+            )
+            .with_state(objects.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let store = S3Store {
+            client: reqwest::Client::new(),
+            endpoint: format!("http://{}", address).parse().unwrap(),
+            bucket: "todo".to_string(),
+            region: "us-east-1".to_string(),
+            access_key_id: "test-key".to_string(),
+            secret_access_key: "test-secret".to_string(),
+        };
+        (store, objects)
+    }
+
+    #[tokio::test]
+    async fn s3_store_round_trip() {
+        let (store, objects) = start_s3_stand_in().await;
+
+        store
+            .put("files/abc", b"hello".to_vec(), "text/plain")
+            .await
+            .unwrap();
+        assert_eq!(
+            objects.lock().unwrap().get("files/abc"),
+            Some(&b"hello".to_vec())
+        );
+        assert_eq!(
+            store.get("files/abc").await.unwrap(),
+            Some(b"hello".to_vec())
+        );
+
+        store.delete("files/abc").await.unwrap();
+        assert_eq!(store.get("files/abc").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn s3_store_reports_refused_requests() {
+        let (mut store, _) = start_s3_stand_in().await;
+        store.bucket = "other".to_string();
+        assert!(store
+            .put("files/abc", Vec::new(), "text/plain")
+            .await
+            .is_err());
+        assert!(store.get("files/abc").await.is_err());
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        // Test case 2
+        assert_eq!(
+            hex::encode(hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
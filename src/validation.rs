@@ -0,0 +1,413 @@
+// Username, password and todo rules, compiled into both the client and the server.
+//
+// The `Register` form uses these for live feedback; `backend::register`
+// runs the same checks so a modified client can't bypass them.
+
+pub const USERNAME_MIN_LEN: usize = 3;
+pub const USERNAME_MAX_LEN: usize = 32;
+pub const PASSWORD_MIN_LEN: usize = 8;
+pub const PASSWORD_MAX_LEN: usize = 128;
+pub const EMAIL_MAX_LEN: usize = 254;
+#[cfg(any(feature = "server", feature = "offline", feature = "cli"))]
+pub const TODO_CONTENT_MAX_LEN: usize = 10_000;
+#[cfg(feature = "server")]
+pub const TODO_NOTES_MAX_LEN: usize = 50_000;
+#[cfg(feature = "server")]
+pub const COMMENT_MAX_LEN: usize = 5_000;
+pub const TAG_MAX_LEN: usize = 32;
+pub const LIST_NAME_MAX_LEN: usize = 64;
+pub const ATTACHMENT_MAX_BYTES: usize = 10 * 1024 * 1024;
+pub const ATTACHMENT_NAME_MAX_LEN: usize = 255;
+pub const ATTACHMENTS_PER_TODO: usize = 20;
+
+// Weakest password `register` accepts
+pub const MIN_PASSWORD_STRENGTH: PasswordStrength = PasswordStrength::Fair;
+
+// Rejected outright regardless of length or character mix
+const COMMON_PASSWORDS: &[&str] = &[
+    "password",
+    "password1",
+    "password123",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "qwertyuiop",
+    "qwerty123",
+    "iloveyou",
+    "letmein1",
+    "welcome1",
+    "admin123",
+    "abc12345",
+    "11111111",
+    "00000000",
+    "passw0rd",
+    "football",
+    "baseball",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PasswordStrength {
+    VeryWeak,
+    Weak,
+    Fair,
+    Strong,
+    VeryStrong,
+}
+
+impl PasswordStrength {
+    pub fn label(self) -> &'static str {
+        match self {
+            PasswordStrength::VeryWeak => "Very weak",
+            PasswordStrength::Weak => "Weak",
+            PasswordStrength::Fair => "Fair",
+            PasswordStrength::Strong => "Strong",
+            PasswordStrength::VeryStrong => "Very strong",
+        }
+    }
+
+    // Fill level for the strength meter, 0-100
+    pub fn percent(self) -> u8 {
+        match self {
+            PasswordStrength::VeryWeak => 10,
+            PasswordStrength::Weak => 30,
+            PasswordStrength::Fair => 55,
+            PasswordStrength::Strong => 80,
+            PasswordStrength::VeryStrong => 100,
+        }
+    }
+
+    // CSS class suffix for the meter colour
+    pub fn css_class(self) -> &'static str {
+        match self {
+            PasswordStrength::VeryWeak | PasswordStrength::Weak => "weak",
+            PasswordStrength::Fair => "fair",
+            PasswordStrength::Strong | PasswordStrength::VeryStrong => "strong",
+        }
+    }
+}
+
+// Canonical form of a username. Usernames are stored like this and compared
+// case-insensitively, so "Alice" and "alice" are the same account.
+#[cfg(feature = "server")]
+pub fn normalize_username(username: &str) -> String {
+    username.trim().to_string()
+}
+
+pub fn validate_username(username: &str) -> Result<(), String> {
+    let username = username.trim();
+    let len = username.chars().count();
+
+    if !(USERNAME_MIN_LEN..=USERNAME_MAX_LEN).contains(&len) {
+        return Err(format!(
+            "Username must be {} to {} characters long.",
+            USERNAME_MIN_LEN, USERNAME_MAX_LEN
+        ));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+    {
+        return Err("Username may only contain letters, digits, '_', '-' and '.'.".to_string());
+    }
+    if !username.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err("Username must start with a letter or digit.".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn password_strength(password: &str, username: &str) -> PasswordStrength {
+    let len = password.chars().count();
+    let lowered = password.to_lowercase();
+    let username = username.trim().to_lowercase();
+
+    if len < PASSWORD_MIN_LEN
+        || COMMON_PASSWORDS.contains(&lowered.as_str())
+        || (!username.is_empty() && lowered.contains(&username))
+        || password.chars().all(|c| password.starts_with(c))
+    {
+        return PasswordStrength::VeryWeak;
+    }
+
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_ascii_digit()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .iter()
+    .filter(|present| **present)
+    .count();
+
+    let length_points = match len {
+        0..=9 => 0,
+        10..=13 => 1,
+        14..=19 => 2,
+        _ => 3,
+    };
+    let class_points = classes.saturating_sub(1);
+
+    match length_points + class_points {
+        0 => PasswordStrength::Weak,
+        1 | 2 => PasswordStrength::Fair,
+        3 | 4 => PasswordStrength::Strong,
+        _ => PasswordStrength::VeryStrong,
+    }
+}
+
+pub fn validate_password(password: &str, username: &str) -> Result<(), String> {
+    let len = password.chars().count();
+
+    if len < PASSWORD_MIN_LEN {
+        return Err(format!(
+            "Password must be at least {} characters long.",
+            PASSWORD_MIN_LEN
+        ));
+    }
+    if len > PASSWORD_MAX_LEN {
+        return Err(format!(
+            "Password must be at most {} characters long.",
+            PASSWORD_MAX_LEN
+        ));
+    }
+    if password_strength(password, username) < MIN_PASSWORD_STRENGTH {
+        return Err(
+            "Password is too weak. Use a longer password or mix letters, digits and symbols, and avoid your username."
+                .to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+// Deliberately loose: the reset email itself is the real check
+pub fn validate_email(email: &str) -> Result<(), String> {
+    let email = email.trim();
+    let invalid = || Err("Please enter a valid email address.".to_string());
+
+    if email.is_empty() || email.len() > EMAIL_MAX_LEN || email.chars().any(char::is_whitespace) {
+        return invalid();
+    }
+    match email.split_once('@') {
+        Some((local, domain))
+            if !local.is_empty() && domain.contains('.') && !domain.contains('@') =>
+        {
+            Ok(())
+        }
+        _ => invalid(),
+    }
+}
+
+// Shared by the todo server functions and the REST API
+#[cfg(any(feature = "server", feature = "offline", feature = "cli"))]
+pub fn validate_todo_content(content: &str) -> Result<(), String> {
+    if content.trim().is_empty() {
+        return Err("Todo content cannot be empty".to_string());
+    }
+    if content.chars().count() > TODO_CONTENT_MAX_LEN {
+        return Err(format!(
+            "Todo content must be at most {} characters.",
+            TODO_CONTENT_MAX_LEN
+        ));
+    }
+    Ok(())
+}
+
+// Notes may be empty
+#[cfg(feature = "server")]
+pub fn validate_todo_notes(notes: &str) -> Result<(), String> {
+    if notes.chars().count() > TODO_NOTES_MAX_LEN {
+        return Err(format!(
+            "Notes must be at most {} characters.",
+            TODO_NOTES_MAX_LEN
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+pub fn validate_comment(body: &str) -> Result<(), String> {
+    if body.trim().is_empty() {
+        return Err("Comment cannot be empty".to_string());
+    }
+    if body.chars().count() > COMMENT_MAX_LEN {
+        return Err(format!(
+            "Comments must be at most {} characters.",
+            COMMENT_MAX_LEN
+        ));
+    }
+    Ok(())
+}
+
+// Size and name only; the server also checks what the file contains
+pub fn validate_attachment(file_name: &str, size: usize) -> Result<(), String> {
+    if file_name.trim().is_empty() || file_name.chars().count() > ATTACHMENT_NAME_MAX_LEN {
+        return Err(format!(
+            "File names must be 1 to {} characters.",
+            ATTACHMENT_NAME_MAX_LEN
+        ));
+    }
+    if size == 0 {
+        return Err(format!("{} is empty.", file_name));
+    }
+    if size > ATTACHMENT_MAX_BYTES {
+        return Err(format!(
+            "{} is larger than {} MB.",
+            file_name,
+            ATTACHMENT_MAX_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(())
+}
+
+// Tags are stored lowercase without a leading '#', so "#Work" and "work" match
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_lowercase()
+}
+
+// Expects a normalized tag
+pub fn validate_tag(tag: &str) -> Result<(), String> {
+    if tag.is_empty() {
+        return Err("Tag cannot be empty".to_string());
+    }
+    if tag.chars().count() > TAG_MAX_LEN {
+        return Err(format!("Tags must be at most {} characters.", TAG_MAX_LEN));
+    }
+    if !tag
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Tags may only contain letters, digits, '-' and '_'.".to_string());
+    }
+    Ok(())
+}
+
+// List names keep their case for display but are unique case-insensitively
+pub fn validate_list_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("List name cannot be empty".to_string());
+    }
+    if name.trim().chars().count() > LIST_NAME_MAX_LEN {
+        return Err(format!(
+            "List names must be at most {} characters.",
+            LIST_NAME_MAX_LEN
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PasswordStrength::*;
+
+    #[test]
+    fn username_length_and_charset() {
+        let cases = [
+            ("ab", false),
+            ("abc", true),
+            (&"a".repeat(USERNAME_MAX_LEN), true),
+            (&"a".repeat(USERNAME_MAX_LEN + 1), false),
+            ("  abc  ", true),
+            ("a.b-c_d", true),
+            ("42", false),
+            ("420", true),
+            ("_abc", false),
+            (".abc", false),
+            ("ab c", false),
+            ("abc!", false),
+            ("élan", false),
+            ("", false),
+        ];
+        for (username, valid) in cases {
+            assert_eq!(validate_username(username).is_ok(), valid, "{:?}", username);
+        }
+    }
+
+    #[test]
+    fn password_length_boundaries() {
+        let cases = [
+            ("Ab1!xyz", false),
+            ("Ab1!wxyz", true),
+            (&format!("Ab1!{}", "x".repeat(PASSWORD_MAX_LEN - 4)), true),
+            (&format!("Ab1!{}", "x".repeat(PASSWORD_MAX_LEN - 3)), false),
+        ];
+        for (password, valid) in cases {
+            assert_eq!(
+                validate_password(password, "alice").is_ok(),
+                valid,
+                "{} characters",
+                password.len()
+            );
+        }
+    }
+
+    #[test]
+    fn common_passwords_are_very_weak_in_any_case() {
+        for password in ["password123", "PASSWORD123", "PassW0rd", "Football"] {
+            assert_eq!(
+                password_strength(password, "alice"),
+                VeryWeak,
+                "{}",
+                password
+            );
+            assert!(validate_password(password, "alice").is_err());
+        }
+    }
+
+    #[test]
+    fn passwords_containing_the_username_are_very_weak() {
+        assert_eq!(password_strength("xx-Alice-2024!", "alice"), VeryWeak);
+        assert_eq!(password_strength("xx-alice-2024!", " ALICE "), VeryWeak);
+        assert!(validate_password("My-alice-Pass-99", "alice").is_err());
+        // An empty username doesn't match everything
+        assert_eq!(password_strength("xx-Alice-2024!", ""), VeryStrong);
+    }
+
+    #[test]
+    fn repeated_characters_are_very_weak() {
+        assert_eq!(password_strength("aaaaaaaaaaaaaaaaaaaa", "bob"), VeryWeak);
+        assert_eq!(password_strength("!!!!!!!!", "bob"), VeryWeak);
+        assert_eq!(password_strength("aaaaaaab", "bob"), Weak);
+    }
+
+    #[test]
+    fn strength_thresholds() {
+        let cases = [
+            // Length points: 8-9 => 0, 10-13 => 1, 14-19 => 2, 20+ => 3.
+            // Class points: one per character class beyond the first.
+            ("abcdefgh", Weak),
+            ("abcdefg1", Fair),
+            ("abcdefghij", Fair),
+            ("abcdefghijklmn", Fair),
+            ("Abcdefg1!", Strong),
+            ("abcdefghijklmnopqrst", Strong),
+            ("Abcdefghijklm1", Strong),
+            ("Abcdefghijklmn1!", VeryStrong),
+            ("Abcdefghijklmnopqrs1!", VeryStrong),
+        ];
+        for (password, expected) in cases {
+            assert_eq!(password_strength(password, "bob"), expected, "{}", password);
+        }
+    }
+
+    #[test]
+    fn register_requires_the_meter_to_show_fair() {
+        assert!(validate_password("abcdefgh", "bob").is_err());
+        assert!(validate_password("abcdefg1", "bob").is_ok());
+    }
+
+    #[test]
+    fn meter_fills_with_strength() {
+        let levels = [VeryWeak, Weak, Fair, Strong, VeryStrong];
+        for pair in levels.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(pair[0].percent() < pair[1].percent());
+        }
+        assert_eq!(
+            levels.map(PasswordStrength::css_class),
+            ["weak", "weak", "fair", "strong", "strong"]
+        );
+        assert_eq!(MIN_PASSWORD_STRENGTH.label(), "Fair");
+    }
+}
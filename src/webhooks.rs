@@ -0,0 +1,535 @@
+// Sends queued webhook deliveries (see `backend::webhooks`).
+//
+// Every POST carries the event in `X-Todo-Event`, the delivery id in
+// `X-Todo-Delivery`, a Unix timestamp in `X-Todo-Timestamp` and
+// `X-Todo-Signature: sha256=<hex>`, the HMAC-SHA256 of "<timestamp>.<body>"
+// with the webhook's secret. Any 2xx answer counts as delivered. Anything
+// else is retried with exponential backoff, up to MAX_ATTEMPTS tries.
+//
+// Receivers must be public addresses: URLs that resolve to loopback, private
+// or link-local addresses are refused, and redirects aren't followed, so a
+// webhook can't reach into our own network. Set
+// `WEBHOOK_ALLOW_PRIVATE_URLS=true` to test against a local receiver.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use crate::backend::server_utils::get_db_pool;
+use crate::secrets;
+
+const MAX_ATTEMPTS: i32 = 8;
+// The first retry waits this long, and each one after twice as long
+const FIRST_RETRY_DELAY_SECS: i64 = 30;
+const MAX_RETRY_DELAY_SECS: i64 = 60 * 60;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Deliveries claimed at once. A claim expires after CLAIM_SECS so another
+// instance picks the delivery up if this one dies mid-batch.
+const BATCH_SIZE: i64 = 20;
+const CLAIM_SECS: i64 = 5 * 60;
+// Finished deliveries are dropped from the log after this many days
+const LOG_RETENTION_DAYS: i32 = 30;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// Kept of an error message or response body in the log
+const ERROR_MAX_LEN: usize = 500;
+
+// One claimed delivery, with its webhook's URL and encrypted secret
+#[derive(sqlx::FromRow)]
+struct Claimed {
+    id: i64,
+    event: String,
+    payload: String,
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+// What one attempt got back: the HTTP status if the receiver answered, and
+// what went wrong unless it was a 2xx
+struct Attempt {
+    response_status: Option<i32>,
+    error: Option<String>,
+}
+
+pub async fn deliver_pending() {
+    let mut next_prune = Instant::now();
+    loop {
+        if Instant::now() >= next_prune {
+            if let Err(e) = prune_log().await {
+                tracing::warn!(error = %e, "Pruning the webhook delivery log failed");
+            }
+            next_prune = Instant::now() + PRUNE_INTERVAL;
+        }
+        match deliver_batch().await {
+            // A full batch means there may be more due right away
+            Ok(sent) if sent as i64 == BATCH_SIZE => continue,
+            Ok(_) => {}
+            Err(e) => tracing::warn!(error = %e, "Sending webhook deliveries failed"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn deliver_batch() -> Result<usize, sqlx::Error> {
+    let pool = get_db_pool().await?;
+    let claimed: Vec<Claimed> = sqlx::query_as(
+        r#"
+        UPDATE webhook_deliveries d
+        SET next_attempt_at = now() + make_interval(secs => $2)
+        FROM webhooks w
+        WHERE w.id = d.webhook_id AND d.id IN (
+            SELECT id FROM webhook_deliveries
+            WHERE status = 'pending' AND next_attempt_at <= now()
+            ORDER BY next_attempt_at, id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING d.id, d.event, d.payload, d.attempts, w.url, w.secret
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .bind(CLAIM_SECS as f64)
+    .fetch_all(pool)
+    .await?;
+
+    let count = claimed.len();
+    for delivery in claimed {
+        let attempt = attempt(&delivery).await;
+        let attempts = delivery.attempts + 1;
+        let delivered = attempt.error.is_none();
+        let status = match (delivered, attempts >= MAX_ATTEMPTS) {
+            (true, _) => "delivered",
+            (false, true) => "failed",
+            (false, false) => "pending",
+        };
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $2, attempts = $3, response_status = $4, last_error = $5,
+                next_attempt_at = now() + make_interval(secs => $6),
+                finished_at = CASE WHEN $2 = 'pending' THEN NULL ELSE now() END
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery.id)
+        .bind(status)
+        .bind(attempts)
+        .bind(attempt.response_status)
+        .bind(&attempt.error)
+        .bind(retry_delay_secs(attempts) as f64)
+        .execute(pool)
+        .await?;
+
+        match &attempt.error {
+            None => tracing::debug!(delivery_id = delivery.id, "Webhook delivered"),
+            Some(error) => tracing::info!(
+                delivery_id = delivery.id,
+                attempts,
+                status,
+                error = %error,
+                "Webhook delivery failed"
+            ),
+        }
+    }
+    Ok(count)
+}
+
+// How long to wait after the `attempts`th failed try
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let doublings = (attempts - 1).clamp(0, 20) as u32;
+    (FIRST_RETRY_DELAY_SECS << doublings).min(MAX_RETRY_DELAY_SECS)
+}
+
+async fn attempt(delivery: &Claimed) -> Attempt {
+    let failed = |error: String| Attempt {
+        response_status: None,
+        error: Some(error),
+    };
+    let secret = match secrets::decrypt(&delivery.secret) {
+        Ok(secret) => secret,
+        Err(e) => {
+            tracing::error!(error = %e, "Secret decryption error");
+            return failed("The webhook secret could not be read on the server".to_string());
+        }
+    };
+    let client = match client_for(&delivery.url, allow_private_urls()).await {
+        Ok(client) => client,
+        Err(e) => return failed(e),
+    };
+    post(&client, delivery, &secret).await
+}
+
+// Sends one signed POST of the delivery and says how it went
+async fn post(client: &reqwest::Client, delivery: &Claimed, secret: &[u8]) -> Attempt {
+    let timestamp = Utc::now().timestamp();
+    let signature = sign(secret, timestamp, &delivery.payload);
+    let response = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Todo-Event", &delivery.event)
+        .header("X-Todo-Delivery", delivery.id.to_string())
+        .header("X-Todo-Timestamp", timestamp.to_string())
+        .header("X-Todo-Signature", format!("sha256={}", signature))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match response {
+        Ok(response) if response.status().is_success() => Attempt {
+            response_status: Some(response.status().as_u16() as i32),
+            error: None,
+        },
+        Ok(mut response) => {
+            let status = response.status();
+            // The start of the body is enough to say what went wrong
+            let body = match response.chunk().await {
+                Ok(Some(chunk)) => String::from_utf8_lossy(&chunk).into_owned(),
+                _ => String::new(),
+            };
+            Attempt {
+                response_status: Some(status.as_u16() as i32),
+                error: Some(truncate(&format!("HTTP {}: {}", status, body.trim()))),
+            }
+        }
+        Err(e) => Attempt {
+            response_status: None,
+            error: Some(truncate(&e.to_string())),
+        },
+    }
+}
+
+// A client that connects to the address we checked, so the host can't
+// resolve somewhere else by the time the request is made
+async fn client_for(url: &str, allow_private: bool) -> Result<reqwest::Client, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "The URL has no host".to_string())?;
+    let port = parsed
+        .port_or_known_default()
+        .ok_or_else(|| "The URL has no port".to_string())?;
+    // IPv6 literals come bracketed
+    let address: SocketAddr = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .next()
+        .ok_or_else(|| format!("Could not resolve {}", host))?;
+    if !is_public(address.ip()) && !allow_private {
+        return Err(format!(
+            "{} resolves to a private address ({})",
+            host,
+            address.ip()
+        ));
+    }
+
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent("todo-webhooks/1")
+        .resolve(host, address)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn allow_private_urls() -> bool {
+    std::env::var("WEBHOOK_ALLOW_PRIVATE_URLS").is_ok_and(|value| value == "true")
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // "This network", 0.0.0.0/8
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                // Benchmarking, 198.18.0.0/15
+                || (a == 198 && (18..20).contains(&b))
+                // Reserved, 240.0.0.0/4
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7, and link-local, fe80::/10
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+// Hex HMAC-SHA256 of "<timestamp>.<payload>"
+fn sign(secret: &[u8], timestamp: i64, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn truncate(message: &str) -> String {
+    match message.char_indices().nth(ERROR_MAX_LEN) {
+        Some((end, _)) => format!("{}...", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+async fn prune_log() -> Result<(), sqlx::Error> {
+    let pool = get_db_pool().await?;
+    let result = sqlx::query(
+        r#"
+        DELETE FROM webhook_deliveries
+        WHERE status <> 'pending' AND finished_at < now() - make_interval(days => $1)
+        "#,
+    )
+    .bind(LOG_RETENTION_DAYS)
+    .execute(pool)
+    .await?;
+    tracing::debug!(
+        deleted = result.rows_affected(),
+        "Webhook delivery log pruned"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        routing, Router,
+    };
+    use std::sync::{Arc, Mutex};
+
+    const SECRET: &[u8] = b"whsec_test";
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    // A stand-in receiver that checks the signature the way the docs tell
+    // receivers to, and keeps what it got
+    async fn receiver(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: String,
+    ) -> (StatusCode, &'static str) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string()
+        };
+        let timestamp: i64 = header("x-todo-timestamp").parse().unwrap_or(0);
+        let expected = format!("sha256={}", sign(SECRET, timestamp, &body));
+        let signed = header("x-todo-signature") == expected;
+        received.lock().unwrap().push((headers, body));
+        if signed {
+            (StatusCode::OK, "")
+        } else {
+            (StatusCode::UNAUTHORIZED, "bad signature")
+        }
+    }
+
+    async fn start_receiver() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/hook", routing::post(receiver))
+            .route(
+                "/broken",
+                routing::post(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "  boom  ") }),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}", address), received)
+    }
+
+    fn claimed(url: String) -> Claimed {
+        Claimed {
+            id: 7,
+            event: "todo.created".to_string(),
+            payload: r#"{"event":"todo.created"}"#.to_string(),
+            attempts: 0,
+            url,
+            secret: String::new(),
+        }
+    }
+
+    #[test]
+    fn signature_is_hmac_of_timestamp_and_body() {
+        // HMAC-SHA256("key", "1700000000.{}"), computed with openssl
+        assert_eq!(
+            sign(b"key", 1_700_000_000, "{}"),
+            "9d713ed406bb7076d4123f0dc2c39d2df5c654ed4b0cd56b52c8b4c940bd63ae"
+        );
+        assert_ne!(
+            sign(b"key", 1_700_000_001, "{}"),
+            sign(b"key", 1_700_000_000, "{}")
+        );
+        assert_ne!(
+            sign(b"other", 1_700_000_000, "{}"),
+            sign(b"key", 1_700_000_000, "{}")
+        );
+    }
+
+    #[test]
+    fn private_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "255.255.255.255",
+            "100.64.0.1",
+            "192.0.2.1",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "93.184.216.34",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "::ffff:1.1.1.1",
+        ] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn this_network_is_not_public() {
+        for ip in ["0.1.2.3", "0.255.255.255", "::ffff:0.0.0.1"] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(is_public("1.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn multicast_is_not_public() {
+        for ip in ["224.0.0.1", "239.255.255.250", "ff02::1", "ff0e::fb"] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        assert!(is_public("223.255.255.254".parse().unwrap()));
+    }
+
+    #[test]
+    fn benchmarking_addresses_are_not_public() {
+        for ip in ["198.18.0.1", "198.19.255.254"] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["198.17.255.254", "198.20.0.1"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn reserved_addresses_are_not_public() {
+        for ip in ["240.0.0.1", "250.1.2.3", "255.255.255.254"] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn retries_back_off_up_to_the_cap() {
+        assert_eq!(retry_delay_secs(1), FIRST_RETRY_DELAY_SECS);
+        assert_eq!(retry_delay_secs(2), FIRST_RETRY_DELAY_SECS * 2);
+        assert_eq!(retry_delay_secs(3), FIRST_RETRY_DELAY_SECS * 4);
+        assert_eq!(retry_delay_secs(100), MAX_RETRY_DELAY_SECS);
+    }
+
+    #[tokio::test]
+    async fn private_receivers_are_refused() {
+        let (url, received) = start_receiver().await;
+        let err = client_for(&format!("{}/hook", url), false)
+            .await
+            .unwrap_err();
+        assert!(err.contains("private address"), "{}", err);
+        assert!(received.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_requests() {
+        let (url, received) = start_receiver().await;
+        let delivery = claimed(format!("{}/hook", url));
+        let client = client_for(&delivery.url, true).await.unwrap();
+
+        let attempt = post(&client, &delivery, SECRET).await;
+        assert_eq!(attempt.response_status, Some(200));
+        assert_eq!(attempt.error, None);
+
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[0];
+        assert_eq!(body, &delivery.payload);
+        assert_eq!(headers["x-todo-event"], "todo.created");
+        assert_eq!(headers["x-todo-delivery"], "7");
+        assert_eq!(headers["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn records_refused_deliveries() {
+        let (url, _) = start_receiver().await;
+
+        // Signed with the wrong secret
+        let delivery = claimed(format!("{}/hook", url));
+        let client = client_for(&delivery.url, true).await.unwrap();
+        let attempt = post(&client, &delivery, b"wrong").await;
+        assert_eq!(attempt.response_status, Some(401));
+        assert_eq!(
+            attempt.error.as_deref(),
+            Some("HTTP 401 Unauthorized: bad signature")
+        );
+
+        let delivery = claimed(format!("{}/broken", url));
+        let attempt = post(&client, &delivery, SECRET).await;
+        assert_eq!(attempt.response_status, Some(500));
+        assert_eq!(
+            attempt.error.as_deref(),
+            Some("HTTP 500 Internal Server Error: boom")
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_receivers_fail_without_a_status() {
+        // Bound, then dropped, so nothing is listening
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        drop(listener);
+
+        let delivery = claimed(url);
+        let client = client_for(&delivery.url, true).await.unwrap();
+        let attempt = post(&client, &delivery, SECRET).await;
+        assert_eq!(attempt.response_status, None);
+        assert!(attempt.error.is_some());
+    }
+
+    #[test]
+    fn long_errors_are_truncated() {
+        let long = "é".repeat(ERROR_MAX_LEN + 10);
+        let truncated = truncate(&long);
+        assert_eq!(truncated.chars().count(), ERROR_MAX_LEN + 3);
+        assert!(truncated.ends_with("..."));
+        assert_eq!(truncate("short"), "short");
+    }
+}